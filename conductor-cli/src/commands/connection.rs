@@ -0,0 +1,51 @@
+use crate::opts::{Connection, Impair};
+use anyhow::{bail, Result};
+use conductor::network_impairment::NetworkImpairment;
+use conductor_config::NetworkImpairmentConfig;
+
+pub async fn handle(c: Connection) -> Result<()> {
+    match c {
+        Connection::Impair(Impair {
+            system,
+            connection_name,
+            delay,
+            jitter,
+            loss,
+            duplicate,
+            reorder,
+            corrupt,
+            rate,
+            clear,
+        }) => {
+            let cfg = NetworkImpairmentConfig {
+                delay,
+                jitter,
+                loss,
+                duplicate,
+                reorder,
+                corrupt,
+                rate,
+            };
+            let impairment = NetworkImpairment::try_from(&cfg)?;
+            if impairment.is_empty() && !clear {
+                bail!("No impairment settings provided, use --clear to remove impairments");
+            }
+
+            let system = system.resolve_system().await?;
+            system
+                .impair_connection(&connection_name, &impairment)
+                .await?;
+
+            if impairment.is_empty() {
+                println!("cleared impairments on connection '{connection_name}'");
+            } else {
+                println!(
+                    "impaired connection '{connection_name}': {}",
+                    impairment.netem_args().join(" ")
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use crate::opts::Fault;
+use anyhow::Result;
+use conductor_config::FaultConfig;
+
+pub async fn handle(f: Fault) -> Result<()> {
+    let Fault {
+        common,
+        kind,
+        target,
+        interface,
+        pin,
+        value,
+        percentage,
+    } = f;
+    let fault = conductor::fault::Fault::from_config(FaultConfig {
+        fault: kind,
+        target,
+        interface,
+        pin,
+        value,
+        percentage,
+    })?;
+
+    let system = common.resolve_system().await?;
+    system.inject_fault(&fault).await?;
+    println!("injected fault: {fault}");
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use crate::opts::{BuildImages, Images};
+use anyhow::Result;
+use conductor::{containers::Container, images::StockImage};
+
+pub async fn handle(i: Images) -> Result<()> {
+    match i {
+        Images::Build(BuildImages { provider }) => {
+            let images = match provider {
+                Some(p) => vec![p],
+                None => StockImage::ALL.to_vec(),
+            };
+            for image in images {
+                println!("Building the {image} image '{}'", image.local_image());
+                let mut container = Container::from_internal_image(image).resolve().await?;
+                container.build_image().await?;
+
+                // Also stand in for the published image, so systems can use it
+                // without pulling
+                container.tag_image(&image.remote_image(), "latest").await?;
+            }
+        }
+    }
+
+    Ok(())
+}
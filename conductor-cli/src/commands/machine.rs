@@ -1,9 +1,14 @@
-use crate::opts::{Attach, Dump, Inspect, List, Machine, Shell, Stats};
+use crate::opts::{Attach, Debug, Dump, Inspect, List, Machine, Shell, Stats};
 use crate::stats::ContainerAndStats;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use conductor::containers::{Container, LogOutput, StdIoChunk};
+use conductor::types::{ComponentName, ContainerRuntimeName, PublishedPort};
+use conductor::{Component, DeploymentContainer};
 use futures_util::StreamExt;
 use std::io::{self, Write};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
 use tabwriter::TabWriter;
 use tokio::io::AsyncWriteExt;
 
@@ -12,8 +17,41 @@ pub async fn handle(s: Machine) -> Result<()> {
         Machine::List(List { .. }) => {
             todo!("machine list");
         }
-        Machine::Inspect(Inspect { .. }) => {
-            todo!("machine inspect");
+        Machine::Inspect(Inspect {
+            system,
+            machine_name,
+        }) => {
+            let system = system.resolve_system().await?;
+            let machine = system
+                .config()
+                .machines
+                .iter()
+                .find(|m| m.base.name == machine_name)
+                .ok_or_else(|| {
+                    anyhow!("The system doesn't have a machine named '{machine_name}'")
+                })?;
+            let component_name = machine.name();
+
+            let deployment = system.deployment()?;
+            let (container_name, ports) =
+                find_container_ports(&deployment.renode_containers, &component_name)
+                    .or_else(|| find_container_ports(&deployment.qemu_containers, &component_name))
+                    .or_else(|| {
+                        find_container_ports(&deployment.container_containers, &component_name)
+                    })
+                    .ok_or_else(|| {
+                        anyhow!("Machine '{machine_name}' isn't part of the deployment")
+                    })?;
+
+            let mut tw = TabWriter::new(io::stdout());
+            writeln!(tw, "NAME\t{machine_name}")?;
+            writeln!(tw, "PROVIDER\t{}", machine.provider())?;
+            writeln!(tw, "CONTAINER\t{container_name}")?;
+            for p in ports.iter() {
+                // Containers use the host's network, the port is the host's as-is
+                writeln!(tw, "PORT\t{p}")?;
+            }
+            tw.flush()?;
         }
         Machine::Attach(Attach {
             system,
@@ -79,11 +117,81 @@ pub async fn handle(s: Machine) -> Result<()> {
             // TODO: attach to newly exec'd thing
             shell_for_container(container).await?;
         }
+        Machine::Debug(Debug {
+            system,
+            gdb,
+            machine_name,
+        }) => {
+            let system = system.resolve_system().await?;
+            let machine = system
+                .config()
+                .machines
+                .iter()
+                .find(|m| m.base.name == machine_name)
+                .ok_or_else(|| {
+                    anyhow!("The system doesn't have a machine named '{machine_name}'")
+                })?;
+            let gdb_server = machine.base.gdb_server.as_ref().ok_or_else(|| {
+                anyhow!("Machine '{machine_name}' doesn't have a gdb-server configured")
+            })?;
+
+            let mut args = Vec::new();
+            if let Some(bin) = &machine.base.bin {
+                args.push(bin.display().to_string());
+            }
+            args.push("-ex".to_owned());
+            args.push(format!("target remote localhost:{}", gdb_server.port));
+            for cmd in gdb_server.init_commands.iter() {
+                args.push("-ex".to_owned());
+                args.push(cmd.clone());
+            }
+
+            exec_gdb(gdb, &args)?;
+        }
     }
 
     Ok(())
 }
 
+/// Replaces this process with gdb so it owns the terminal, including Ctrl-C
+fn exec_gdb(gdb: Option<PathBuf>, args: &[String]) -> Result<()> {
+    const DEFAULT_GDBS: &[&str] = &["gdb-multiarch", "gdb"];
+
+    let candidates = match gdb {
+        Some(gdb) => vec![gdb],
+        None => DEFAULT_GDBS.iter().map(PathBuf::from).collect(),
+    };
+    for gdb in candidates.iter() {
+        // exec only returns on failure
+        let err = Command::new(gdb).args(args).exec();
+        if err.kind() != io::ErrorKind::NotFound {
+            return Err(anyhow!("Failed to run '{}': {err}", gdb.display()));
+        }
+    }
+    Err(anyhow!(
+        "Could not find {}, use --gdb to specify the gdb executable",
+        candidates
+            .iter()
+            .map(|g| g.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" or ")
+    ))
+}
+
+fn find_container_ports<C>(
+    containers: &[DeploymentContainer<C>],
+    component_name: &ComponentName,
+) -> Option<(ContainerRuntimeName, Vec<PublishedPort>)> {
+    containers
+        .iter()
+        .find(|c| {
+            ContainerRuntimeName::extract_components(c.name.as_str())
+                .map(|(_, comps)| comps.contains(component_name))
+                .unwrap_or(false)
+        })
+        .map(|c| (c.name.clone(), c.ports.iter().copied().collect()))
+}
+
 async fn attach_to_container(container: &Container) -> Result<()> {
     let io = container.attach().await?;
 
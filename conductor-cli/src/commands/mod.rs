@@ -1,2 +1,6 @@
+pub mod connection;
+pub mod fault;
+pub mod images;
 pub mod machine;
 pub mod system;
+pub mod test;
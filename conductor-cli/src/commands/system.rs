@@ -1,15 +1,21 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use std::borrow::Cow;
 use std::io::{self, Write};
 use std::{collections::BTreeMap, fs, path::Path};
 use tabwriter::TabWriter;
+use tokio::signal::unix::{signal, SignalKind};
 
 use crate::{
-    opts::{self, Build, Check, GraphFormat, Start, SystemStats, Watch},
+    logs::{container_label, SystemLogs},
+    opts::{
+        self, Build, Check, Display, GraphFormat, Pause, Resume, Start, Status, StatusFormat, Step,
+        Stop, SystemStats, Watch,
+    },
     stats::ContainerAndStats,
     tui::watch::WatchApp,
 };
-use conductor::types::ContainerRuntimeName;
+use conductor::system::SystemStatus;
+use conductor::types::{ComponentName, ContainerRuntimeName};
 use conductor::*;
 
 pub async fn handle(s: opts::System) -> Result<()> {
@@ -75,36 +81,59 @@ pub async fn handle(s: opts::System) -> Result<()> {
             system.build().await?;
             println!("system built");
         }
-        opts::System::Start(Start { common }) => {
+        opts::System::Start(Start {
+            common,
+            foreground,
+            abort_on_exit,
+        }) => {
             let mut system = common.resolve_system().await?;
             system.start().await?;
             println!("system started");
+            if foreground {
+                let logs = SystemLogs::follow(&system).await?;
+                let res = tokio::select! {
+                    res = system.supervise(abort_on_exit.as_ref()) => res,
+                    res = async {
+                        logs.print().await?;
+                        std::future::pending::<Result<Option<i64>>>().await
+                    } => res,
+                    res = shutdown_signal() => res.map(|()| None),
+                };
+
+                // Tear the system down however supervision ended
+                system.stop().await?;
+                system.remove().await?;
+                println!("system stopped");
+                if let Some(exit_code) = res? {
+                    std::process::exit(i32::try_from(exit_code).unwrap_or(1));
+                }
+            }
+        }
+        opts::System::Stop(Stop { common }) => {
+            let mut system = common.resolve_system().await?;
+            system.stop().await?;
+            system.remove().await?;
+            println!("system stopped");
+        }
+        opts::System::Status(Status { common, format }) => {
+            let system = common.resolve_system().await?;
+            let status = system.status().await?;
+            match format {
+                StatusFormat::Table => write_status_table(&status)?,
+                StatusFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+            }
+            if !status.healthy {
+                bail!("system '{}' is unhealthy", status.name);
+            }
         }
         opts::System::Stats(SystemStats { common }) => {
             let system = common.resolve_system().await?;
             let mut tw = TabWriter::new(io::stdout());
             writeln!(tw, "{}", ContainerAndStats::TABWRITER_HEADER)?;
             for container in system.containers() {
-                let (_system_name, component_names) = container
-                    .name()
-                    .and_then(ContainerRuntimeName::extract_components)
-                    .ok_or_else(|| {
-                        anyhow!("Failed to resolve the system component runtime container names")
-                    })?;
-
-                let name = if component_names.len() == 1 {
-                    component_names[0].to_string()
-                } else {
-                    format!(
-                        "[{}]",
-                        component_names
-                            .iter()
-                            .map(|c| c.as_str())
-                            .collect::<Vec<&str>>()
-                            .join(", ")
-                    )
-                };
-
+                let name = container.name().map(container_label).ok_or_else(|| {
+                    anyhow!("Failed to resolve the system component runtime container names")
+                })?;
                 let stats = ContainerAndStats::new(name, container.stats().await?);
                 stats.tabwriter_writeln(&mut tw)?;
             }
@@ -115,12 +144,144 @@ pub async fn handle(s: opts::System) -> Result<()> {
 
             WatchApp::new(system).run().await?;
         }
-        _ => todo!("system"),
+        opts::System::Display(Display {
+            common,
+            component_name,
+        }) => {
+            let system = common.resolve_system().await?;
+            let deployment = system.deployment()?;
+            let (uses_host_display, vnc) =
+                find_container_display(&deployment.gazebo_containers, &component_name)
+                    .or_else(|| {
+                        find_container_display(&deployment.renode_containers, &component_name)
+                    })
+                    .or_else(|| {
+                        find_container_display(&deployment.qemu_containers, &component_name)
+                    })
+                    .or_else(|| {
+                        find_container_display(&deployment.container_containers, &component_name)
+                    })
+                    .ok_or_else(|| {
+                        anyhow!("The system doesn't have a component named '{component_name}'")
+                    })?;
+            match vnc {
+                Some(vnc) => {
+                    println!("{}", vnc.vnc_url());
+                    if let Some(url) = vnc.novnc_url() {
+                        println!("{url}");
+                    }
+                }
+                None if uses_host_display => {
+                    println!("Component '{component_name}' uses the host display")
+                }
+                None => println!("Component '{component_name}' doesn't have a display"),
+            }
+        }
+        opts::System::Pause(Pause { common }) => {
+            let system = common.resolve_system().await?;
+            system.pause().await?;
+            println!("system paused");
+        }
+        opts::System::Resume(Resume { common }) => {
+            let system = common.resolve_system().await?;
+            system.resume().await?;
+            println!("system resumed");
+        }
+        opts::System::Step(Step { common, duration }) => {
+            let system = common.resolve_system().await?;
+            system.step(duration).await?;
+            println!("system stepped {duration:?}");
+        }
+        opts::System::Snapshot(snapshot) => match snapshot {
+            opts::Snapshot::Save { common, name } => {
+                let system = common.resolve_system().await?;
+                system.save_snapshot(&name).await?;
+                println!("saved snapshot '{name}'");
+            }
+            opts::Snapshot::Load { common, name } => {
+                let mut system = common.resolve_system().await?;
+                system.load_snapshot(&name).await?;
+                println!("loaded snapshot '{name}'");
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn write_status_table(status: &SystemStatus) -> Result<()> {
+    let mut tw = TabWriter::new(io::stdout());
+    writeln!(tw, "COMPONENT\tCONTAINER\tSTATE\tREADY\tRESTARTS\tHEALTHY")?;
+    for c in status.components.iter() {
+        let state = match (c.exit_code, c.oom_killed) {
+            (Some(code), true) => format!("{} ({code}, out of memory)", c.state),
+            (Some(code), false) => format!("{} ({code})", c.state),
+            (None, _) => c.state.to_string(),
+        };
+        writeln!(
+            tw,
+            "{}\t{}\t{state}\t{}\t{}\t{}",
+            c.name,
+            container_label(&c.container),
+            yes_no(c.ready),
+            c.restarts,
+            yes_no(c.healthy),
+        )?;
     }
+    if !status.connections.is_empty() {
+        writeln!(tw)?;
+        writeln!(tw, "CONNECTION\tKIND\tCOMPONENTS\tHEALTHY")?;
+        for c in status.connections.iter() {
+            let healthy = if c.healthy {
+                "yes".to_owned()
+            } else {
+                format!("no: {}", c.problems.join(", "))
+            };
+            writeln!(
+                tw,
+                "{}\t{}\t{}\t{healthy}",
+                c.name,
+                c.kind,
+                c.components.join(", "),
+            )?;
+        }
+    }
+    tw.flush()?;
+    Ok(())
+}
 
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Resolves on Ctrl-C or SIGTERM
+async fn shutdown_signal() -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = sigterm.recv() => (),
+    }
     Ok(())
 }
 
+fn find_container_display<C>(
+    containers: &[DeploymentContainer<C>],
+    component_name: &ComponentName,
+) -> Option<(bool, Option<conductor::display::VncDisplay>)> {
+    containers
+        .iter()
+        .find(|c| {
+            ContainerRuntimeName::extract_components(c.name.as_str())
+                .map(|(_, comps)| comps.contains(component_name))
+                .unwrap_or(false)
+        })
+        .map(|c| (c.uses_host_display, c.vnc))
+}
+
 fn gen_container_deployment_plan<P: AsRef<Path>, C>(
     root_dir: P,
     container_idx: usize,
@@ -140,12 +301,18 @@ fn gen_container_deployment_plan<P: AsRef<Path>, C>(
     let plan = serde_json::to_string_pretty(&serde_json::json!({
         "name" : *c.name,
         "uses_host_display" : c.uses_host_display,
+        "vnc_port": c.vnc.map(|v| v.port),
+        "novnc_port": c.vnc.and_then(|v| v.novnc_port),
         "environment_variables": *c.environment_variables,
         "assets": *c.assets,
         "command": c.command,
         "args": c.args,
         "networks": c.connections.iter().filter(|c| c.is_network()).map(|c| c.name().as_str()).collect::<Vec<&str>>(),
         "taps_to_bridges": c.taps_to_bridges.iter().map(|(t, b)| (t.as_str(), b.as_str())).collect::<BTreeMap<&str, &str>>(),
+        "tap_impairments": c.tap_impairments.iter().map(|(t, i)| (t.as_str(), i.netem_args().join(" "))).collect::<BTreeMap<&str, String>>(),
+        "bridge_impairments": c.bridge_impairments.iter().map(|(b, i)| (b.as_str(), i.netem_args().join(" "))).collect::<BTreeMap<&str, String>>(),
+        "can_interfaces": c.can_interfaces.iter().map(|(c, i)| (c.as_str(), i.as_str())).collect::<BTreeMap<&str, &str>>(),
+        "ports": c.ports.iter().map(|p| p.to_string()).collect::<Vec<String>>(),
     }))?;
     fs::write(plan_path, plan)?;
 
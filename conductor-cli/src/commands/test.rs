@@ -0,0 +1,80 @@
+use crate::opts::Test;
+use anyhow::{bail, Result};
+use conductor::scenario::{self, Scenario, StepOutcome};
+use std::fs;
+
+pub async fn handle(t: Test) -> Result<()> {
+    let Test {
+        common,
+        scenarios,
+        robot,
+        robot_output,
+        junit,
+        tap,
+    } = t;
+
+    // Parse every scenario up front, so a typo doesn't surface after a long run
+    let scenarios = scenarios
+        .iter()
+        .map(Scenario::read)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut system = common.resolve_system().await?;
+    let mut results = Vec::new();
+    for s in scenarios.iter() {
+        println!("scenario '{}'", s.name);
+        let result = scenario::run(&mut system, s).await;
+        for step in result.steps.iter() {
+            match &step.outcome {
+                StepOutcome::Passed => println!("  ok      {}", step.description),
+                StepOutcome::Failed(reason) => {
+                    println!("  FAILED  {}: {reason}", step.description)
+                }
+                StepOutcome::Skipped => println!("  skipped {}", step.description),
+            }
+        }
+        results.push(result);
+    }
+
+    if let Some(path) = junit {
+        fs::write(path, scenario::junit_xml(&results))?;
+    }
+    if let Some(path) = tap {
+        fs::write(path, scenario::tap(&results))?;
+    }
+
+    let mut failures = Vec::new();
+    if !results.is_empty() {
+        let failed = results.iter().filter(|r| !r.passed()).count();
+        println!(
+            "{} scenarios, {} passed, {failed} failed",
+            results.len(),
+            results.len() - failed
+        );
+        if failed != 0 {
+            failures.push(format!("{failed} scenarios failed"));
+        }
+    }
+
+    if !robot.is_empty() {
+        let robot_results = system.run_robot_suites(&robot, &robot_output).await?;
+        for r in robot_results.iter() {
+            let status = if r.passed() { "ok     " } else { "FAILED " };
+            println!(
+                "robot {status} '{}', output in {}",
+                r.container,
+                r.output_dir.display()
+            );
+        }
+        let failed = robot_results.iter().filter(|r| !r.passed()).count();
+        if failed != 0 {
+            failures.push(format!("Robot suites failed in {failed} containers"));
+        }
+    }
+
+    if !failures.is_empty() {
+        bail!("{}", failures.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use conductor::{containers::LogOutput, types::ContainerRuntimeName, System};
+use crossterm::style::{Color, Stylize};
+use futures_util::{stream, Stream, StreamExt};
+use std::io::{self, IsTerminal, Write};
+use std::pin::Pin;
+
+/// The colours the components' prefixes cycle through
+const PREFIX_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Green,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+type ContainerOutput = Pin<Box<dyn Stream<Item = Result<LogOutput>> + Send>>;
+
+/// The aggregated output of a system's containers, each line prefixed with the
+/// container's components
+pub struct SystemLogs {
+    prefixes: Vec<String>,
+    outputs: Vec<ContainerOutput>,
+}
+
+impl SystemLogs {
+    /// Follow every container's output, including the runs after a container is
+    /// restarted by the supervisor
+    pub async fn follow(system: &System) -> Result<Self> {
+        let mut prefixes = Vec::new();
+        let mut outputs = Vec::new();
+        for container in system.containers() {
+            let name = container.name().unwrap_or_default();
+            prefixes.push(container_label(name));
+            outputs.push(Box::pin(container.follow_output().await?) as ContainerOutput);
+        }
+        Ok(Self { prefixes, outputs })
+    }
+
+    /// Print the output as it arrives, until every container has been removed
+    pub async fn print(self) -> Result<()> {
+        let width = self.prefixes.iter().map(|p| p.len()).max().unwrap_or(0);
+        let colorize = io::stdout().is_terminal();
+        let prefixes: Vec<String> = self
+            .prefixes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let p = format!("{p:width$} |");
+                if colorize {
+                    p.with(PREFIX_COLORS[i % PREFIX_COLORS.len()]).to_string()
+                } else {
+                    p
+                }
+            })
+            .collect();
+
+        let mut partial_lines = vec![String::new(); prefixes.len()];
+        let mut outputs = stream::select_all(
+            self.outputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, output)| output.map(move |o| (i, o))),
+        );
+        while let Some((i, output)) = outputs.next().await {
+            let message = match output {
+                Ok(LogOutput::StdIn { message })
+                | Ok(LogOutput::StdOut { message })
+                | Ok(LogOutput::StdErr { message })
+                | Ok(LogOutput::Console { message }) => message,
+                Err(e) => {
+                    tracing::warn!(container = %self.prefixes[i], error = %e, "Failed to read container output");
+                    continue;
+                }
+            };
+            let partial_line = &mut partial_lines[i];
+            partial_line.push_str(&String::from_utf8_lossy(&message));
+            let mut stdout = io::stdout().lock();
+            while let Some(end) = partial_line.find('\n') {
+                let line: String = partial_line.drain(..=end).collect();
+                writeln!(stdout, "{} {}", prefixes[i], line.trim_end())?;
+            }
+            stdout.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A container's components, e.g. 'm0' or '[m0, m1]'
+pub fn container_label(container_name: &str) -> String {
+    let Some((_system_name, component_names)) =
+        ContainerRuntimeName::extract_components(container_name)
+    else {
+        return container_name.to_owned();
+    };
+    if component_names.len() == 1 {
+        component_names[0].to_string()
+    } else {
+        format!(
+            "[{}]",
+            component_names
+                .iter()
+                .map(|c| c.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )
+    }
+}
@@ -1,4 +1,5 @@
 mod commands;
+mod logs;
 mod opts;
 mod stats;
 mod tui;
@@ -19,5 +20,9 @@ async fn main() -> Result<()> {
     match args.command {
         Command::System(c) => commands::system::handle(c).await,
         Command::Machine(m) => commands::machine::handle(m).await,
+        Command::Connection(c) => commands::connection::handle(c).await,
+        Command::Images(i) => commands::images::handle(i).await,
+        Command::Test(t) => commands::test::handle(t).await,
+        Command::Fault(f) => commands::fault::handle(f).await,
     }
 }
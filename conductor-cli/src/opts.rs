@@ -1,6 +1,11 @@
 use clap::Parser;
-use conductor::types::MachineName;
-use std::{path::PathBuf, str::FromStr};
+use conductor::{
+    images::StockImage,
+    snapshot::SnapshotName,
+    types::{ComponentName, ConnectionName, MachineName},
+};
+use conductor_config::FaultKind;
+use std::{path::PathBuf, str::FromStr, time::Duration};
 
 pub fn parse_args() -> Args {
     Args::parse()
@@ -22,6 +27,12 @@ pub enum Command {
     System(System),
     #[command(subcommand)]
     Machine(Machine),
+    #[command(subcommand)]
+    Connection(Connection),
+    #[command(subcommand)]
+    Images(Images),
+    Test(Test),
+    Fault(Fault),
 }
 
 #[derive(Parser, Debug)]
@@ -30,10 +41,17 @@ pub enum System {
     Build(Build),
     Start(Start),
     Stop(Stop),
+    Status(Status),
     Stats(SystemStats),
     #[command(subcommand)]
     Export(Export),
     Watch(Watch),
+    Display(Display),
+    Pause(Pause),
+    Resume(Resume),
+    Step(Step),
+    #[command(subcommand)]
+    Snapshot(Snapshot),
 }
 
 /// Check a system
@@ -55,11 +73,38 @@ pub struct Build {
 pub struct Start {
     #[command(flatten)]
     pub common: CommonSystemOptions,
+
+    /// Keep running and supervise the system, printing its components' output and
+    /// restarting components that exit according to their restart policies.
+    /// Ctrl-C or SIGTERM stops and removes the system.
+    #[arg(long)]
+    pub foreground: bool,
+
+    /// Stop and remove the system when the machine exits, exiting with its exit code
+    #[arg(long, value_name = "MACHINE", requires = "foreground")]
+    pub abort_on_exit: Option<ComponentName>,
+}
+
+/// Tear down a system, stopping and removing its containers
+#[derive(Parser, Debug)]
+pub struct Stop {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
 }
 
-/// Tear down a system
+/// Show the health of each of the components and connections in a system.
+///
+/// Exits with a non-zero code when a component isn't running and ready, or a
+/// connection is missing one of its endpoints.
 #[derive(Parser, Debug)]
-pub struct Stop {}
+pub struct Status {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+
+    /// Output format to use
+    #[arg(short = 'f', long, default_value = "table")]
+    pub format: StatusFormat,
+}
 
 /// Show stats for each of the components in a system
 #[derive(Parser, Debug)]
@@ -110,6 +155,82 @@ pub struct Watch {
     pub common: CommonSystemOptions,
 }
 
+/// Show where to connect to a component's virtual display
+///
+/// Requires `display = "vnc"` in the global config.
+#[derive(Parser, Debug)]
+pub struct Display {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+
+    pub component_name: ComponentName,
+}
+
+/// Pause the simulated time of every component in a running system
+///
+/// Renode machines are paused through their monitor port and QEMU machines through
+/// their QMP server, when configured, otherwise their containers are frozen.
+#[derive(Parser, Debug)]
+pub struct Pause {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+}
+
+/// Resume the simulated time of every component in a paused system
+#[derive(Parser, Debug)]
+pub struct Resume {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+}
+
+/// Run a paused system for a duration, then pause it again
+///
+/// The duration is simulated time for Renode machines and Gazebo worlds, other
+/// components are run for the duration in wall-clock time.
+#[derive(Parser, Debug)]
+pub struct Step {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+
+    /// How long to run for, e.g. '100ms' or '2s'
+    #[arg(value_parser = parse_step_duration)]
+    pub duration: Duration,
+}
+
+fn parse_step_duration(s: &str) -> Result<Duration, String> {
+    conductor::time_control::parse_duration(s)
+        .ok_or_else(|| format!("Invalid duration '{s}', expected e.g. '100ms' or '2s'"))
+}
+
+/// Save or restore snapshots of a running system
+///
+/// Renode machines require a monitor port and QEMU machines a QMP server port.
+/// QEMU snapshots are stored in the machine's writable qcow2 storage. Container
+/// machines are committed to images, keeping only their filesystem. Systems with
+/// Gazebo worlds can't be snapshotted.
+#[derive(Parser, Debug)]
+pub enum Snapshot {
+    /// Save a snapshot of the running system
+    Save {
+        #[command(flatten)]
+        common: CommonSystemOptions,
+
+        /// Name of the snapshot, an existing snapshot of the same name is replaced
+        name: SnapshotName,
+    },
+
+    /// Restore the running system to a snapshot
+    ///
+    /// Snapshots taken before the system's configuration changed are refused.
+    Load {
+        #[command(flatten)]
+        common: CommonSystemOptions,
+
+        /// Name of the snapshot
+        name: SnapshotName,
+    },
+}
+
 #[derive(Parser, Debug)]
 pub enum Machine {
     List(List),
@@ -118,6 +239,7 @@ pub enum Machine {
     Stats(Stats),
     Dump(Dump),
     Shell(Shell),
+    Debug(Debug),
 }
 
 /// List machines
@@ -132,6 +254,8 @@ pub struct List {
 pub struct Inspect {
     #[command(flatten)]
     pub system: CommonSystemOptions,
+
+    pub machine_name: MachineName,
 }
 
 /// Attach to a running machine
@@ -168,6 +292,159 @@ pub struct Shell {
     pub machine_name: MachineName,
 }
 
+/// Debug a running machine with gdb
+///
+/// Connects to the machine's GDB server with the symbols loaded from the machine's bin,
+/// then runs the configured init commands.
+#[derive(Parser, Debug)]
+pub struct Debug {
+    #[command(flatten)]
+    pub system: CommonSystemOptions,
+
+    /// The gdb executable to use, defaults to gdb-multiarch or gdb
+    #[arg(long)]
+    pub gdb: Option<PathBuf>,
+
+    pub machine_name: MachineName,
+}
+
+#[derive(Parser, Debug)]
+pub enum Connection {
+    Impair(Impair),
+}
+
+/// Change the impairment of a network connection on a running system
+///
+/// The given settings replace any impairment currently applied to the connection.
+#[derive(Parser, Debug)]
+pub struct Impair {
+    #[command(flatten)]
+    pub system: CommonSystemOptions,
+
+    pub connection_name: ConnectionName,
+
+    /// Delay added to each packet, e.g. '100ms'
+    #[arg(long)]
+    pub delay: Option<String>,
+
+    /// Random variation of the delay, e.g. '10ms'
+    #[arg(long, requires = "delay")]
+    pub jitter: Option<String>,
+
+    /// Percentage of packets to drop, e.g. '5%'
+    #[arg(long)]
+    pub loss: Option<String>,
+
+    /// Percentage of packets to duplicate, e.g. '1%'
+    #[arg(long)]
+    pub duplicate: Option<String>,
+
+    /// Percentage of packets sent immediately, out of order, e.g. '25%'
+    #[arg(long, requires = "delay")]
+    pub reorder: Option<String>,
+
+    /// Percentage of packets with a random bit flipped, e.g. '0.1%'
+    #[arg(long)]
+    pub corrupt: Option<String>,
+
+    /// Rate limit, e.g. '1mbit'
+    #[arg(long)]
+    pub rate: Option<String>,
+
+    /// Remove all impairments from the connection
+    #[arg(long, conflicts_with_all = ["delay", "jitter", "loss", "duplicate", "reorder", "corrupt", "rate"])]
+    pub clear: bool,
+}
+
+#[derive(Parser, Debug)]
+pub enum Images {
+    Build(BuildImages),
+}
+
+/// Build the stock provider images locally
+///
+/// The images are tagged with the conductor version, and also stand in for the
+/// published images when they can't be pulled.
+#[derive(Parser, Debug)]
+pub struct BuildImages {
+    /// Only build this provider's image, one of 'gazebo', 'qemu' or 'renode'
+    pub provider: Option<StockImage>,
+}
+
+/// Run test scenarios against a system
+///
+/// Scenario files are TOML, with a `[[step]]` table per step. Unless a scenario sets
+/// `start-system = false`, the system is started before its first step and stopped
+/// after its last. Exits with an error when any scenario fails.
+///
+/// Robot Framework suites are run with Renode's `renode-test`, once per Renode
+/// container, after the scenarios. Suites include `${CONDUCTOR_RESOURCE}` and
+/// use its `Load Conductor System` keyword to load the conductor-generated
+/// script, then `Start Emulation`. Machines are addressed by their configured
+/// names, e.g. `machine=m0`.
+#[derive(Parser, Debug)]
+pub struct Test {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+
+    /// Scenario files, run in order
+    #[arg(required_unless_present = "robot")]
+    pub scenarios: Vec<PathBuf>,
+
+    /// Robot Framework suites to run against the system's Renode machines.
+    /// The system must not be running.
+    #[arg(long, num_args = 1..)]
+    pub robot: Vec<PathBuf>,
+
+    /// Directory the Robot output is written to, in a subdirectory per container
+    #[arg(long, default_value = "robot_results")]
+    pub robot_output: PathBuf,
+
+    /// Write a JUnit XML report to this file
+    #[arg(long)]
+    pub junit: Option<PathBuf>,
+
+    /// Write a TAP report to this file
+    #[arg(long)]
+    pub tap: Option<PathBuf>,
+}
+
+/// Inject a fault into a running system
+///
+/// Container faults (kill, restart, freeze, thaw) target a component, and apply to
+/// its whole container. 'disconnect', 'reconnect' and 'corrupt' target a network
+/// connection, 'cut-uart' and 'restore-uart' a UART connection between Renode
+/// machines, and 'gpio' a Renode machine. Injected faults are recorded in the
+/// system's event log.
+#[derive(Parser, Debug)]
+pub struct Fault {
+    #[command(flatten)]
+    pub common: CommonSystemOptions,
+
+    /// One of 'kill', 'restart', 'freeze', 'thaw', 'disconnect', 'reconnect',
+    /// 'cut-uart', 'restore-uart', 'gpio' or 'corrupt'
+    pub kind: FaultKind,
+
+    /// The component, connection or machine the fault is injected into
+    pub target: String,
+
+    /// The GPIO peripheral of a 'gpio' fault, e.g. 'sysbus.gpioPortA'
+    #[arg(long)]
+    pub interface: Option<String>,
+
+    /// The GPIO pin of a 'gpio' fault
+    #[arg(long)]
+    pub pin: Option<u16>,
+
+    /// The value a 'gpio' fault drives the pin to
+    #[arg(long)]
+    pub value: Option<bool>,
+
+    /// The percentage of packets a 'corrupt' fault corrupts, e.g. '1%'
+    #[arg(long)]
+    pub percentage: Option<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct CommonSystemOptions {
     /// Path to config file.
@@ -206,3 +483,25 @@ impl FromStr for GraphFormat {
         }
     }
 }
+
+#[derive(Parser, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum StatusFormat {
+    /// A table per component and connection
+    #[default]
+    Table,
+
+    /// A JSON object, for monitoring scripts
+    Json,
+}
+
+impl FromStr for StatusFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(StatusFormat::Table),
+            "json" => Ok(StatusFormat::Json),
+            _ => Err(format!("'{s}' is not a valid StatusFormat kind")),
+        }
+    }
+}
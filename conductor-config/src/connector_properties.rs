@@ -1,4 +1,4 @@
-use crate::MachineConnector;
+use crate::{Decimal, MachineConnector};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -22,6 +22,30 @@ pub enum ConnectorPropertiesError {
         #[source]
         error: Box<toml::de::Error>,
     },
+    #[error("Failed to parse connector '{name}' CAN properties")]
+    ParseCan {
+        name: String,
+        #[source]
+        error: Box<toml::de::Error>,
+    },
+    #[error("Failed to parse connector '{name}' I2C properties")]
+    ParseI2c {
+        name: String,
+        #[source]
+        error: Box<toml::de::Error>,
+    },
+    #[error("Failed to parse connector '{name}' SPI properties")]
+    ParseSpi {
+        name: String,
+        #[source]
+        error: Box<toml::de::Error>,
+    },
+    #[error("Failed to parse connector '{name}' wireless network properties")]
+    ParseWirelessNetwork {
+        name: String,
+        #[source]
+        error: Box<toml::de::Error>,
+    },
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
@@ -81,6 +105,123 @@ impl TryFrom<&MachineConnector> for GpioConnectorProperties {
 #[serde(rename_all = "kebab-case", default)]
 pub struct NetworkConnectorProperties {
     pub promiscuous_mode: Option<bool>,
+    /// Overrides the connection-level impairment settings for this connector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impairment: Option<NetworkImpairmentConfig>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct CanConnectorProperties {
+    /// The CAN controller device model used by QEMU machines, defaults to `kvaser_pci`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl TryFrom<&MachineConnector> for CanConnectorProperties {
+    type Error = ConnectorPropertiesError;
+
+    fn try_from(value: &MachineConnector) -> Result<Self, Self::Error> {
+        let props =
+            value
+                .context
+                .clone()
+                .try_into()
+                .map_err(|e| ConnectorPropertiesError::ParseCan {
+                    name: value.name.clone(),
+                    error: Box::new(e),
+                })?;
+        Ok(props)
+    }
+}
+
+/// The role of a connector on a shared bus
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum BusRole {
+    Controller,
+    #[default]
+    Target,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct I2cConnectorProperties {
+    pub role: BusRole,
+    /// The 7-bit address of a target, required for targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u16>,
+}
+
+impl TryFrom<&MachineConnector> for I2cConnectorProperties {
+    type Error = ConnectorPropertiesError;
+
+    fn try_from(value: &MachineConnector) -> Result<Self, Self::Error> {
+        let props =
+            value
+                .context
+                .clone()
+                .try_into()
+                .map_err(|e| ConnectorPropertiesError::ParseI2c {
+                    name: value.name.clone(),
+                    error: Box::new(e),
+                })?;
+        Ok(props)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SpiConnectorProperties {
+    pub role: BusRole,
+    /// The chip select line of a target, required for targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<u16>,
+}
+
+impl TryFrom<&MachineConnector> for SpiConnectorProperties {
+    type Error = ConnectorPropertiesError;
+
+    fn try_from(value: &MachineConnector) -> Result<Self, Self::Error> {
+        let props =
+            value
+                .context
+                .clone()
+                .try_into()
+                .map_err(|e| ConnectorPropertiesError::ParseSpi {
+                    name: value.name.clone(),
+                    error: Box::new(e),
+                })?;
+        Ok(props)
+    }
+}
+
+/// Link impairment settings, applied with `tc netem`.
+///
+/// Renode machines are impaired on their tap devices. Other components use the host's
+/// network, so they're impaired together on the connection's host bridge and their
+/// images require `tc`.
+///
+/// Values use the `tc` notation, e.g. `delay = "100ms"`, `loss = "5%"` or `rate = "1mbit"`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct NetworkImpairmentConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reorder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrupt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<String>,
 }
 
 impl TryFrom<&MachineConnector> for NetworkConnectorProperties {
@@ -96,3 +237,120 @@ impl TryFrom<&MachineConnector> for NetworkConnectorProperties {
         Ok(props)
     }
 }
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WirelessNetworkConnectorProperties {
+    /// Position of the radio within the medium, used for range-based loss
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Position {
+    pub x: Decimal,
+    pub y: Decimal,
+    pub z: Decimal,
+}
+
+impl TryFrom<&MachineConnector> for WirelessNetworkConnectorProperties {
+    type Error = ConnectorPropertiesError;
+
+    fn try_from(value: &MachineConnector) -> Result<Self, Self::Error> {
+        let props = value.context.clone().try_into().map_err(|e| {
+            ConnectorPropertiesError::ParseWirelessNetwork {
+                name: value.name.clone(),
+                error: Box::new(e),
+            }
+        })?;
+        Ok(props)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use indoc::indoc;
+    use std::str::FromStr;
+
+    #[test]
+    fn read_wireless_connector_position() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "biz"
+                [[machine.connector]]
+                name = "barbiz-bt"
+                interface = "net0"
+                position = { x = 1.5, y = 0, z = -2 }
+        "#})
+        .unwrap();
+        assert_eq!(
+            WirelessNetworkConnectorProperties::try_from(&cfg.machines[0].connectors[0]).unwrap(),
+            WirelessNetworkConnectorProperties {
+                position: Some(Position {
+                    x: 1.5.into(),
+                    y: 0.0.into(),
+                    z: (-2.0).into(),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn read_connector_impairment() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "biz"
+                [[machine.connector]]
+                name = "foobiz"
+                interface = "net2"
+                impairment = { delay = '20ms', jitter = '5ms' }
+        "#})
+        .unwrap();
+        assert_eq!(
+            NetworkConnectorProperties::try_from(&cfg.machines[0].connectors[0])
+                .unwrap()
+                .impairment,
+            Some(NetworkImpairmentConfig {
+                delay: Some("20ms".to_owned()),
+                jitter: Some("5ms".to_owned()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn read_bus_connector_roles() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "foo"
+                [[machine.connector]]
+                name = "sensor-bus"
+                interface = "sysbus.i2c1"
+                role = "controller"
+
+                [[machine.connector]]
+                name = "flash-bus"
+                interface = "sysbus.spi1"
+                address = 2
+        "#})
+        .unwrap();
+        let connectors = &cfg.machines[0].connectors;
+        assert_eq!(
+            I2cConnectorProperties::try_from(&connectors[0]).unwrap(),
+            I2cConnectorProperties {
+                role: BusRole::Controller,
+                address: None,
+            }
+        );
+        assert_eq!(
+            SpiConnectorProperties::try_from(&connectors[1]).unwrap(),
+            SpiConnectorProperties {
+                role: BusRole::Target,
+                address: Some(2),
+            }
+        );
+    }
+}
@@ -10,6 +10,59 @@ pub struct ContainerMachineProvider {
     pub containerfile: Option<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<PathBuf>,
+    /// Overrides the image's command, when a bin is also set it's only mounted
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub command: Option<String>,
+    pub command: Option<ContainerCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<ContainerCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// A command given either as a shell string, which is run with `/bin/sh -c`, or as an argv list
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ContainerCommand {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl ContainerCommand {
+    pub fn to_argv(&self) -> Vec<String> {
+        match self {
+            ContainerCommand::Shell(s) => vec!["/bin/sh".to_owned(), "-c".to_owned(), s.clone()],
+            ContainerCommand::Argv(argv) => argv.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_container_provider() {
+        let provider: ContainerMachineProvider =
+            toml::from_str("command = ['/app/run', '--fast']\nworkdir = '/app'").unwrap();
+        assert_eq!(
+            provider,
+            ContainerMachineProvider {
+                command: Some(ContainerCommand::Argv(vec![
+                    "/app/run".to_owned(),
+                    "--fast".to_owned()
+                ])),
+                workdir: Some("/app".into()),
+                ..Default::default()
+            }
+        );
+
+        let provider: ContainerMachineProvider =
+            toml::from_str("entrypoint = 'exec /app/run'").unwrap();
+        assert_eq!(
+            provider.entrypoint.unwrap().to_argv(),
+            vec!["/bin/sh", "-c", "exec /app/run"]
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::ProviderImage;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -20,4 +21,6 @@ pub struct GazeboWorldProvider {
     pub auto_start: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub verbose: Option<usize>,
+    #[serde(flatten)]
+    pub base_image: ProviderImage,
 }
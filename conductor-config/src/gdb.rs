@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// A GDB remote server exposed by a machine's emulator, e.g.
+/// `gdb-server = { port = 3333, wait = true }`
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct GdbServerConfig {
+    /// The port the server listens on, defaults to 3333
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Halt the machine until a debugger connects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait: Option<bool>,
+    /// Extra commands `conductor machine debug` runs in gdb after connecting
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub init_commands: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_gdb_server_config() {
+        let cfg: GdbServerConfig =
+            toml::from_str("port = 1234\nwait = true\ninit-commands = ['break main']").unwrap();
+        assert_eq!(
+            cfg,
+            GdbServerConfig {
+                port: Some(1234),
+                wait: Some(true),
+                init_commands: vec!["break main".to_owned()],
+            }
+        );
+        assert_eq!(
+            toml::from_str::<GdbServerConfig>("").unwrap(),
+            GdbServerConfig::default()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The container image a provider runs in, defaults to the stock conductor image
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ProviderImage {
+    /// e.g. 'registry.example.com/conductor-renode:1.14.0'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Extends the image, which is available to the containerfile as the
+    /// `BASE_IMAGE` build arg
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub containerfile: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<PathBuf>,
+}
+
+impl ProviderImage {
+    pub fn is_extended(&self) -> bool {
+        self.containerfile.is_some() || self.context.is_some()
+    }
+}
+
+/// System-wide image overrides for each provider, a provider's `image` takes precedence
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ProviderImages {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qemu: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gazebo: Option<String>,
+}
+
+impl ProviderImages {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn read_provider_images() {
+        let images: ProviderImages =
+            toml::from_str("renode = 'registry.example.com/conductor-renode:1.14.0'").unwrap();
+        assert_eq!(
+            images.renode.as_deref(),
+            Some("registry.example.com/conductor-renode:1.14.0")
+        );
+        assert!(!images.is_empty());
+
+        let image: ProviderImage = toml::from_str(indoc! {r#"
+            image = 'registry.example.com/conductor-qemu:8.0.0'
+            containerfile = 'path/to/Containerfile'
+        "#})
+        .unwrap();
+        assert_eq!(
+            image,
+            ProviderImage {
+                image: Some("registry.example.com/conductor-qemu:8.0.0".to_owned()),
+                containerfile: Some("path/to/Containerfile".into()),
+                context: None,
+            }
+        );
+        assert!(image.is_extended());
+    }
+}
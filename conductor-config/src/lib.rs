@@ -7,19 +7,35 @@ use std::{
 };
 
 pub use connector_properties::{
-    ConnectorPropertiesError, GpioConnectorProperties, NetworkConnectorProperties,
-    UartConnectorProperties,
+    BusRole, CanConnectorProperties, ConnectorPropertiesError, GpioConnectorProperties,
+    I2cConnectorProperties, NetworkConnectorProperties, NetworkImpairmentConfig, Position,
+    SpiConnectorProperties, UartConnectorProperties, WirelessNetworkConnectorProperties,
 };
-pub use container::ContainerMachineProvider;
+pub use container::{ContainerCommand, ContainerMachineProvider};
+pub use decimal::Decimal;
 pub use gazebo::GazeboWorldProvider;
+pub use gdb::GdbServerConfig;
+pub use image::{ProviderImage, ProviderImages};
 pub use qemu::{QemuMachineProtocolConfig, QemuMachineProvider};
 pub use renode::{RenodeCliConfig, RenodeMachineProvider, RenodeScriptConfig};
+pub use resources::ResourceLimitsConfig;
+pub use restart::{RestartConfig, RestartPolicyKind};
+pub use scenario::{
+    DurationStep, ExecStep, FaultConfig, FaultKind, GpioStep, LogTrigger, ScenarioConfig,
+    ScenarioStep, ScheduledFault, UartStep, WaitForLogStep,
+};
 
 mod connector_properties;
 mod container;
+mod decimal;
 mod gazebo;
+mod gdb;
+mod image;
 mod qemu;
 mod renode;
+mod resources;
+mod restart;
+mod scenario;
 
 pub const DEFAULT_CONFIG_FILE_NAME: &str = "conductor.toml";
 pub const DEFAULT_SYSTEM_NAME: &str = "default-system";
@@ -85,12 +101,41 @@ pub struct Config {
 pub struct Global {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// The host X11 display, or 'vnc' to run a virtual display with a VNC server
+    /// in each GUI container
     #[serde(skip_serializing_if = "Option::is_none")]
     pub display: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub xauthority: Option<PathBuf>,
+    /// The host Wayland display, defaults to $WAYLAND_DISPLAY
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wayland_display: Option<String>,
+    /// Forces the protocol used to forward the host display. By default Wayland is
+    /// used when available, with X11 (e.g. XWayland) as the fallback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_protocol: Option<DisplayProtocol>,
+    /// The VNC port of the first GUI container, each subsequent one uses the next port.
+    /// Defaults to 5900
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vnc_port: Option<u16>,
+    /// When set, noVNC is served over HTTP starting at this port, in the same manner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub novnc_port: Option<u16>,
+    #[serde(skip_serializing_if = "ProviderImages::is_empty")]
+    pub images: ProviderImages,
     #[serde(skip_serializing_if = "BTreeMap::is_empty")]
     pub environment_variables: BTreeMap<String, String>,
+    /// The ratio of simulated time to wall-clock time, e.g. 0.5 for half speed.
+    /// QEMU machines approximate it with instruction counting, so they don't use KVM
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_scale: Option<Decimal>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisplayProtocol {
+    X11,
+    Wayland,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
@@ -106,6 +151,14 @@ pub struct World {
     pub provider: Option<WorldProvider>,
     #[serde(alias = "connector", skip_serializing_if = "Vec::is_empty")]
     pub connectors: Vec<WorldConnector>,
+    #[serde(skip_serializing_if = "ResourceLimitsConfig::is_empty")]
+    pub resources: ResourceLimitsConfig,
+    #[serde(flatten, skip_serializing_if = "RestartConfig::is_empty")]
+    pub restart: RestartConfig,
+    /// Ports the container listens on, '<port>[/<tcp|udp>]', e.g. '80/tcp'. Containers
+    /// use the host's network, so they're the same ports on the host
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -118,6 +171,30 @@ pub enum WorldProvider {
 #[serde(rename_all = "kebab-case")]
 pub struct WorldConnector {
     pub name: String,
+    /// A gz-transport topic bridged onto the connection, e.g. '/imu'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// The topic's message type, e.g. 'gz.msgs.IMU'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+    /// Defaults to 'to-machine'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<TopicDirection>,
+    /// The UDP port used when bridging onto a network connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum TopicDirection {
+    /// Messages published by the world are forwarded to the machines
+    #[default]
+    ToMachine,
+    /// Data from the machines is published to the world
+    FromMachine,
 }
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
@@ -135,6 +212,27 @@ pub struct Machine {
     pub provider: Option<MachineProvider>,
     #[serde(alias = "connector", skip_serializing_if = "Vec::is_empty")]
     pub connectors: Vec<MachineConnector>,
+    /// Storage attached to this machine, by name or as a table with an interface
+    #[serde(alias = "storages", skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<MachineStorage>,
+    #[serde(skip_serializing_if = "ResourceLimitsConfig::is_empty")]
+    pub resources: ResourceLimitsConfig,
+    #[serde(flatten, skip_serializing_if = "RestartConfig::is_empty")]
+    pub restart: RestartConfig,
+    /// Host devices passed through to the machine's container, e.g. '/dev/ttyUSB0'
+    /// or '/dev/ttyUSB0:/dev/ttyS1:rw'
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<String>,
+    /// Extra Linux capabilities for the machine's container, e.g. 'SYS_PTRACE'
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+    /// Ports the container listens on, '<port>[/<tcp|udp>]', e.g. '80/tcp'. Containers
+    /// use the host's network, so they're the same ports on the host
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
+    /// Serve the machine's CPU over the GDB remote protocol, Renode and QEMU only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gdb_server: Option<GdbServerConfig>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -160,6 +258,10 @@ pub enum Connection {
     Uart(UartConnection),
     Gpio(GpioConnection),
     Network(NetworkConnection),
+    WirelessNetwork(WirelessNetworkConnection),
+    Can(CanConnection),
+    I2c(I2cConnection),
+    Spi(SpiConnection),
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -178,6 +280,45 @@ pub struct GpioConnection {
 #[serde(rename_all = "kebab-case")]
 pub struct NetworkConnection {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impairment: Option<NetworkImpairmentConfig>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CanConnection {
+    pub name: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct I2cConnection {
+    pub name: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpiConnection {
+    pub name: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WirelessNetworkConnection {
+    pub name: String,
+    pub medium: WirelessMedium,
+    /// Radios further apart than this range can't communicate, requires
+    /// each connector to have a position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Decimal>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WirelessMedium {
+    Ble,
+    #[serde(rename = "ieee802.15.4", alias = "ieee802154")]
+    Ieee802_15_4,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
@@ -191,6 +332,47 @@ pub enum Storage {
 pub struct VirtioStorage {
     pub name: String,
     pub image: PathBuf,
+    /// Writes go to a throwaway overlay so each run starts from the pristine image,
+    /// defaults to true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copy_on_write: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+}
+
+/// A machine's reference to a `[[storage]]` entry
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", from = "MachineStorageRepr")]
+pub struct MachineStorage {
+    pub name: String,
+    /// The device the storage is attached to. Required for Renode machines, where it's
+    /// the block device or SD card peripheral. Optional for QEMU machines, where it's the
+    /// drive interface and defaults to `virtio`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, rename_all = "kebab-case")]
+enum MachineStorageRepr {
+    Name(String),
+    Table {
+        name: String,
+        #[serde(default)]
+        interface: Option<String>,
+    },
+}
+
+impl From<MachineStorageRepr> for MachineStorage {
+    fn from(value: MachineStorageRepr) -> Self {
+        match value {
+            MachineStorageRepr::Name(name) => MachineStorage {
+                name,
+                interface: None,
+            },
+            MachineStorageRepr::Table { name, interface } => MachineStorage { name, interface },
+        }
+    }
 }
 
 impl Config {
@@ -359,4 +541,265 @@ mod tests {
         assert_eq!(cfg.worlds.len(), 1);
         assert_eq!(cfg.machines.len(), 3);
     }
+
+    #[test]
+    fn read_network_impairment() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[connection]]
+            name = "foobiz"
+            type = "network"
+                [connection.impairment]
+                loss = '5%'
+                rate = '1mbit'
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.connections,
+            vec![Connection::Network(NetworkConnection {
+                name: "foobiz".to_owned(),
+                impairment: Some(NetworkImpairmentConfig {
+                    loss: Some("5%".to_owned()),
+                    rate: Some("1mbit".to_owned()),
+                    ..Default::default()
+                }),
+            })]
+        );
+    }
+
+    #[test]
+    fn read_can_connection() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "foo"
+                [[machine.connector]]
+                name = "vehicle-bus"
+                interface = "sysbus.can0"
+                model = "kvaser_pci"
+
+            [[connection]]
+            name = "vehicle-bus"
+            type = "can"
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.connections,
+            vec![Connection::Can(CanConnection {
+                name: "vehicle-bus".to_owned(),
+            })]
+        );
+        assert_eq!(
+            CanConnectorProperties::try_from(&cfg.machines[0].connectors[0]).unwrap(),
+            CanConnectorProperties {
+                model: Some("kvaser_pci".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn read_bus_connections() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[connection]]
+            name = "sensor-bus"
+            type = "i2c"
+
+            [[connection]]
+            name = "flash-bus"
+            type = "spi"
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.connections,
+            vec![
+                Connection::I2c(I2cConnection {
+                    name: "sensor-bus".to_owned(),
+                }),
+                Connection::Spi(SpiConnection {
+                    name: "flash-bus".to_owned(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_storage() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "foo"
+            storage = ["my-img", { name = "my-data", interface = "sysbus.virtio" }]
+
+            [[storage]]
+            name = "my-data"
+            type = "virtio"
+            image = 'path/to/data.qcow2'
+            copy-on-write = false
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.machines[0].storage,
+            vec![
+                MachineStorage {
+                    name: "my-img".to_owned(),
+                    interface: None,
+                },
+                MachineStorage {
+                    name: "my-data".to_owned(),
+                    interface: Some("sysbus.virtio".to_owned()),
+                },
+            ]
+        );
+        assert_eq!(
+            cfg.storages,
+            vec![Storage::Virtio(VirtioStorage {
+                name: "my-data".to_owned(),
+                image: "path/to/data.qcow2".into(),
+                copy_on_write: Some(false),
+                read_only: None,
+            })]
+        );
+    }
+
+    #[test]
+    fn read_world_topic_connectors() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[world]]
+            name = 'a world'
+                [[world.connector]]
+                name = "foobar"
+                topic = '/imu'
+                message-type = 'gz.msgs.IMU'
+
+                [[world.connector]]
+                name = "barbiz"
+                topic = '/cmd_vel'
+                direction = 'from-machine'
+                port = 5000
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.worlds[0].connectors,
+            vec![
+                WorldConnector {
+                    name: "foobar".to_owned(),
+                    topic: Some("/imu".to_owned()),
+                    message_type: Some("gz.msgs.IMU".to_owned()),
+                    direction: None,
+                    port: None,
+                },
+                WorldConnector {
+                    name: "barbiz".to_owned(),
+                    topic: Some("/cmd_vel".to_owned()),
+                    message_type: None,
+                    direction: Some(TopicDirection::FromMachine),
+                    port: Some(5000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn read_machine_devices_and_capabilities() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[machine]]
+            name = "bar"
+            devices = ['/dev/kvm', '/dev/ttyUSB0:/dev/ttyS1:rw']
+            capabilities = ['SYS_PTRACE']
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.machines[0].devices,
+            vec![
+                "/dev/kvm".to_owned(),
+                "/dev/ttyUSB0:/dev/ttyS1:rw".to_owned()
+            ]
+        );
+        assert_eq!(cfg.machines[0].capabilities, vec!["SYS_PTRACE".to_owned()]);
+    }
+
+    #[test]
+    fn read_ports() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[world]]
+            name = 'a world'
+            ports = ['11345/tcp']
+
+            [[machine]]
+            name = "foo"
+            ports = ['8080/tcp', '5353/udp']
+        "#})
+        .unwrap();
+        assert_eq!(cfg.worlds[0].ports, vec!["11345/tcp".to_owned()]);
+        assert_eq!(
+            cfg.machines[0].ports,
+            vec!["8080/tcp".to_owned(), "5353/udp".to_owned()]
+        );
+    }
+
+    #[test]
+    fn read_time_scale() {
+        let cfg = Config::from_str("time-scale = 0.5").unwrap();
+        assert_eq!(cfg.global.time_scale, Some(0.5.into()));
+        assert_eq!(Config::from_str("").unwrap().global.time_scale, None);
+    }
+
+    #[test]
+    fn read_restart_policies() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[world]]
+            name = 'a world'
+            restart = 'always'
+
+            [[machine]]
+            name = "bar"
+            restart = 'on-failure'
+            restart-max-retries = 3
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.worlds[0].restart.restart,
+            Some(RestartPolicyKind::Always)
+        );
+        assert_eq!(
+            cfg.machines[0].restart,
+            RestartConfig {
+                restart: Some(RestartPolicyKind::OnFailure),
+                restart_max_retries: Some(3),
+                restart_backoff: None,
+            }
+        );
+    }
+
+    #[test]
+    fn read_wireless_network_connection() {
+        let cfg = Config::from_str(indoc! {r#"
+            [[connection]]
+            name = "barbiz-bt"
+            type = "wireless-network"
+            medium = "ble"
+            range = 10
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg.connections,
+            vec![Connection::WirelessNetwork(WirelessNetworkConnection {
+                name: "barbiz-bt".to_owned(),
+                medium: WirelessMedium::Ble,
+                range: Some(10.0.into()),
+            })]
+        );
+    }
+
+    #[test]
+    fn read_mixed_system_config() {
+        let cfg_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_resources/systems/mixed")
+            .join(DEFAULT_CONFIG_FILE_NAME);
+        let cfg = Config::read(cfg_path).unwrap();
+        assert!(cfg.connections.iter().any(|c| matches!(
+            c,
+            Connection::WirelessNetwork(WirelessNetworkConnection {
+                medium: WirelessMedium::Ble,
+                ..
+            })
+        )));
+    }
 }
@@ -1,8 +1,12 @@
+use crate::ProviderImage;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct QemuMachineProvider {
+    /// The guest architecture, i.e. the `qemu-system-<arch>` emulator, defaults to 'arm'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub machine: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -12,6 +16,8 @@ pub struct QemuMachineProvider {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub no_graphic: Option<bool>,
     pub qmp: QemuMachineProtocolConfig,
+    #[serde(flatten)]
+    pub base_image: ProviderImage,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
@@ -24,3 +30,32 @@ pub struct QemuMachineProtocolConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub server: Option<bool>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn read_qemu_provider_image() {
+        let qemu: QemuMachineProvider = toml::from_str(indoc! {r#"
+            machine = 'mps2-an385'
+            image = 'registry.example.com/conductor-qemu:8.0.0'
+            [qmp]
+            port = 4444
+        "#})
+        .unwrap();
+        assert_eq!(qemu.qmp.port, Some(4444));
+        assert_eq!(
+            qemu.base_image.image.as_deref(),
+            Some("registry.example.com/conductor-qemu:8.0.0")
+        );
+        assert!(!qemu.base_image.is_extended());
+    }
+
+    #[test]
+    fn read_qemu_arch() {
+        let qemu: QemuMachineProvider = toml::from_str("arch = 'aarch64'").unwrap();
+        assert_eq!(qemu.arch.as_deref(), Some("aarch64"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ProviderImage;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -9,6 +10,8 @@ pub struct RenodeMachineProvider {
 
     #[serde(flatten)]
     pub resc: RenodeScriptConfig,
+    #[serde(flatten)]
+    pub base_image: ProviderImage,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
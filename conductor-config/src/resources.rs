@@ -0,0 +1,60 @@
+use crate::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Container resource limits for a machine or world.
+///
+/// Values use the docker notation, e.g. `cpus = 1.5`, `cpuset = "0-3"` or `memory = "512m"`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ResourceLimitsConfig {
+    /// The number of CPUs, can be fractional
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<Decimal>,
+    /// The CPUs the container is pinned to, e.g. '0-3' or '1,3'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpuset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// The memory plus swap limit, '-1' for unlimited swap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_swap: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pids_limit: Option<i64>,
+}
+
+impl ResourceLimitsConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn read_resource_limits() {
+        let limits: ResourceLimitsConfig = toml::from_str(indoc! {r#"
+            cpus = 2.5
+            cpuset = '0,2-3'
+            memory = '512m'
+            memory-swap = '-1'
+            pids-limit = 128
+        "#})
+        .unwrap();
+        assert_eq!(
+            limits,
+            ResourceLimitsConfig {
+                cpus: Some(2.5.into()),
+                cpuset: Some("0,2-3".to_owned()),
+                memory: Some("512m".to_owned()),
+                memory_swap: Some("-1".to_owned()),
+                pids_limit: Some(128),
+            }
+        );
+        assert!(toml::from_str::<ResourceLimitsConfig>("")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// When a machine's or world's container is restarted after it exits, e.g.
+/// `restart = "on-failure"`, `restart-max-retries = 5` and `restart-backoff = "2s"`.
+///
+/// Restarts only happen while the system is supervised by
+/// `conductor system start --foreground`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RestartConfig {
+    /// Defaults to 'no'
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartPolicyKind>,
+    /// The number of consecutive restarts before giving up, unlimited by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart_max_retries: Option<u32>,
+    /// The delay before the first restart, doubled for each consecutive restart,
+    /// e.g. '500ms' or '2s'. Defaults to '1s'.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart_backoff: Option<String>,
+}
+
+impl RestartConfig {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicyKind {
+    #[default]
+    No,
+    /// Restart when the container exits with a non-zero code or runs out of memory
+    OnFailure,
+    Always,
+}
+
+impl fmt::Display for RestartPolicyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RestartPolicyKind::No => "no",
+            RestartPolicyKind::OnFailure => "on-failure",
+            RestartPolicyKind::Always => "always",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn read_restart_config() {
+        let cfg: RestartConfig = toml::from_str(indoc! {r#"
+            restart = 'on-failure'
+            restart-max-retries = 3
+            restart-backoff = '500ms'
+        "#})
+        .unwrap();
+        assert_eq!(
+            cfg,
+            RestartConfig {
+                restart: Some(RestartPolicyKind::OnFailure),
+                restart_max_retries: Some(3),
+                restart_backoff: Some("500ms".to_owned()),
+            }
+        );
+        assert!(toml::from_str::<RestartConfig>("").unwrap().is_empty());
+        assert_eq!(RestartPolicyKind::OnFailure.to_string(), "on-failure");
+    }
+}
@@ -0,0 +1,402 @@
+use crate::ConfigReadError;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path, str::FromStr};
+
+/// A test scenario run against a system by `conductor test`
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScenarioConfig {
+    /// Defaults to the scenario file's name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Start the system before the first step and stop it after the last one,
+    /// defaults to true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_system: Option<bool>,
+    #[serde(alias = "step", skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<ScenarioStep>,
+    /// Faults injected alongside the steps, at a time or when a machine logs a line
+    #[serde(alias = "fault", skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<ScheduledFault>,
+}
+
+/// Timeouts and durations use the same notation as `conductor system step`,
+/// e.g. '500ms' or '2s'
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ScenarioStep {
+    /// Wait for a line of a machine's container output to match a regex
+    WaitForLog(WaitForLogStep),
+    /// Write to and/or wait for output from a machine's UART socket terminal
+    Uart(UartStep),
+    /// Run a command in a machine's container
+    Exec(ExecStep),
+    /// Drive a Renode machine's GPIO pin
+    Gpio(GpioStep),
+    Pause,
+    Resume,
+    /// Run the paused system for a duration
+    Step(DurationStep),
+    /// Wait for a duration of wall-clock time
+    Sleep(DurationStep),
+    /// Inject a fault
+    Fault(FaultConfig),
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WaitForLogStep {
+    pub machine: String,
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UartStep {
+    pub machine: String,
+    /// A UART connection the machine has a socket terminal `port` on
+    pub connection: String,
+    /// Written to the UART, before waiting for the expected output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write: Option<String>,
+    /// An extended regular expression a line of output must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExecStep {
+    pub machine: String,
+    pub command: Vec<String>,
+    /// A regex the command's output must match, the command must also succeed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GpioStep {
+    pub machine: String,
+    /// The GPIO peripheral, e.g. 'sysbus.gpioPortA'
+    pub interface: String,
+    pub pin: u16,
+    pub value: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DurationStep {
+    pub duration: String,
+}
+
+/// A fault injected into a running system, also used by `conductor fault`
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FaultConfig {
+    pub fault: FaultKind,
+    /// The component for container faults, the connection for the others,
+    /// or the machine for 'gpio'
+    pub target: String,
+    /// The GPIO peripheral of a 'gpio' fault, e.g. 'sysbus.gpioPortA'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<bool>,
+    /// The percentage of packets a 'corrupt' fault corrupts, e.g. '1%'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<String>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaultKind {
+    /// Kill a component's container
+    Kill,
+    /// Restart a component's container
+    Restart,
+    /// Freeze every process in a component's container
+    Freeze,
+    /// Resume a frozen component
+    Thaw,
+    /// Take down a network connection's links
+    Disconnect,
+    /// Bring a disconnected network connection's links back up
+    Reconnect,
+    /// Disconnect the Renode machines from a UART connection
+    CutUart,
+    /// Reconnect the Renode machines to a cut UART connection
+    RestoreUart,
+    /// Drive a Renode machine's GPIO pin
+    Gpio,
+    /// Corrupt a percentage of the packets on a network connection
+    Corrupt,
+}
+
+impl FaultKind {
+    pub const ALL: [FaultKind; 10] = [
+        FaultKind::Kill,
+        FaultKind::Restart,
+        FaultKind::Freeze,
+        FaultKind::Thaw,
+        FaultKind::Disconnect,
+        FaultKind::Reconnect,
+        FaultKind::CutUart,
+        FaultKind::RestoreUart,
+        FaultKind::Gpio,
+        FaultKind::Corrupt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FaultKind::Kill => "kill",
+            FaultKind::Restart => "restart",
+            FaultKind::Freeze => "freeze",
+            FaultKind::Thaw => "thaw",
+            FaultKind::Disconnect => "disconnect",
+            FaultKind::Reconnect => "reconnect",
+            FaultKind::CutUart => "cut-uart",
+            FaultKind::RestoreUart => "restore-uart",
+            FaultKind::Gpio => "gpio",
+            FaultKind::Corrupt => "corrupt",
+        }
+    }
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FaultKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FaultKind::ALL
+            .into_iter()
+            .find(|k| k.as_str() == s)
+            .ok_or_else(|| {
+                let kinds: Vec<&str> = FaultKind::ALL.iter().map(FaultKind::as_str).collect();
+                format!("Unknown fault '{s}', expected one of {}", kinds.join(", "))
+            })
+    }
+}
+
+/// A fault injected at a time, or when a machine logs a matching line
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScheduledFault {
+    #[serde(flatten)]
+    pub fault: FaultConfig,
+    /// Time since the scenario's first step, e.g. '5s'
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+    /// Inject the fault once a line of the machine's output matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_log: Option<LogTrigger>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogTrigger {
+    pub machine: String,
+    pub pattern: String,
+}
+
+impl ScenarioConfig {
+    pub fn read<P: AsRef<Path>>(scenario_path: P) -> Result<Self, ConfigReadError> {
+        let content = fs::read_to_string(&scenario_path)?;
+        Self::from_str(&content).map_err(|e| ConfigReadError::ConfigToml {
+            path: scenario_path.as_ref().to_owned(),
+            error: e,
+        })
+    }
+}
+
+impl FromStr for ScenarioConfig {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const SCENARIO_TOML: &str = indoc! {r#"
+        name = 'echo'
+        start-system = false
+
+        [[step]]
+        type = 'wait-for-log'
+        machine = 'foo'
+        pattern = 'booted'
+        timeout = '30s'
+
+        [[step]]
+        type = 'uart'
+        machine = 'foo'
+        connection = 'foo-uart'
+        write = "ping\n"
+        expect = 'pong'
+
+        [[step]]
+        type = 'exec'
+        machine = 'bar'
+        command = ['cat', '/etc/hostname']
+        expect = 'bar'
+
+        [[step]]
+        type = 'gpio'
+        machine = 'foo'
+        interface = 'sysbus.gpioPortA'
+        pin = 2
+        value = true
+
+        [[step]]
+        type = 'pause'
+
+        [[step]]
+        type = 'step'
+        duration = '100ms'
+
+        [[step]]
+        type = 'resume'
+
+        [[step]]
+        type = 'sleep'
+        duration = '1s'
+
+        [[step]]
+        type = 'fault'
+        fault = 'kill'
+        target = 'bar'
+
+        [[fault]]
+        fault = 'disconnect'
+        target = 'foo-net'
+        at = '5s'
+
+        [[fault]]
+        fault = 'gpio'
+        target = 'foo'
+        interface = 'sysbus.gpioPortA'
+        pin = 3
+        value = false
+        on-log = { machine = 'foo', pattern = 'armed' }
+    "#};
+
+    #[test]
+    fn read_scenario() {
+        let s = ScenarioConfig::from_str(SCENARIO_TOML).unwrap();
+        assert_eq!(
+            s,
+            ScenarioConfig {
+                name: Some("echo".to_owned()),
+                start_system: Some(false),
+                steps: vec![
+                    ScenarioStep::WaitForLog(WaitForLogStep {
+                        machine: "foo".to_owned(),
+                        pattern: "booted".to_owned(),
+                        timeout: Some("30s".to_owned()),
+                    }),
+                    ScenarioStep::Uart(UartStep {
+                        machine: "foo".to_owned(),
+                        connection: "foo-uart".to_owned(),
+                        write: Some("ping\n".to_owned()),
+                        expect: Some("pong".to_owned()),
+                        timeout: None,
+                    }),
+                    ScenarioStep::Exec(ExecStep {
+                        machine: "bar".to_owned(),
+                        command: vec!["cat".to_owned(), "/etc/hostname".to_owned()],
+                        expect: Some("bar".to_owned()),
+                        timeout: None,
+                    }),
+                    ScenarioStep::Gpio(GpioStep {
+                        machine: "foo".to_owned(),
+                        interface: "sysbus.gpioPortA".to_owned(),
+                        pin: 2,
+                        value: true,
+                    }),
+                    ScenarioStep::Pause,
+                    ScenarioStep::Step(DurationStep {
+                        duration: "100ms".to_owned(),
+                    }),
+                    ScenarioStep::Resume,
+                    ScenarioStep::Sleep(DurationStep {
+                        duration: "1s".to_owned(),
+                    }),
+                    ScenarioStep::Fault(FaultConfig {
+                        fault: FaultKind::Kill,
+                        target: "bar".to_owned(),
+                        interface: None,
+                        pin: None,
+                        value: None,
+                        percentage: None,
+                    }),
+                ],
+                faults: vec![
+                    ScheduledFault {
+                        fault: FaultConfig {
+                            fault: FaultKind::Disconnect,
+                            target: "foo-net".to_owned(),
+                            interface: None,
+                            pin: None,
+                            value: None,
+                            percentage: None,
+                        },
+                        at: Some("5s".to_owned()),
+                        on_log: None,
+                    },
+                    ScheduledFault {
+                        fault: FaultConfig {
+                            fault: FaultKind::Gpio,
+                            target: "foo".to_owned(),
+                            interface: Some("sysbus.gpioPortA".to_owned()),
+                            pin: Some(3),
+                            value: Some(false),
+                            percentage: None,
+                        },
+                        at: None,
+                        on_log: Some(LogTrigger {
+                            machine: "foo".to_owned(),
+                            pattern: "armed".to_owned(),
+                        }),
+                    },
+                ],
+            }
+        );
+        for kind in FaultKind::ALL {
+            assert_eq!(kind.to_string().parse::<FaultKind>(), Ok(kind));
+        }
+        assert!("explode".parse::<FaultKind>().is_err());
+    }
+
+    #[test]
+    fn read_renode_system_scenario() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_resources/systems/renode/scenario.toml");
+        let s = ScenarioConfig::read(path).unwrap();
+        assert_eq!(s.name.as_deref(), Some("m1 console"));
+        assert_eq!(s.start_system, None);
+        assert_eq!(s.steps.len(), 3);
+        assert!(matches!(
+            &s.steps[0],
+            ScenarioStep::WaitForLog(WaitForLogStep { machine, .. }) if machine == "m1"
+        ));
+        assert!(s.faults.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Drive conductor systems from Rust integration tests.
+//!
+//! A [`TestSystem`] builds and starts a system from its configuration file, and
+//! tears it down when dropped, even when the test panics.
+//!
+//! ```no_run
+//! use conductor_test::TestSystem;
+//! use std::time::Duration;
+//!
+//! #[tokio::test]
+//! async fn console_echoes() -> anyhow::Result<()> {
+//!     let sys = TestSystem::start("test_resources/systems/renode/conductor.toml").await?;
+//!     let m1 = sys.machine("m1")?;
+//!     m1.expect_output("Machine started", Duration::from_secs(60)).await?;
+//!     m1.uart("m1-to-host")?
+//!         .write_expect("help\n", "usage", Duration::from_secs(10))
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Each test system gets a unique name, so its containers don't clash with those of
+//! tests running in parallel. Containers run in the host's network, so systems using
+//! fixed ports (monitor, QMP, socket terminals, published ports) can't run in parallel.
+
+use anyhow::{anyhow, Result};
+use conductor::{
+    containers::Container,
+    types::{ConnectionName, MachineName, SystemName},
+    Config, System,
+};
+use regex::Regex;
+use std::{
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+use tracing::warn;
+
+static TEST_SYSTEM_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A running system, stopped and removed when dropped
+pub struct TestSystem {
+    system: Option<System>,
+}
+
+impl TestSystem {
+    /// Build and start the system defined by a configuration file.
+    ///
+    /// The containers run in the host's network, so systems with fixed ports, e.g. a
+    /// Renode monitor, QMP server, socket terminal or published port, clash with each
+    /// other. Tests starting such systems must not run in parallel, e.g. use
+    /// `--test-threads=1` or a lock shared by the tests.
+    pub async fn start<P: AsRef<Path>>(config_path: P) -> Result<Self> {
+        let config = Config::read(config_path)?;
+        Self::start_with_config(config).await
+    }
+
+    /// Build and start a system, under a unique name derived from the configured one
+    pub async fn start_with_config(mut config: Config) -> Result<Self> {
+        config.global.name = unique_system_name(&config.global.name);
+        let system = System::from_config(config).await?;
+        // Held before building, so a failed build or start is torn down too
+        let mut test_system = TestSystem {
+            system: Some(system),
+        };
+        test_system.system_mut().build().await?;
+        test_system.system_mut().start().await?;
+        Ok(test_system)
+    }
+
+    pub fn system(&self) -> &System {
+        // NOTE: only taken on drop or teardown
+        self.system.as_ref().unwrap()
+    }
+
+    fn system_mut(&mut self) -> &mut System {
+        self.system.as_mut().unwrap()
+    }
+
+    pub fn name(&self) -> &SystemName {
+        &self.system().config().global.name
+    }
+
+    /// A handle to one of the system's machines
+    pub fn machine(&self, name: &str) -> Result<Machine<'_>> {
+        let system = self.system();
+        let machine = system
+            .config()
+            .machines
+            .iter()
+            .find(|m| m.base.name.as_str() == name)
+            .ok_or_else(|| anyhow!("machine '{name}' not found"))?;
+        let container = system.get_container_by_component_name(name)?;
+        Ok(Machine {
+            system,
+            name: machine.base.name.clone(),
+            container,
+        })
+    }
+
+    /// Stop and remove the system, reporting any errors that dropping it would ignore
+    pub async fn teardown(mut self) -> Result<()> {
+        match self.system.take() {
+            Some(mut system) => system.remove().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TestSystem {
+    fn drop(&mut self) {
+        let Some(mut system) = self.system.take() else {
+            return;
+        };
+        // Drop can't be async and usually runs within the test's runtime, so the
+        // system is torn down on a separate thread with its own runtime
+        let teardown = thread::spawn(move || -> Result<()> {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            rt.block_on(system.remove())
+        });
+        match teardown.join() {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => warn!(error = %e, "Failed to tear down the test system"),
+            Err(_) => warn!("Tearing down the test system panicked"),
+        }
+    }
+}
+
+/// A machine of a [`TestSystem`]
+pub struct Machine<'a> {
+    system: &'a System,
+    name: MachineName,
+    container: &'a Container,
+}
+
+impl<'a> Machine<'a> {
+    pub fn name(&self) -> &MachineName {
+        &self.name
+    }
+
+    /// Run a command in the machine's container, returning its output. Fails if the
+    /// command does.
+    pub async fn exec<I, S>(&self, cmd: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cmd: Vec<String> = cmd.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        self.container.exec(&cmd).await
+    }
+
+    /// Wait for a line of the machine's container output to match a regex, returning
+    /// the line. The output is searched from when the container was last started,
+    /// so the output of earlier runs doesn't match.
+    pub async fn expect_output(&self, pattern: &str, timeout: Duration) -> Result<String> {
+        let pattern = Regex::new(pattern)?;
+        let since = self.container.started_at().unwrap_or(UNIX_EPOCH);
+        tokio::time::timeout(timeout, self.container.wait_for_output(&pattern, since))
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "no output of '{}' matched '{pattern}' within {timeout:?}",
+                    self.name
+                )
+            })?
+    }
+
+    /// A handle to one of the machine's UARTs, requires a socket terminal `port` on
+    /// the UART's connector
+    pub fn uart(&self, connection: &str) -> Result<Uart<'a>> {
+        let connection = ConnectionName::new_canonicalize(connection)
+            .ok_or_else(|| anyhow!("invalid connection name '{connection}'"))?;
+        Ok(Uart {
+            system: self.system,
+            machine: self.name.clone(),
+            connection,
+        })
+    }
+
+    /// Run Renode monitor commands in the context of this machine, returning the
+    /// monitor's output
+    pub async fn monitor<S: AsRef<str>>(&self, cmds: &[S]) -> Result<String> {
+        let cmds: Vec<String> = cmds.iter().map(|s| s.as_ref().to_owned()).collect();
+        self.system.renode_monitor(&self.name, &cmds).await
+    }
+
+    /// Send a QMP command to this QEMU machine, e.g. `{"execute":"query-status"}`,
+    /// returning the server's responses
+    pub async fn qmp(&self, command: &str) -> Result<String> {
+        self.system.qmp(&self.name, command).await
+    }
+}
+
+/// A UART of a Renode [`Machine`].
+///
+/// Expected output is an extended regular expression matched against each line
+/// received after the call starts.
+pub struct Uart<'a> {
+    system: &'a System,
+    machine: MachineName,
+    connection: ConnectionName,
+}
+
+impl Uart<'_> {
+    pub async fn write(&self, data: &str) -> Result<()> {
+        self.exchange(Some(data), None, Duration::ZERO).await
+    }
+
+    /// Wait for a line of output to match
+    pub async fn expect(&self, pattern: &str, timeout: Duration) -> Result<()> {
+        self.exchange(None, Some(pattern), timeout).await
+    }
+
+    /// Write, then wait for a line of the response to match
+    pub async fn write_expect(&self, data: &str, pattern: &str, timeout: Duration) -> Result<()> {
+        self.exchange(Some(data), Some(pattern), timeout).await
+    }
+
+    async fn exchange(
+        &self,
+        data: Option<&str>,
+        pattern: Option<&str>,
+        timeout: Duration,
+    ) -> Result<()> {
+        self.system
+            .uart_exchange(&self.machine, &self.connection, data, pattern, timeout)
+            .await
+    }
+}
+
+fn unique_system_name(name: &SystemName) -> SystemName {
+    let n = TEST_SYSTEM_COUNT.fetch_add(1, Ordering::Relaxed);
+    SystemName::new_canonicalize(format!("{name}-{}-{n}", process::id()))
+        .expect("non-empty system name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_system_names() {
+        let name = SystemName::new_canonicalize("my system").unwrap();
+        let a = unique_system_name(&name);
+        let b = unique_system_name(&name);
+        assert_ne!(a, b);
+        assert!(a.as_str().starts_with("my_system-"));
+    }
+}
@@ -100,10 +100,8 @@ impl<N: Component + fmt::Display + Clone> ComponentGraph<N> {
         // just immediate neighboring components
         let mut containers_with_dups = BTreeSet::new();
         for node_idx in g.node_indices() {
-            let comp_name = g
-                .node_weight(node_idx)
-                .map(|c| c.name())
-                .ok_or(InconsistencyError)?;
+            let comp = g.node_weight(node_idx).ok_or(InconsistencyError)?;
+            let comp_name = comp.name();
 
             // Always start with 1-to-1 container-to-component relationship
             let mut container = Container::from(comp_name);
@@ -112,12 +110,13 @@ impl<N: Component + fmt::Display + Clone> ComponentGraph<N> {
             for edge in g.edges(node_idx) {
                 let connection = edge.weight();
 
-                if connection.kind().is_restricted_to_common_conatainer() {
-                    let other_comp = g
-                        .node_weight(edge.target())
-                        .map(|c| c.name())
-                        .ok_or(InconsistencyError)?;
-                    container.components.insert(other_comp);
+                let other_comp = g.node_weight(edge.target()).ok_or(InconsistencyError)?;
+
+                // Worlds reach machines through topic bridges on the host network
+                let involves_world = comp.provider().is_world() || other_comp.provider().is_world();
+
+                if connection.kind().is_restricted_to_common_conatainer() && !involves_world {
+                    container.components.insert(other_comp.name());
                 }
             }
             containers_with_dups.insert(container);
@@ -1,16 +1,22 @@
 use crate::{
     component::{Component, ComponentConnector},
-    display,
+    display::{self, VncDisplay},
+    network_impairment::{NetworkImpairment, NetworkImpairmentError},
+    resources::{ResourceLimits, ResourceLimitsError},
+    restart::{RestartPolicy, RestartPolicyError},
     types::{
-        ComponentName, ConnectionKind, ConnectionName, EnvironmentVariableKeyValuePairs,
-        EnvironmentVariableMergeConflict, HostToGuestAssetPathMergeConflict, HostToGuestAssetPaths,
-        InterfaceName, MachineName, ProviderKind, SystemName, WorldName,
+        Capability, ComponentName, ConnectionKind, ConnectionName, Device,
+        EnvironmentVariableKeyValuePairs, EnvironmentVariableMergeConflict,
+        HostToGuestAssetPathMergeConflict, HostToGuestAssetPaths, InterfaceName, MachineName,
+        ProviderKind, PublishedPort, StorageName, SystemName, WorldName,
     },
 };
 use conductor_config::{
-    ConnectorPropertiesError, ContainerMachineProvider, GazeboWorldProvider,
-    GpioConnectorProperties, NetworkConnectorProperties, QemuMachineProvider,
-    RenodeMachineProvider, UartConnectorProperties,
+    BusRole, CanConnectorProperties, ConnectorPropertiesError, ContainerMachineProvider, Decimal,
+    DisplayProtocol, GazeboWorldProvider, GpioConnectorProperties, I2cConnectorProperties,
+    NetworkConnectorProperties, ProviderImage, QemuMachineProvider, RenodeMachineProvider,
+    SpiConnectorProperties, TopicDirection, UartConnectorProperties, WirelessMedium,
+    WirelessNetworkConnectorProperties,
 };
 use derive_more::{Display, From};
 use std::{
@@ -33,6 +39,16 @@ pub enum ConfigError {
     DupMachineConnector(MachineName, ConnectionName),
     #[error("World '{_0}' has a invalid duplicate connector '{_1}'")]
     DupWorldConnector(WorldName, ConnectionName),
+    #[error(
+        "World connector '{_0}' bridges a topic onto a {_1} connection, which isn't supported"
+    )]
+    UnsupportedWorldConnectorBridge(ConnectionName, ConnectionKind),
+    #[error("World connector '{_0}' can't bridge a topic from a {_1} connection into the world")]
+    UnsupportedWorldConnectorBridgeDirection(ConnectionName, ConnectionKind),
+    #[error("World connector '{_0}' must set both a topic and a message type")]
+    IncompleteWorldConnectorBridge(ConnectionName),
+    #[error("World connector '{_0}' bridges onto a network connection and requires a port")]
+    NoWorldConnectorBridgePort(ConnectionName),
     #[error("A machine connector references a connection '{_0}' that isn't defined")]
     MissingMachineConnectorConnection(ConnectionName),
     #[error("A world connector references a connection '{_0}' that isn't defined")]
@@ -57,9 +73,63 @@ pub enum ConfigError {
     DupMachine(MachineName),
     #[error("Found duplicate worlds with name '{_0}'")]
     DupWorld(WorldName),
+    #[error("A storage must have a name")]
+    EmptyStorageName,
+    #[error("Found duplicate storages with name '{_0}'")]
+    DupStorage(StorageName),
+    #[error("The image '{_0:?}' for storage '{_1}' does not exist")]
+    NonExistentStorageImage(PathBuf, StorageName),
+    #[error("The image '{_0:?}' for storage '{_1}' is not a file")]
+    StorageImageNotAFile(PathBuf, StorageName),
+    #[error("Machine '{_0}' references a storage '{_1}' that isn't defined")]
+    MissingMachineStorage(MachineName, StorageName),
+    #[error("Machine '{_0}' has a invalid duplicate storage '{_1}'")]
+    DupMachineStorage(MachineName, StorageName),
+    #[error("Machine '{_0}' uses the {_1} provider, which doesn't support storage")]
+    UnsupportedMachineStorage(MachineName, ProviderKind),
+    #[error("Machine '{_0}' storage '{_1}' requires an interface")]
+    NoMachineStorageInterface(MachineName, StorageName),
+    #[error("Machine '{_0}' has an invalid device '{_1}', expected '<host-path>[:<guest-path>[:<permissions>]]' with absolute paths")]
+    InvalidMachineDevice(MachineName, String),
+    #[error("Machine '{_0}' has an invalid capability '{_1}'")]
+    InvalidMachineCapability(MachineName, String),
+    #[error("Machine '{_0}' has an invalid port '{_1}', expected '<port>[/<tcp|udp>]'")]
+    InvalidMachinePort(MachineName, String),
+    #[error("World '{_0}' has an invalid port '{_1}', expected '<port>[/<tcp|udp>]'")]
+    InvalidWorldPort(WorldName, String),
+    #[error("Machine '{_0}' uses the {_1} provider, which doesn't support a GDB server")]
+    UnsupportedMachineGdbServer(MachineName, ProviderKind),
+    #[error("Machine '{_0}' has an invalid GDB server port 0")]
+    InvalidMachineGdbServerPort(MachineName),
+    #[error("Invalid time-scale '{_0}', must be positive")]
+    InvalidTimeScale(Decimal),
+    #[error("Machine '{_0}' uses the {_1} provider, which doesn't support {_2} connection '{_3}'")]
+    UnsupportedMachineConnection(MachineName, ProviderKind, ConnectionKind, ConnectionName),
+    #[error("The {_0} connection '{_1}' must have a controller connector")]
+    NoBusController(ConnectionKind, ConnectionName),
+    #[error("The {_0} connection '{_1}' can only have one controller connector")]
+    MultipleBusControllers(ConnectionKind, ConnectionName),
+    #[error("Renode machines '{_0}' and '{_1}' share a container but don't agree on its image")]
+    ConflictingRenodeImages(MachineName, MachineName),
+    #[error("Machine '{_0}' has a {_1} target connector '{_2}' without an address")]
+    NoBusTargetAddress(MachineName, ConnectionKind, ConnectionName),
+    #[error("The {_0} connection '{_1}' has multiple targets with address {_2:#x}")]
+    DupBusTargetAddress(ConnectionKind, ConnectionName, u16),
+    #[error("Machine '{_0}' has an I2C connector '{_1}' with an invalid 7-bit address {_2:#x}")]
+    InvalidI2cAddress(MachineName, ConnectionName, u16),
+    #[error("Wireless network connection '{_0}' has an invalid range '{_1}', must be positive")]
+    InvalidWirelessRange(ConnectionName, Decimal),
+    #[error("Machine '{_0}' has no position on its connector to '{_1}', a wireless network connection with a range")]
+    NoWirelessPosition(MachineName, ConnectionName),
     #[error(transparent)]
     ConnectorProperties(#[from] ConnectorPropertiesError),
     #[error(transparent)]
+    NetworkImpairment(#[from] NetworkImpairmentError),
+    #[error(transparent)]
+    ResourceLimits(#[from] ResourceLimitsError),
+    #[error(transparent)]
+    RestartPolicy(#[from] RestartPolicyError),
+    #[error(transparent)]
     EnvironmentVariableMergeConflict(#[from] EnvironmentVariableMergeConflict),
     #[error(transparent)]
     HostToGuestAssetPathMergeConflict(#[from] HostToGuestAssetPathMergeConflict),
@@ -79,8 +149,7 @@ pub struct Config {
     pub worlds: Vec<World>,
     pub machines: Vec<Machine>,
     pub connections: BTreeSet<Connection>,
-    // TODO
-    //pub storages: Vec<Storage>,
+    pub storages: Vec<Storage>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -88,8 +157,14 @@ pub struct Global {
     pub name: SystemName,
     pub display: Option<String>,
     pub xauthority: Option<PathBuf>,
+    /// The host's Wayland socket
+    pub wayland_socket: Option<PathBuf>,
+    /// When set, GUI containers use a virtual display served over VNC instead
+    /// of the host's display, this is the first GUI container's display
+    pub vnc: Option<VncDisplay>,
     pub environment_variables: EnvironmentVariableKeyValuePairs,
-    // TODO display (else defaults to $DISPLAY when needed)
+    /// The ratio of simulated time to wall-clock time
+    pub time_scale: Option<Decimal>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
@@ -105,6 +180,10 @@ pub struct BaseWorld {
     pub environment_variables: EnvironmentVariableKeyValuePairs,
     pub assets: HostToGuestAssetPaths,
     pub connectors: Vec<WorldConnector>,
+    pub resources: ResourceLimits,
+    pub restart: RestartPolicy,
+    /// Ports the container listens on, on the host's network
+    pub ports: Vec<PublishedPort>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, From)]
@@ -124,6 +203,18 @@ impl WorldProvider {
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct WorldConnector {
     pub name: ConnectionName,
+    /// When set, a gz-transport topic is bridged onto the connection, otherwise
+    /// the connection only shares the world's gz-transport partition
+    pub bridge: Option<TopicBridge>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct TopicBridge {
+    pub topic: String,
+    pub message_type: String,
+    pub direction: TopicDirection,
+    /// UDP port, only used for network connections
+    pub port: Option<u16>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
@@ -140,6 +231,30 @@ pub struct BaseMachine {
     pub environment_variables: EnvironmentVariableKeyValuePairs,
     pub assets: HostToGuestAssetPaths,
     pub connectors: Vec<MachineConnector>,
+    pub storage: Vec<MachineStorage>,
+    pub resources: ResourceLimits,
+    pub restart: RestartPolicy,
+    /// Host devices passed through to the machine's container
+    pub devices: Vec<Device>,
+    /// Extra capabilities for the machine's container
+    pub capabilities: Vec<Capability>,
+    /// Ports the container listens on, on the host's network
+    pub ports: Vec<PublishedPort>,
+    pub gdb_server: Option<GdbServer>,
+}
+
+/// A GDB remote server exposed by the machine's emulator
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct GdbServer {
+    pub port: u16,
+    /// The machine is halted until a debugger connects
+    pub wait: bool,
+    /// Extra gdb commands to run after connecting
+    pub init_commands: Vec<String>,
+}
+
+impl GdbServer {
+    pub const DEFAULT_PORT: u16 = 3333;
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, From)]
@@ -160,6 +275,39 @@ impl MachineProvider {
     }
 }
 
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct MachineStorage {
+    pub storage: Storage,
+    pub interface: Option<InterfaceName>,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, From)]
+pub enum Storage {
+    Virtio(VirtioStorage),
+}
+
+impl Storage {
+    pub fn name(&self) -> &StorageName {
+        match self {
+            Storage::Virtio(s) => &s.name,
+        }
+    }
+
+    pub fn image(&self) -> &Path {
+        match self {
+            Storage::Virtio(s) => &s.image,
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct VirtioStorage {
+    pub name: StorageName,
+    pub image: PathBuf,
+    pub copy_on_write: bool,
+    pub read_only: bool,
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct MachineConnector {
     pub name: ConnectionName,
@@ -172,6 +320,10 @@ pub enum ConnectorProperties {
     Uart(UartConnectorProperties),
     Gpio(GpioConnectorProperties),
     Network(NetworkConnectorProperties),
+    WirelessNetwork(WirelessNetworkConnectorProperties),
+    Can(CanConnectorProperties),
+    I2c(I2cConnectorProperties),
+    Spi(SpiConnectorProperties),
 }
 
 impl ConnectorProperties {
@@ -183,6 +335,10 @@ impl ConnectorProperties {
             Uart(_p) => None,
             Gpio(p) => Some(p.source_pin.is_some()),
             Network(_p) => None,
+            WirelessNetwork(_p) => None,
+            Can(_p) => None,
+            I2c(_p) => None,
+            Spi(_p) => None,
         }
     }
 
@@ -192,6 +348,10 @@ impl ConnectorProperties {
             Uart(p) => p.pipe.is_some() || p.pty.is_some() || p.port.is_some(),
             Gpio(_p) => false,
             Network(_p) => false,
+            WirelessNetwork(_p) => false,
+            Can(_p) => false,
+            I2c(_p) => false,
+            Spi(_p) => false,
         }
     }
 
@@ -201,6 +361,32 @@ impl ConnectorProperties {
             Uart(_) => ConnectionKind::Uart,
             Gpio(_) => ConnectionKind::Gpio,
             Network(_) => ConnectionKind::Network,
+            WirelessNetwork(_) => ConnectionKind::WirelessNetwork,
+            Can(_) => ConnectionKind::Can,
+            I2c(_) => ConnectionKind::I2c,
+            Spi(_) => ConnectionKind::Spi,
+        }
+    }
+
+    /// Returns the role and address of I2C and SPI connectors
+    pub fn bus_role_and_address(&self) -> Option<(BusRole, Option<u16>)> {
+        use ConnectorProperties::*;
+        match self {
+            I2c(p) => Some((p.role, p.address)),
+            Spi(p) => Some((p.role, p.address)),
+            _ => None,
+        }
+    }
+
+    /// Returns the connector-level network impairment, if any
+    pub fn network_impairment(&self) -> Result<Option<NetworkImpairment>, NetworkImpairmentError> {
+        match self {
+            ConnectorProperties::Network(p) => p
+                .impairment
+                .as_ref()
+                .map(NetworkImpairment::try_from)
+                .transpose(),
+            _ => Ok(None),
         }
     }
 }
@@ -214,6 +400,14 @@ pub enum Connection {
     Gpio(GpioConnection),
     #[display(fmt = "<{}> {}", "self.kind()", "self.name()")]
     Network(NetworkConnection),
+    #[display(fmt = "<{}> {}", "self.kind()", "self.name()")]
+    WirelessNetwork(WirelessNetworkConnection),
+    #[display(fmt = "<{}> {}", "self.kind()", "self.name()")]
+    Can(CanConnection),
+    #[display(fmt = "<{}> {}", "self.kind()", "self.name()")]
+    I2c(I2cConnection),
+    #[display(fmt = "<{}> {}", "self.kind()", "self.name()")]
+    Spi(SpiConnection),
 }
 
 impl Connection {
@@ -223,6 +417,10 @@ impl Connection {
             Uart(c) => &c.name,
             Gpio(c) => &c.name,
             Network(c) => &c.name,
+            WirelessNetwork(c) => &c.name,
+            Can(c) => &c.name,
+            I2c(c) => &c.name,
+            Spi(c) => &c.name,
         }
     }
 
@@ -232,12 +430,28 @@ impl Connection {
             Uart(_) => ConnectionKind::Uart,
             Gpio(_) => ConnectionKind::Gpio,
             Network(_) => ConnectionKind::Network,
+            WirelessNetwork(_) => ConnectionKind::WirelessNetwork,
+            Can(_) => ConnectionKind::Can,
+            I2c(_) => ConnectionKind::I2c,
+            Spi(_) => ConnectionKind::Spi,
         }
     }
 
     pub fn is_network(&self) -> bool {
         matches!(self.kind(), ConnectionKind::Network)
     }
+
+    pub fn is_can(&self) -> bool {
+        matches!(self.kind(), ConnectionKind::Can)
+    }
+
+    /// Returns the connection-level network impairment for network connections
+    pub fn network_impairment(&self) -> Option<&NetworkImpairment> {
+        match self {
+            Connection::Network(c) => Some(&c.impairment),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
@@ -256,25 +470,74 @@ pub struct GpioConnection {
 #[display(fmt = "{}", name)]
 pub struct NetworkConnection {
     pub name: ConnectionName,
+    pub impairment: NetworkImpairment,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(fmt = "{}", name)]
+pub struct CanConnection {
+    pub name: ConnectionName,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(fmt = "{}", name)]
+pub struct I2cConnection {
+    pub name: ConnectionName,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(fmt = "{}", name)]
+pub struct SpiConnection {
+    pub name: ConnectionName,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(fmt = "{}", name)]
+pub struct WirelessNetworkConnection {
+    pub name: ConnectionName,
+    pub medium: WirelessMedium,
+    pub range: Option<Decimal>,
 }
 
 impl From<conductor_config::Global> for Global {
     fn from(value: conductor_config::Global) -> Self {
+        let uses_vnc = value.display.as_deref() == Some(display::VNC_DISPLAY_MODE);
+        let uses_x11 = !uses_vnc && value.display_protocol != Some(DisplayProtocol::Wayland);
+        let uses_wayland = !uses_vnc && value.display_protocol != Some(DisplayProtocol::X11);
         Self {
             name: value
                 .name
                 .as_ref()
                 .and_then(SystemName::new_canonicalize)
                 .unwrap_or_default(),
-            display: value
-                .display
-                .or_else(|| env::var(display::DISPLAY_ENV_VAR).ok()),
+            display: if uses_x11 {
+                value
+                    .display
+                    .or_else(|| env::var(display::DISPLAY_ENV_VAR).ok())
+            } else {
+                None
+            },
             xauthority: value.xauthority.or_else(|| {
                 env::var(display::XAUTHORITY_ENV_VAR)
                     .map(PathBuf::from)
                     .ok()
             }),
+            wayland_socket: if uses_wayland {
+                value
+                    .wayland_display
+                    .or_else(|| env::var(display::WAYLAND_DISPLAY_ENV_VAR).ok())
+                    .and_then(|d| display::host_wayland_socket_path(&d))
+            } else {
+                None
+            },
+            vnc: uses_vnc.then(|| {
+                VncDisplay::new(
+                    value.vnc_port.unwrap_or(display::DEFAULT_VNC_PORT),
+                    value.novnc_port,
+                )
+            }),
             environment_variables: value.environment_variables.into(),
+            time_scale: value.time_scale,
         }
     }
 }
@@ -302,12 +565,24 @@ impl TryFrom<(conductor_config::World, &BTreeSet<Connection>)> for World {
             }
             connectors.push(c);
         }
+        let resources = ResourceLimits::try_from(&value.resources)?;
+        let restart = RestartPolicy::try_from(&value.restart)?;
+        let mut ports = Vec::with_capacity(value.ports.len());
+        for p in value.ports.iter() {
+            let Ok(port) = p.parse::<PublishedPort>() else {
+                return Err(ConfigError::InvalidWorldPort(name, p.clone()));
+            };
+            ports.push(port);
+        }
         Ok(Self {
             base: BaseWorld {
                 name,
                 environment_variables: value.environment_variables.into(),
                 assets: value.assets.into(),
                 connectors,
+                resources,
+                restart,
+                ports,
             },
             provider: provider.into(),
         })
@@ -320,10 +595,42 @@ impl TryFrom<(conductor_config::WorldConnector, &BTreeSet<Connection>)> for Worl
     fn try_from(
         values: (conductor_config::WorldConnector, &BTreeSet<Connection>),
     ) -> Result<Self, Self::Error> {
-        let (value, _connections) = values;
+        let (value, connections) = values;
         let name =
             ConnectionName::new_canonicalize(value.name).ok_or(ConfigError::EmptyConnectorName)?;
-        Ok(Self { name })
+        let connection = connections
+            .iter()
+            .find(|c| c.name() == &name)
+            .ok_or_else(|| ConfigError::MissingWorldConnectorConnection(name.clone()))?;
+        let bridge = match (value.topic, value.message_type) {
+            (None, None) => None,
+            (Some(topic), Some(message_type)) => {
+                let direction = value.direction.unwrap_or_default();
+                match connection.kind() {
+                    ConnectionKind::Uart => (),
+                    ConnectionKind::Gpio if direction == TopicDirection::ToMachine => (),
+                    ConnectionKind::Gpio => {
+                        return Err(ConfigError::UnsupportedWorldConnectorBridgeDirection(
+                            name,
+                            ConnectionKind::Gpio,
+                        ))
+                    }
+                    ConnectionKind::Network if value.port.is_some() => (),
+                    ConnectionKind::Network => {
+                        return Err(ConfigError::NoWorldConnectorBridgePort(name))
+                    }
+                    kind => return Err(ConfigError::UnsupportedWorldConnectorBridge(name, kind)),
+                }
+                Some(TopicBridge {
+                    topic,
+                    message_type,
+                    direction,
+                    port: value.port,
+                })
+            }
+            _ => return Err(ConfigError::IncompleteWorldConnectorBridge(name)),
+        };
+        Ok(Self { name, bridge })
     }
 }
 
@@ -335,13 +642,13 @@ impl From<conductor_config::WorldProvider> for WorldProvider {
     }
 }
 
-impl TryFrom<(conductor_config::Machine, &BTreeSet<Connection>)> for Machine {
+impl TryFrom<(conductor_config::Machine, &BTreeSet<Connection>, &[Storage])> for Machine {
     type Error = ConfigError;
 
     fn try_from(
-        values: (conductor_config::Machine, &BTreeSet<Connection>),
+        values: (conductor_config::Machine, &BTreeSet<Connection>, &[Storage]),
     ) -> Result<Self, Self::Error> {
-        let (value, connections) = values;
+        let (value, connections, storages) = values;
         let name = value
             .name
             .as_ref()
@@ -351,26 +658,110 @@ impl TryFrom<(conductor_config::Machine, &BTreeSet<Connection>)> for Machine {
             .provider
             .ok_or_else(|| ConfigError::NoMachineProvider(name.clone()))?
             .into();
-        // TODO - the expectation and way we handle bin fields on a per-component
-        // basis will change
-        let bin = if matches!(provider.kind(), ProviderKind::Container) {
-            value.bin
-        } else {
-            // These providers require a bin field
+        let bin = if provider.kind().requires_machine_bin() {
             Some(
                 value
                     .bin
                     .ok_or_else(|| ConfigError::NoMachineBin(name.clone()))?,
             )
+        } else {
+            value.bin
         };
         let mut connectors = Vec::with_capacity(value.connectors.len());
         for c in value.connectors.into_iter() {
             let c = MachineConnector::try_from((c, connections))?;
+            let kind = c.properties.kind();
+            if kind.is_restricted_to_renode() && provider.kind() != ProviderKind::Renode {
+                return Err(ConfigError::UnsupportedMachineConnection(
+                    name,
+                    provider.kind(),
+                    kind,
+                    c.name,
+                ));
+            }
+            if let Some((role, address)) = c.properties.bus_role_and_address() {
+                match (role, address) {
+                    (BusRole::Target, None) => {
+                        return Err(ConfigError::NoBusTargetAddress(name, kind, c.name));
+                    }
+                    (_, Some(addr)) if kind == ConnectionKind::I2c && addr > 0x7F => {
+                        return Err(ConfigError::InvalidI2cAddress(name, c.name, addr));
+                    }
+                    _ => (),
+                }
+            }
             if connectors.contains(&c) {
                 return Err(ConfigError::DupMachineConnector(name, c.name));
             }
             connectors.push(c);
         }
+        let mut storage: Vec<MachineStorage> = Vec::with_capacity(value.storage.len());
+        for s in value.storage.into_iter() {
+            let storage_name =
+                StorageName::new_canonicalize(&s.name).ok_or(ConfigError::EmptyStorageName)?;
+            match provider.kind() {
+                ProviderKind::Qemu => (),
+                ProviderKind::Renode if s.interface.is_some() => (),
+                ProviderKind::Renode => {
+                    return Err(ConfigError::NoMachineStorageInterface(name, storage_name));
+                }
+                kind => return Err(ConfigError::UnsupportedMachineStorage(name, kind)),
+            }
+            if storage.iter().any(|ms| ms.storage.name() == &storage_name) {
+                return Err(ConfigError::DupMachineStorage(name, storage_name));
+            }
+            let Some(st) = storages.iter().find(|st| st.name() == &storage_name) else {
+                return Err(ConfigError::MissingMachineStorage(name, storage_name));
+            };
+            storage.push(MachineStorage {
+                storage: st.clone(),
+                interface: s
+                    .interface
+                    .as_ref()
+                    .and_then(InterfaceName::new_canonicalize),
+            });
+        }
+        let resources = ResourceLimits::try_from(&value.resources)?;
+        let restart = RestartPolicy::try_from(&value.restart)?;
+        let mut devices = Vec::with_capacity(value.devices.len());
+        for d in value.devices.iter() {
+            let Ok(device) = d.parse::<Device>() else {
+                return Err(ConfigError::InvalidMachineDevice(name, d.clone()));
+            };
+            devices.push(device);
+        }
+        let mut capabilities = Vec::with_capacity(value.capabilities.len());
+        for c in value.capabilities.iter() {
+            let Some(cap) = Capability::new_canonicalize(c) else {
+                return Err(ConfigError::InvalidMachineCapability(name, c.clone()));
+            };
+            capabilities.push(cap);
+        }
+        let mut ports = Vec::with_capacity(value.ports.len());
+        for p in value.ports.iter() {
+            let Ok(port) = p.parse::<PublishedPort>() else {
+                return Err(ConfigError::InvalidMachinePort(name, p.clone()));
+            };
+            ports.push(port);
+        }
+        let gdb_server = match value.gdb_server {
+            Some(gdb) => {
+                match provider.kind() {
+                    ProviderKind::Renode | ProviderKind::Qemu => (),
+                    kind => return Err(ConfigError::UnsupportedMachineGdbServer(name, kind)),
+                }
+                let port = gdb.port.unwrap_or(GdbServer::DEFAULT_PORT);
+                if port == 0 {
+                    return Err(ConfigError::InvalidMachineGdbServerPort(name));
+                }
+                Some(GdbServer {
+                    port,
+                    wait: gdb.wait.unwrap_or(false),
+                    init_commands: gdb.init_commands,
+                })
+            }
+            None => None,
+        };
         Ok(Self {
             base: BaseMachine {
                 name,
@@ -378,6 +769,13 @@ impl TryFrom<(conductor_config::Machine, &BTreeSet<Connection>)> for Machine {
                 environment_variables: value.environment_variables.into(),
                 assets: value.assets.into(),
                 connectors,
+                storage,
+                resources,
+                restart,
+                devices,
+                capabilities,
+                ports,
+                gdb_server,
             },
             provider,
         })
@@ -402,11 +800,19 @@ impl TryFrom<(conductor_config::MachineConnector, &BTreeSet<Connection>)> for Ma
         // TODO - do semantic checks on props
         // GPIO can only specify src or dest pin, not both
         // UART can only have one kind of host integration
-        let properties = match connection.kind() {
+        let properties: ConnectorProperties = match connection.kind() {
             ConnectionKind::Uart => UartConnectorProperties::try_from(&value)?.into(),
             ConnectionKind::Gpio => GpioConnectorProperties::try_from(&value)?.into(),
             ConnectionKind::Network => NetworkConnectorProperties::try_from(&value)?.into(),
+            ConnectionKind::WirelessNetwork => {
+                WirelessNetworkConnectorProperties::try_from(&value)?.into()
+            }
+            ConnectionKind::Can => CanConnectorProperties::try_from(&value)?.into(),
+            ConnectionKind::I2c => I2cConnectorProperties::try_from(&value)?.into(),
+            ConnectionKind::Spi => SpiConnectorProperties::try_from(&value)?.into(),
         };
+        // Surface impairment errors at config-read time rather than at deployment
+        properties.network_impairment()?;
         Ok(Self {
             name,
             interface,
@@ -433,6 +839,12 @@ impl TryFrom<conductor_config::Connection> for Connection {
             conductor_config::Connection::Uart(c) => UartConnection::try_from(c)?.into(),
             conductor_config::Connection::Gpio(c) => GpioConnection::try_from(c)?.into(),
             conductor_config::Connection::Network(c) => NetworkConnection::try_from(c)?.into(),
+            conductor_config::Connection::WirelessNetwork(c) => {
+                WirelessNetworkConnection::try_from(c)?.into()
+            }
+            conductor_config::Connection::Can(c) => CanConnection::try_from(c)?.into(),
+            conductor_config::Connection::I2c(c) => I2cConnection::try_from(c)?.into(),
+            conductor_config::Connection::Spi(c) => SpiConnection::try_from(c)?.into(),
         })
     }
 }
@@ -466,6 +878,87 @@ impl TryFrom<conductor_config::NetworkConnection> for NetworkConnection {
         Ok(Self {
             name: ConnectionName::new_canonicalize(value.name)
                 .ok_or(ConfigError::EmptyConnectionName)?,
+            impairment: value
+                .impairment
+                .as_ref()
+                .map(NetworkImpairment::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<conductor_config::CanConnection> for CanConnection {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::CanConnection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: ConnectionName::new_canonicalize(value.name)
+                .ok_or(ConfigError::EmptyConnectionName)?,
+        })
+    }
+}
+
+impl TryFrom<conductor_config::Storage> for Storage {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::Storage) -> Result<Self, Self::Error> {
+        Ok(match value {
+            conductor_config::Storage::Virtio(s) => VirtioStorage::try_from(s)?.into(),
+        })
+    }
+}
+
+impl TryFrom<conductor_config::VirtioStorage> for VirtioStorage {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::VirtioStorage) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: StorageName::new_canonicalize(value.name).ok_or(ConfigError::EmptyStorageName)?,
+            image: value.image,
+            copy_on_write: value.copy_on_write.unwrap_or(true),
+            read_only: value.read_only.unwrap_or(false),
+        })
+    }
+}
+
+impl TryFrom<conductor_config::I2cConnection> for I2cConnection {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::I2cConnection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: ConnectionName::new_canonicalize(value.name)
+                .ok_or(ConfigError::EmptyConnectionName)?,
+        })
+    }
+}
+
+impl TryFrom<conductor_config::SpiConnection> for SpiConnection {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::SpiConnection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: ConnectionName::new_canonicalize(value.name)
+                .ok_or(ConfigError::EmptyConnectionName)?,
+        })
+    }
+}
+
+impl TryFrom<conductor_config::WirelessNetworkConnection> for WirelessNetworkConnection {
+    type Error = ConfigError;
+
+    fn try_from(value: conductor_config::WirelessNetworkConnection) -> Result<Self, Self::Error> {
+        let name =
+            ConnectionName::new_canonicalize(value.name).ok_or(ConfigError::EmptyConnectionName)?;
+        if let Some(range) = value.range {
+            if !(range.0.is_finite() && range.0 > 0.0) {
+                return Err(ConfigError::InvalidWirelessRange(name, range));
+            }
+        }
+        Ok(Self {
+            name,
+            medium: value.medium,
+            range: value.range,
         })
     }
 }
@@ -548,6 +1041,12 @@ impl Config {
         let cfg = conductor_config::Config::read(&config_path)?;
         let cfg_dir = config_path.as_ref().parent();
 
+        let images = cfg.global.images.clone();
+        if let Some(time_scale) = cfg.global.time_scale {
+            if !(time_scale.0.is_finite() && time_scale.0 > 0.0) {
+                return Err(ConfigError::InvalidTimeScale(time_scale).into());
+            }
+        }
         let global = Global::from(cfg.global);
 
         let mut connections = BTreeSet::new();
@@ -558,6 +1057,35 @@ impl Config {
             }
         }
 
+        let mut storages: Vec<Storage> = Vec::with_capacity(cfg.storages.len());
+        for s in cfg.storages.into_iter() {
+            let mut s = Storage::try_from(s)?;
+            if storages.iter().any(|known_s| known_s.name() == s.name()) {
+                return Err(ConfigError::DupStorage(s.name().clone()).into());
+            }
+
+            // Convert relative paths on the host to absolute, where possible
+            let Storage::Virtio(vs) = &mut s;
+            if let Some(cfg_dir) = cfg_dir {
+                if vs.image.is_relative() {
+                    vs.image = cfg_dir.join(&vs.image);
+                }
+            }
+            if !vs.image.exists() {
+                return Err(ConfigError::NonExistentStorageImage(
+                    vs.image.clone(),
+                    vs.name.clone(),
+                )
+                .into());
+            }
+            if !vs.image.is_file() || vs.image.file_name().is_none() {
+                return Err(
+                    ConfigError::StorageImageNotAFile(vs.image.clone(), vs.name.clone()).into(),
+                );
+            }
+            storages.push(s);
+        }
+
         let mut worlds: Vec<World> = Vec::with_capacity(cfg.worlds.len());
         for w in cfg.worlds.into_iter() {
             let mut w = World::try_from((w, &connections))?;
@@ -574,6 +1102,12 @@ impl Config {
                 .merge(&global.environment_variables)
                 .map_err(ConfigError::from)?;
 
+            match &mut w.provider {
+                WorldProvider::Gazebo(gp) => {
+                    resolve_provider_image(&mut gp.base_image, &images.gazebo, cfg_dir)
+                }
+            }
+
             // Convert relative paths on the host to absolute, where possible
             if let Some(cfg_dir) = cfg_dir {
                 match &mut w.provider {
@@ -628,7 +1162,7 @@ impl Config {
 
         let mut machines: Vec<Machine> = Vec::with_capacity(cfg.machines.len());
         for m in cfg.machines.into_iter() {
-            let mut m = Machine::try_from((m, &connections))?;
+            let mut m = Machine::try_from((m, &connections, storages.as_slice()))?;
             let contains_name_already = machines
                 .iter()
                 .any(|known_m| known_m.base.name == m.base.name);
@@ -642,6 +1176,16 @@ impl Config {
                 .merge(&global.environment_variables)
                 .map_err(ConfigError::from)?;
 
+            match &mut m.provider {
+                MachineProvider::Renode(p) => {
+                    resolve_provider_image(&mut p.base_image, &images.renode, cfg_dir)
+                }
+                MachineProvider::Qemu(p) => {
+                    resolve_provider_image(&mut p.base_image, &images.qemu, cfg_dir)
+                }
+                MachineProvider::Container(_) => (),
+            }
+
             // Convert relative paths on the host to absolute, where possible
             if let Some(cfg_dir) = cfg_dir {
                 if let Some(bin) = m.base.bin.as_mut() {
@@ -702,11 +1246,152 @@ impl Config {
             machines.push(m);
         }
 
+        check_bus_connectors(&connections, &machines)?;
+        check_renode_images(&connections, &machines)?;
+        check_wireless_positions(&connections, &machines)?;
+
         Ok(Self {
             global,
             worlds,
             machines,
             connections,
+            storages,
         })
     }
 }
+
+/// Applies the system-wide image override and converts relative paths
+/// on the host to absolute
+fn resolve_provider_image(
+    base_image: &mut ProviderImage,
+    system_image: &Option<String>,
+    cfg_dir: Option<&Path>,
+) {
+    if base_image.image.is_none() {
+        base_image.image = system_image.clone();
+    }
+    if let Some(cfg_dir) = cfg_dir {
+        for p in [&mut base_image.containerfile, &mut base_image.context]
+            .into_iter()
+            .flatten()
+        {
+            if p.is_relative() {
+                *p = cfg_dir.join(&p);
+            }
+        }
+    }
+}
+
+/// Renode machines connected by a connection that's restricted to a common
+/// container all run in the same container
+fn check_renode_images(
+    connections: &BTreeSet<Connection>,
+    machines: &[Machine],
+) -> Result<(), ConfigError> {
+    for conn in connections
+        .iter()
+        .filter(|c| c.kind().is_restricted_to_common_conatainer())
+    {
+        let mut renode_machines = machines.iter().filter_map(|m| match &m.provider {
+            MachineProvider::Renode(p)
+                if m.base.connectors.iter().any(|c| &c.name == conn.name()) =>
+            {
+                Some((&m.base.name, &p.base_image))
+            }
+            _ => None,
+        });
+        if let Some((first_name, first_image)) = renode_machines.next() {
+            for (name, image) in renode_machines {
+                if image != first_image {
+                    return Err(ConfigError::ConflictingRenodeImages(
+                        first_name.clone(),
+                        name.clone(),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Range-based loss depends on the distance between the radios, so every connector
+/// to a wireless network with a range needs a position
+fn check_wireless_positions(
+    connections: &BTreeSet<Connection>,
+    machines: &[Machine],
+) -> Result<(), ConfigError> {
+    for conn in connections.iter() {
+        let Connection::WirelessNetwork(WirelessNetworkConnection {
+            name,
+            range: Some(_),
+            ..
+        }) = conn
+        else {
+            continue;
+        };
+        for m in machines.iter() {
+            for c in m.base.connectors.iter().filter(|c| &c.name == name) {
+                let has_position = matches!(
+                    &c.properties,
+                    ConnectorProperties::WirelessNetwork(p) if p.position.is_some()
+                );
+                if !has_position {
+                    return Err(ConfigError::NoWirelessPosition(
+                        m.base.name.clone(),
+                        name.clone(),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// I2C and SPI connections need a controller (only one for SPI) and
+/// targets with distinct addresses
+fn check_bus_connectors(
+    connections: &BTreeSet<Connection>,
+    machines: &[Machine],
+) -> Result<(), ConfigError> {
+    for conn in connections
+        .iter()
+        .filter(|c| c.kind().is_controller_target_bus())
+    {
+        let mut controllers = 0;
+        let mut target_addresses = BTreeSet::new();
+        for (role, address) in machines
+            .iter()
+            .flat_map(|m| m.base.connectors.iter())
+            .filter(|c| &c.name == conn.name())
+            .filter_map(|c| c.properties.bus_role_and_address())
+        {
+            match (role, address) {
+                (BusRole::Controller, _) => controllers += 1,
+                (BusRole::Target, Some(addr)) => {
+                    if !target_addresses.insert(addr) {
+                        return Err(ConfigError::DupBusTargetAddress(
+                            conn.kind(),
+                            conn.name().clone(),
+                            addr,
+                        ));
+                    }
+                }
+                // Already checked by the machine connector conversion
+                (BusRole::Target, None) => (),
+            }
+        }
+        if controllers == 0 {
+            return Err(ConfigError::NoBusController(
+                conn.kind(),
+                conn.name().clone(),
+            ));
+        }
+        if conn.kind() == ConnectionKind::Spi && controllers > 1 {
+            return Err(ConfigError::MultipleBusControllers(
+                conn.kind(),
+                conn.name().clone(),
+            ));
+        }
+    }
+    Ok(())
+}
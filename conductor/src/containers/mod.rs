@@ -1,20 +1,38 @@
 use anyhow::{anyhow, bail, Context as _, Result};
 use bollard::{
-    container::{self, AttachContainerOptions, ListContainersOptions, StatsOptions},
-    image::{BuildImageOptions, CreateImageOptions, ListImagesOptions},
+    container::{
+        self, AttachContainerOptions, DownloadFromContainerOptions, ListContainersOptions,
+        LogsOptions, RemoveContainerOptions, StatsOptions, StopContainerOptions,
+        UploadToContainerOptions,
+    },
+    exec::CreateExecOptions,
+    image::{
+        BuildImageOptions, CommitContainerOptions, CreateImageOptions, ListImagesOptions,
+        TagImageOptions,
+    },
     models::{DeviceMapping, DeviceRequest, EndpointSettings, Mount, MountTypeEnum},
     Docker,
 };
 use data_encoding::HEXLOWER;
-use futures_util::StreamExt;
-use ring::digest::{Context, Digest, SHA256};
-use std::collections::HashMap;
+use futures_util::{stream, Stream, StreamExt};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use regex::Regex;
+use ring::digest::{digest, Context, Digest, SHA256};
+use std::collections::{BTreeMap, HashMap};
 use std::default::Default;
 use std::fs::{self, File};
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek};
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::io::ReaderStream;
 use tracing::{info, instrument, trace, warn};
 
+use crate::images::StockImage;
+use crate::resources::ResourceLimits;
+use crate::types::{Capability, Device};
+
 pub mod network;
 
 pub use network::{Network, NetworkState};
@@ -31,6 +49,13 @@ pub use bollard::exec::StartExecResults;
 pub use containers_api::conn::Multiplexer as StdIo;
 pub use containers_api::conn::TtyChunk as StdIoChunk;
 
+/// Only applied to containers, images are shared by containers with the same inputs
+const CONTAINER_NAME_LABEL: &str = "io.auxon.conductor.container";
+
+/// How often a stopped container is checked for having been restarted while
+/// following its output
+const FOLLOW_OUTPUT_INTERVAL: Duration = Duration::from_secs(1);
+
 type ContainerClient = Docker;
 
 #[derive(Debug, Default)]
@@ -38,11 +63,20 @@ pub struct ContainerBuilder {
     name: Option<String>,
     image: Option<String>,
     containerfile: Option<PathBuf>,
+    containerfile_content: Option<String>,
+    fallback_containerfile_content: Option<String>,
     context: Option<PathBuf>,
+    build_args: HashMap<String, String>,
     cmd: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    workdir: Option<String>,
+    user: Option<String>,
     mounts: Option<HashMap<String, String>>,
     env: Option<Vec<String>>,
     gpu_cap: bool,
+    resources: ResourceLimits,
+    devices: Vec<Device>,
+    capabilities: Vec<Capability>,
     networks: Vec<Network>,
 }
 
@@ -53,13 +87,24 @@ pub struct Container {
     image: Option<String>,
     containerfile: Option<PathBuf>,
     containerfile_digest: Option<Digest>,
+    containerfile_content: Option<String>,
+    fallback_containerfile_content: Option<String>,
     context: Option<PathBuf>,
     context_digest: Option<Digest>,
+    build_args: HashMap<String, String>,
     cmd: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    workdir: Option<String>,
+    user: Option<String>,
     mounts: Option<HashMap<String, String>>,
     env: Option<Vec<String>>,
     gpu_cap: bool,
+    resources: ResourceLimits,
+    devices: Vec<Device>,
+    capabilities: Vec<Capability>,
     networks: Vec<Network>,
+    /// When the container was last started by conductor
+    started_at: Option<SystemTime>,
     client: docker_api::Docker,
 }
 
@@ -78,6 +123,9 @@ pub enum ContainerState {
     Exited {
         image_id: String,
         container_id: String,
+        exit_code: i64,
+        /// The container was killed for exceeding its memory limit
+        oom_killed: bool,
     },
 }
 
@@ -122,6 +170,26 @@ impl ContainerBuilder {
         self
     }
 
+    /// Uses an in-memory containerfile, e.g. one that's embedded in the binary
+    pub fn set_containerfile_content(&mut self, content: impl AsRef<str>) {
+        self.containerfile_content = Some(content.as_ref().to_string());
+    }
+    pub fn with_containerfile_content(mut self, content: impl AsRef<str>) -> Self {
+        self.set_containerfile_content(content);
+
+        self
+    }
+
+    /// The containerfile to build the image from when it can't be pulled
+    pub fn set_fallback_containerfile_content(&mut self, content: impl AsRef<str>) {
+        self.fallback_containerfile_content = Some(content.as_ref().to_string());
+    }
+    pub fn with_fallback_containerfile_content(mut self, content: impl AsRef<str>) -> Self {
+        self.set_fallback_containerfile_content(content);
+
+        self
+    }
+
     pub fn set_context(&mut self, context: impl AsRef<Path>) {
         self.context = Some(context.as_ref().to_path_buf());
     }
@@ -140,6 +208,41 @@ impl ContainerBuilder {
         self
     }
 
+    pub fn set_entrypoint(&mut self, entrypoint: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.entrypoint = Some(
+            entrypoint
+                .into_iter()
+                .map(|a| a.as_ref().to_string())
+                .collect(),
+        );
+    }
+    pub fn with_entrypoint(
+        mut self,
+        entrypoint: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.set_entrypoint(entrypoint);
+
+        self
+    }
+
+    pub fn set_workdir(&mut self, workdir: impl AsRef<str>) {
+        self.workdir = Some(workdir.as_ref().to_string());
+    }
+    pub fn with_workdir(mut self, workdir: impl AsRef<str>) -> Self {
+        self.set_workdir(workdir);
+
+        self
+    }
+
+    pub fn set_user(&mut self, user: impl AsRef<str>) {
+        self.user = Some(user.as_ref().to_string());
+    }
+    pub fn with_user(mut self, user: impl AsRef<str>) -> Self {
+        self.set_user(user);
+
+        self
+    }
+
     pub fn set_mounts(
         &mut self,
         mounts: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>,
@@ -185,6 +288,44 @@ impl ContainerBuilder {
         self
     }
 
+    pub fn set_resources(&mut self, resources: ResourceLimits) {
+        self.resources = resources;
+    }
+    pub fn with_resources(mut self, resources: ResourceLimits) -> Self {
+        self.set_resources(resources);
+
+        self
+    }
+
+    /// Host devices passed through to the container, in addition to the GPU
+    pub fn set_devices(&mut self, devices: impl IntoIterator<Item = Device>) {
+        self.devices = devices.into_iter().collect();
+    }
+    pub fn with_devices(mut self, devices: impl IntoIterator<Item = Device>) -> Self {
+        self.set_devices(devices);
+
+        self
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: impl IntoIterator<Item = Capability>) {
+        self.capabilities = capabilities.into_iter().collect();
+    }
+    pub fn with_capabilities(mut self, capabilities: impl IntoIterator<Item = Capability>) -> Self {
+        self.set_capabilities(capabilities);
+
+        self
+    }
+
+    pub fn set_build_arg(&mut self, key: impl AsRef<str>, value: impl AsRef<str>) {
+        self.build_args
+            .insert(key.as_ref().to_string(), value.as_ref().to_string());
+    }
+    pub fn with_build_arg(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.set_build_arg(key, value);
+
+        self
+    }
+
     pub fn set_networks(&mut self, networks: Vec<Network>) {
         self.networks = networks;
     }
@@ -229,6 +370,18 @@ impl ContainerBuilder {
                 containerfile_digest_str,
             ));
 
+            Some(containerfile_digest)
+        } else if let Some(ref content) = self.containerfile_content {
+            let containerfile_digest = digest(&SHA256, content.as_bytes());
+            let containerfile_digest_str = HEXLOWER.encode(containerfile_digest.as_ref());
+
+            trace!("io.auxon.conductor.containerfile = {containerfile_digest_str}");
+
+            filters.push(format!(
+                "io.auxon.conductor.containerfile={}",
+                containerfile_digest_str,
+            ));
+
             Some(containerfile_digest)
         } else {
             None
@@ -247,30 +400,50 @@ impl ContainerBuilder {
             None
         };
 
-        // lookup existing local image, if it exists
-        let image_id =
-            if self.image.is_some() && self.context.is_none() && self.containerfile.is_none() {
-                let image_resp = client.inspect_image(self.image.as_deref().unwrap()).await;
+        if !self.build_args.is_empty() {
+            let build_args_digest_str =
+                HEXLOWER.encode(build_args_digest(&self.build_args).as_ref());
 
-                image_resp.map(|image| image.id).ok().flatten()
-            } else {
-                let images = client
-                    .list_images(Some(ListImagesOptions {
-                        filters: HashMap::from_iter([("label".to_string(), filters.clone())]),
-                        ..Default::default()
-                    }))
-                    .await?;
+            trace!("io.auxon.conductor.build-args = {build_args_digest_str}");
 
-                images.into_iter().next().map(|i| i.id)
-            };
+            filters.push(format!(
+                "io.auxon.conductor.build-args={}",
+                build_args_digest_str,
+            ));
+        }
+
+        // lookup existing local image, if it exists
+        let image_id = if self.image.is_some()
+            && self.context.is_none()
+            && self.containerfile.is_none()
+            && self.containerfile_content.is_none()
+        {
+            let image_resp = client.inspect_image(self.image.as_deref().unwrap()).await;
+
+            image_resp.map(|image| image.id).ok().flatten()
+        } else {
+            let images = client
+                .list_images(Some(ListImagesOptions {
+                    filters: HashMap::from_iter([("label".to_string(), filters.clone())]),
+                    ..Default::default()
+                }))
+                .await?;
+
+            images.into_iter().next().map(|i| i.id)
+        };
 
         //trace!("image: {image_id:?}");
 
         // lookup existing built container, if it exists
+        // Containers sharing an image are told apart by name
+        let mut container_filters = filters;
+        if let Some(ref name) = self.name {
+            container_filters.push(format!("{CONTAINER_NAME_LABEL}={name}"));
+        }
         let containers = client
             .list_containers(Some(ListContainersOptions {
                 all: true,
-                filters: HashMap::from_iter([("label".to_string(), filters)]),
+                filters: HashMap::from_iter([("label".to_string(), container_filters)]),
                 ..Default::default()
             }))
             .await?;
@@ -278,18 +451,25 @@ impl ContainerBuilder {
         //trace!("containers: {containers:#?}");
 
         let state = if let (Some(image), Some(container)) = (image_id, containers.get(0)) {
+            let container_id = container.id.clone().expect("container that exists has id");
             match container.state.as_deref() {
-                Some("running") => ContainerState::Running {
+                // A paused container is still running as far as we're concerned
+                Some("running") | Some("paused") => ContainerState::Running {
                     image_id: image,
-                    container_id: container.id.clone().expect("container that exists has id"),
-                },
-                Some("exited") => ContainerState::Exited {
-                    image_id: image,
-                    container_id: container.id.clone().expect("container that exists has id"),
+                    container_id,
                 },
+                Some("exited") => {
+                    let (exit_code, oom_killed) = exit_status(&client, &container_id).await?;
+                    ContainerState::Exited {
+                        image_id: image,
+                        container_id,
+                        exit_code,
+                        oom_killed,
+                    }
+                }
                 _ => ContainerState::Built {
                     image_id: image,
-                    container_id: container.id.clone().expect("container that exists has id"),
+                    container_id,
                 },
             }
         } else {
@@ -321,13 +501,23 @@ impl ContainerBuilder {
             image: self.image,
             containerfile: self.containerfile,
             containerfile_digest,
+            containerfile_content: self.containerfile_content,
+            fallback_containerfile_content: self.fallback_containerfile_content,
             context: self.context,
             context_digest,
+            build_args: self.build_args,
             cmd: self.cmd,
+            entrypoint: self.entrypoint,
+            workdir: self.workdir,
+            user: self.user,
             mounts: self.mounts,
             env: self.env,
             gpu_cap: self.gpu_cap,
+            resources: self.resources,
+            devices: self.devices,
+            capabilities: self.capabilities,
             networks: self.networks,
+            started_at: None,
             client: client2,
         };
 
@@ -335,6 +525,18 @@ impl ContainerBuilder {
     }
 }
 
+fn build_args_digest(build_args: &HashMap<String, String>) -> Digest {
+    let mut context = Context::new(&SHA256);
+    let sorted: BTreeMap<_, _> = build_args.iter().collect();
+    for (k, v) in sorted {
+        context.update(k.as_bytes());
+        context.update(b"=");
+        context.update(v.as_bytes());
+        context.update(b"\n");
+    }
+    context.finish()
+}
+
 #[instrument]
 fn digest_for_path(path: &Path) -> Result<Digest> {
     let mut context = Context::new(&SHA256);
@@ -352,27 +554,76 @@ fn digest_for_path(path: &Path) -> Result<Digest> {
 
 #[instrument(skip(context))]
 fn digest_dir(context: &mut Context, dir: &Path, ref_dir: &Path) -> Result<()> {
-    // list dir
-    let mut paths = fs::read_dir(dir)?
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, _>>()?;
+    for ref path in context_files(dir)? {
+        digest_file(context, path, ref_dir)?;
+    }
+
+    Ok(())
+}
+
+/// The sorted files within a build context directory, excluding those matched by
+/// its `.containerignore` or `.dockerignore`
+fn context_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let ignore = context_ignore(dir)?;
+    let mut paths = Vec::new();
+    for entry in WalkBuilder::new(dir).standard_filters(false).build() {
+        let path = entry?.into_path();
+        if path.is_file() {
+            // Unlike gitignore, a file in an excluded directory can be re-included
+            if !ignore.matched_path_or_any_parents(&path, false).is_ignore() {
+                paths.push(path);
+            }
+        } else if !path.is_dir() {
+            bail!("path is not a directory or file: {}", path.display());
+        }
+    }
 
     // sort listing
     paths.sort();
 
-    // digest each file
-    for ref path in paths {
-        // TODO: do I need to prevent infinite recursions? What does the tar library do?
-        if path.is_dir() {
-            digest_dir(context, path, ref_dir)?;
-        } else if path.is_file() {
-            digest_file(context, path, ref_dir)?;
-        } else {
-            bail!("path is not a directory or file");
+    Ok(paths)
+}
+
+/// The exclusions of a build context's `.containerignore` or `.dockerignore`. As
+/// with the container runtimes, only the file at the context root is read and its
+/// patterns are relative to the root, whatever their leading slashes.
+fn context_ignore(dir: &Path) -> Result<Gitignore> {
+    let Some(ignore_file) = [".containerignore", ".dockerignore"]
+        .into_iter()
+        .map(|f| dir.join(f))
+        .find(|p| p.is_file())
+    else {
+        return Ok(Gitignore::empty());
+    };
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for line in fs::read_to_string(&ignore_file)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
+        let (negation, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => ("!", pattern.trim()),
+            None => ("", line),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+        builder.add_line(Some(ignore_file.clone()), &format!("{negation}/{pattern}"))?;
     }
+    Ok(builder.build()?)
+}
 
-    Ok(())
+/// The exit code of a container that has stopped, and whether it was killed for
+/// exceeding its memory limit
+async fn exit_status(client: &ContainerClient, container_id: &str) -> Result<(i64, bool)> {
+    let state = client
+        .inspect_container(container_id, None)
+        .await?
+        .state
+        .unwrap_or_default();
+    Ok((
+        state.exit_code.unwrap_or_default(),
+        state.oom_killed.unwrap_or_default(),
+    ))
 }
 
 #[instrument(skip(context))]
@@ -402,20 +653,8 @@ impl Container {
         Default::default()
     }
 
-    pub fn from_internal_image(image: &str) -> ContainerBuilder {
-        let image_context_dir = {
-            // HACK: only supports repo-local images
-            let mut path: PathBuf = env!("CARGO_MANIFEST_DIR").into();
-            path.pop();
-            path.push("images/");
-            path.push(image);
-            trace!("image context: {}", path.display());
-            path
-        };
-
-        Self::builder()
-            .with_image(format!("conductor/{image}"))
-            .with_context(image_context_dir)
+    pub fn from_internal_image(image: StockImage) -> ContainerBuilder {
+        image.builder()
     }
 
     // TODO: should name just be made non-optional?
@@ -423,6 +662,20 @@ impl Container {
         self.name.as_deref()
     }
 
+    pub fn state(&self) -> &ContainerState {
+        &self.state
+    }
+
+    /// When the container was last started, unknown for a container found already
+    /// running
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.started_at
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, ContainerState::Running { .. })
+    }
+
     async fn client(&self) -> ContainerClient {
         Docker::connect_with_local_defaults()
             .context("connect to container system service")
@@ -432,277 +685,258 @@ impl Container {
             .unwrap()
     }
 
-    async fn build_context_tar(&mut self) -> Result<Vec<u8>> {
-        let containerfile = self.containerfile.clone();
-        let context = self.context.clone();
+    fn labels(&self) -> HashMap<&'static str, String> {
+        let mut labels = HashMap::new();
 
-        // TODO: stream files from FS, taring in flight, don't block
-        tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
-            let mut tarball = tar::Builder::new(Vec::new());
+        // always apply this label to everything for easy filtering of all resources
+        // TODO: use this for something? tool version? is that useful?
+        labels.insert("io.auxon.conductor", "".into());
 
-            if let Some(containerfile) = containerfile {
-                let containerfile_file_name = containerfile
-                    .file_name()
-                    .ok_or(anyhow!("containerfile does not name a file"))?
-                    .to_os_string();
+        if let Some(ref image) = self.image {
+            labels.insert("io.auxon.conductor.name", image.clone());
+        }
 
-                tarball
-                    .append_path_with_name(containerfile, containerfile_file_name)
-                    .context("build in-memory image tarball")
-                    .unwrap();
-            }
+        if let Some(containerfile_digest) = self.containerfile_digest {
+            labels.insert(
+                "io.auxon.conductor.containerfile",
+                HEXLOWER.encode(containerfile_digest.as_ref()),
+            );
+        }
 
-            if let Some(context) = context {
-                tarball
-                    .append_dir_all(".", context)
-                    .context("build in-memory image tarball")
-                    .unwrap();
-            }
+        // The context and build args labels are always set, an image built with
+        // them must not be up to date for a build without
+        let context_digest = self.context_digest.unwrap_or_else(|| digest(&SHA256, b""));
+        labels.insert(
+            "io.auxon.conductor.context",
+            HEXLOWER.encode(context_digest.as_ref()),
+        );
 
-            // TODO: decide what should happen if `containerfile` is set and `context` has
-            // containerfile
+        labels.insert(
+            "io.auxon.conductor.build-args",
+            HEXLOWER.encode(build_args_digest(&self.build_args).as_ref()),
+        );
 
-            tarball
-                .into_inner()
-                .context("finish in-memory image tarball")
-        })
-        .await
-        .context("spawn blocking tokio task to build tarball")?
+        labels
     }
 
+    /// Builds the image from its containerfile/context or pulls it, returning the image id.
+    ///
+    /// When pulling fails, an existing local image is used, otherwise the fallback
+    /// containerfile is built, if there is one.
     #[instrument]
-    pub async fn build(&mut self) -> Result<()> {
+    pub async fn build_image(&mut self) -> Result<String> {
         let client = self.client().await;
 
-        match &self.state {
-            ContainerState::Defined => {
-                let mut labels = HashMap::new();
+        if self.is_built_from_containerfile() {
+            return self.build_image_from_containerfile(&client).await;
+        }
 
-                // always apply this label to everything for easy filtering of all resources
-                // TODO: use this for something? tool version? is that useful?
-                labels.insert("io.auxon.conductor", "".into());
+        let Some(image) = self.image.clone() else {
+            bail!("container without image definition")
+        };
 
-                if let Some(ref image) = self.image {
-                    labels.insert("io.auxon.conductor.name", image.clone());
+        match self.pull_image(&client, &image).await {
+            Ok(image_id) => Ok(image_id),
+            Err(e) => {
+                if let Some(image_id) = client.inspect_image(&image).await.ok().and_then(|i| i.id) {
+                    warn!(%image, "failed to pull image, using the local image: {e}");
+                    Ok(image_id)
+                } else if let Some(content) = self.fallback_containerfile_content.take() {
+                    warn!(%image, "failed to pull image, building it instead: {e}");
+                    self.containerfile_digest = Some(digest(&SHA256, content.as_bytes()));
+                    self.containerfile_content = Some(content);
+                    self.build_image_from_containerfile(&client).await
+                } else {
+                    Err(e)
                 }
+            }
+        }
+    }
 
-                if let Some(containerfile_digest) = self.containerfile_digest {
-                    labels.insert(
-                        "io.auxon.conductor.containerfile",
-                        HEXLOWER.encode(containerfile_digest.as_ref()),
-                    );
-                }
+    fn is_built_from_containerfile(&self) -> bool {
+        self.containerfile.is_some()
+            || self.containerfile_content.is_some()
+            || self.context.is_some()
+    }
 
-                if let Some(context_digest) = self.context_digest {
-                    labels.insert(
-                        "io.auxon.conductor.context",
-                        HEXLOWER.encode(context_digest.as_ref()),
-                    );
-                }
+    async fn build_image_from_containerfile(&mut self, client: &ContainerClient) -> Result<String> {
+        let image = self.image.clone().unwrap();
+        let labels = self.labels();
+        let labels_ref = labels.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let build_args = self.build_args.clone();
+        let image_options = BuildImageOptions {
+            dockerfile: "Containerfile",
+            t: &image,
+            labels: labels_ref,
+            buildargs: build_args
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            ..Default::default()
+        };
 
-                let labels_ref = labels.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        // Skip the build when an image with the same inputs already exists
+        let filters = labels
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>();
+        let existing_images = client
+            .list_images(Some(ListImagesOptions {
+                filters: HashMap::from_iter([("label".to_string(), filters)]),
+                ..Default::default()
+            }))
+            .await?;
+        if let Some(image_id) = existing_images.into_iter().next().map(|i| i.id) {
+            info!(image, image_id, "image is up to date");
+            return Ok(image_id);
+        }
 
-                let image_id = if self.containerfile.is_some() || self.context.is_some() {
-                    let image = self.image.clone().unwrap();
-                    let image_options = BuildImageOptions {
-                        dockerfile: "Containerfile",
-                        t: &image,
-                        labels: labels_ref,
-                        ..Default::default()
-                    };
+        let tarball = self.build_context_tar().await?;
 
-                    let tarball = self.build_context_tar().await?;
+        let mut build_image_progress = client.build_image(image_options, None, Some(tarball));
 
-                    let mut build_image_progress =
-                        client.build_image(image_options, None, Some(tarball.into()));
+        // receive progress reports on image being built
+        let mut image_id = None;
+        while let Some(progress) = build_image_progress.next().await {
+            trace!(?progress, "build image progress");
+            let progress = progress?;
+            if let Some(msg) = progress.stream {
+                // TODO: print in the CLI handler
+                print!("{}", msg);
+            }
 
-                    // receive progress reports on image being built
-                    let mut image_id = None;
-                    while let Some(progress) = build_image_progress.next().await {
-                        trace!(?progress, "build image progress");
-                        let progress = progress?;
-                        if let Some(msg) = progress.stream {
-                            // TODO: print in the CLI handler
-                            print!("{}", msg);
-                        }
+            if let Some(aux) = progress.aux {
+                if let Some(id) = aux.id {
+                    info!("id: {id}");
+                    let _ = image_id.insert(id);
+                }
+            }
+        }
 
-                        if let Some(aux) = progress.aux {
-                            if let Some(id) = aux.id {
-                                info!("id: {id}");
-                                let _ = image_id.insert(id);
-                            }
-                        }
-                    }
+        let Some(image_id) = image_id else {
+            bail!("no image id reported by container service");
+        };
 
-                    let Some(image_id) = image_id else {
-                        bail!("no image id reported by container service");
-                    };
+        trace!(image_id, "image built");
 
-                    trace!(image_id, "image built");
+        Ok(image_id)
+    }
 
-                    image_id
-                } else if let Some(ref image) = self.image {
-                    let image_options = CreateImageOptions {
-                        from_image: image.as_str(),
-                        ..Default::default()
-                    };
+    async fn pull_image(&self, client: &ContainerClient, image: &str) -> Result<String> {
+        let image_options = CreateImageOptions {
+            from_image: image,
+            ..Default::default()
+        };
 
-                    trace!(?image_options, "create image");
-                    let mut create_image_progress =
-                        client.create_image(Some(image_options), None, None);
-
-                    // receive progress reports on image being built
-                    let mut image_id = None;
-                    while let Some(progress) = create_image_progress.next().await {
-                        //trace!(?progress, "create image progress");
-                        // TODO: print in the CLI handler
-                        let progress = progress?;
-                        if let Some(msg) = progress.progress {
-                            // TODO: print in the CLI handler
-                            print!("{}", msg);
-                        }
+        trace!(?image_options, "create image");
+        let mut create_image_progress = client.create_image(Some(image_options), None, None);
+
+        // receive progress reports on image being built
+        let mut image_id = None;
+        while let Some(progress) = create_image_progress.next().await {
+            //trace!(?progress, "create image progress");
+            // TODO: print in the CLI handler
+            let progress = progress?;
+            if let Some(msg) = progress.progress {
+                // TODO: print in the CLI handler
+                print!("{}", msg);
+            }
 
-                        if let Some(id) = progress.id {
-                            if id.starts_with("sha256:") {
-                                info!("id: {id}");
-                                let _ = image_id.insert(id);
-                            } else {
-                                warn!("non-id returned as ID: {id}");
-                            }
-                        }
-                    }
+            if let Some(id) = progress.id {
+                if id.starts_with("sha256:") {
+                    info!("id: {id}");
+                    let _ = image_id.insert(id);
+                } else {
+                    warn!("non-id returned as ID: {id}");
+                }
+            }
+        }
 
-                    // workaround: docker just reports the label (eg. "latest") as the "id", if
-                    // that's the case, just copy over the source image name
-                    let image_id = image_id.unwrap_or_else(|| image.to_string());
+        // workaround: docker just reports the label (eg. "latest") as the "id", if
+        // that's the case, just copy over the source image name
+        let image_id = image_id.unwrap_or_else(|| image.to_string());
 
-                    trace!(image_id, "image created");
+        trace!(image_id, "image created");
 
-                    image_id
-                } else {
-                    bail!("container without image definition")
-                };
+        Ok(image_id)
+    }
 
-                // build network endpoint definition
-                let mut container_network_endpoints = HashMap::new();
-                for network in &self.networks {
-                    let network_name = &network.name;
-
-                    let NetworkState::Built { id: network_id } = &network.state else {
-                            panic!("unbuilt network passed to container builder")
-                        };
-
-                    container_network_endpoints.insert(
-                        network_name.as_str(),
-                        EndpointSettings {
-                            network_id: Some(network_id.to_string()),
-                            // TODO: add alias for the simple machine name too, currently does the
-                            // "fully qualified" name only, eg. "two_networked_containers___server"
-                            aliases: self.name.clone().map(|n| vec![n]),
-                            ..Default::default()
-                        },
-                    );
-                }
+    /// Adds another name to the container's image, e.g. a versioned tag
+    pub async fn tag_image(&self, repo: &str, tag: &str) -> Result<()> {
+        let Some(image) = self.image.as_deref() else {
+            bail!("container without image definition")
+        };
+        let client = self.client().await;
+        client
+            .tag_image(image, Some(TagImageOptions { repo, tag }))
+            .await?;
+        Ok(())
+    }
 
-                let image = self.image.as_deref();
+    /// Writes the build context tarball to a temporary file on disk, rather than
+    /// assembling it in memory, and streams it from there
+    async fn build_context_tar(&mut self) -> Result<hyper::Body> {
+        let containerfile = self.containerfile.clone();
+        let containerfile_content = self.containerfile_content.clone();
+        let context = self.context.clone();
 
-                let env = self
-                    .env
-                    .as_ref()
-                    .map(|vars| vars.iter().map(|ev| ev.as_str()).collect());
+        let file = tokio::task::spawn_blocking(move || -> Result<File> {
+            let mut tarball = tar::Builder::new(tempfile::tempfile()?);
 
-                let mounts = self.mounts.as_ref().map(|some_mounts| {
-                    some_mounts
-                        .iter()
-                        .map(|(host_path, container_path)| Mount {
-                            source: Some(host_path.as_str().to_string()),
-                            target: Some(container_path.as_str().to_string()),
-                            typ: Some(MountTypeEnum::BIND),
-                            ..Default::default()
-                        })
-                        .collect()
-                });
-
-                let cmd = self
-                    .cmd
-                    .as_ref()
-                    .map(|some_cmd| some_cmd.iter().map(|arg| arg.as_str()).collect());
-
-                let labels_ref = labels.iter().map(|(k, v)| (*k, v.as_str())).collect();
-
-                trace!(?container_network_endpoints);
-
-                // hook up GPU for GUI containers
-                let (devices, device_requests) = if self.gpu_cap {
-                    if std::env::var("NVIDIA_GPU").is_ok() {
-                        (
-                            None,
-                            Some(vec![DeviceRequest {
-                                capabilities: Some(vec![vec!["gpu".to_owned()]]),
-                                ..Default::default()
-                            }]),
-                        )
-                    } else {
-                        (
-                            Some(vec![DeviceMapping {
-                                path_on_host: Some("/dev/dri".to_string()),
-                                path_in_container: Some("/dev/dri".to_string()),
-                                cgroup_permissions: Some("rwm".to_string()),
-                            }]),
-                            None,
-                        )
-                    }
-                } else {
-                    (None, None)
-                };
+            if let Some(containerfile) = containerfile {
+                let containerfile_file_name = containerfile
+                    .file_name()
+                    .ok_or(anyhow!("containerfile does not name a file"))?
+                    .to_os_string();
 
-                let container_config = container::Config {
-                    image,
-                    cmd,
-                    tty: Some(true),
-                    open_stdin: Some(true),
-                    env,
-                    host_config: Some(bollard::models::HostConfig {
-                        network_mode: Some("host".to_owned()),
-                        // TODO - only need CAP_NET_ADMIN if dealing with TUN/TAP interfaces
-                        //cap_add: Some(vec!["NET_ADMIN".to_owned()]),
-                        mounts,
-                        devices,
-                        device_requests,
-                        ..Default::default()
-                    }),
-                    labels: Some(labels_ref),
-                    // networking must be set here explicitly to implicitly disable the default
-                    // bridge network
-                    //
-                    // TODO: reenable networking, need to figure out how to make GUI work without
-                    //       host network mode
-                    //
-                    //networking_config: Some(NetworkingConfig {
-                    //    endpoints_config: container_network_endpoints,
-                    //}),
-                    ..Default::default()
-                };
+                tarball
+                    .append_path_with_name(containerfile, containerfile_file_name)
+                    .context("build image tarball")?;
+            }
 
-                let container = client
-                    .create_container::<&str, _>(
-                        None, // disabled until deleting works
-                        /*self.name
-                        .as_ref()
-                        .map(|n| container::CreateContainerOptions {
-                            name: n.clone(),
-                            ..Default::default()
-                        })*/
-                        container_config,
-                    )
-                    .await?;
+            if let Some(content) = containerfile_content {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                tarball
+                    .append_data(&mut header, "Containerfile", content.as_bytes())
+                    .context("build image tarball")?;
+            }
 
-                trace!(?container, "created container");
+            if let Some(context) = context {
+                for path in context_files(&context)? {
+                    let rel_path = path.strip_prefix(&context).context("get relative path")?;
+                    tarball
+                        .append_path_with_name(&path, rel_path)
+                        .context("build image tarball")?;
+                }
+            }
 
-                self.state = ContainerState::Built {
-                    image_id,
-                    container_id: container.id,
-                };
+            // TODO: decide what should happen if `containerfile` is set and `context` has
+            // containerfile
+
+            let mut file = tarball.into_inner().context("finish image tarball")?;
+            file.rewind()?;
+            Ok(file)
+        })
+        .await
+        .context("spawn blocking tokio task to build tarball")??;
+
+        Ok(hyper::Body::wrap_stream(ReaderStream::new(
+            tokio::fs::File::from_std(file),
+        )))
+    }
+
+    #[instrument]
+    pub async fn build(&mut self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                let image_id = self.build_image().await?;
+                let image = self.image.clone();
+                self.create(&client, image_id, image.as_deref()).await?;
             }
             ContainerState::Built { .. } => {
                 trace!("image already built, nothing to build");
@@ -718,32 +952,199 @@ impl Container {
         Ok(())
     }
 
-    #[instrument]
-    pub async fn start(&mut self) -> Result<()> {
-        let client = self.client().await;
+    /// Creates the container from the image. It's labelled by its definition rather
+    /// than the image, so it's found by [`ContainerBuilder::resolve`] either way.
+    async fn create(
+        &mut self,
+        client: &ContainerClient,
+        image_id: String,
+        image: Option<&str>,
+    ) -> Result<()> {
+        let mut labels = self.labels();
+        if let Some(ref name) = self.name {
+            labels.insert(CONTAINER_NAME_LABEL, name.clone());
+        }
 
-        match &self.state {
-            ContainerState::Defined => {
-                // TODO: just do the build here
-                bail!("can't start unbuilt system");
-            }
-            ContainerState::Built {
-                container_id,
-                image_id,
-            }
-            | ContainerState::Exited {
-                container_id,
-                image_id,
-                ..
-            } => {
-                trace!(container_id, "start previously built container");
-                assert!(
-                    !container_id.is_empty(),
-                    "container id can't be the empty string"
-                );
-                client.start_container::<String>(container_id, None).await?;
+        // build network endpoint definition
+        let mut container_network_endpoints = HashMap::new();
+        for network in &self.networks {
+            let network_name = &network.name;
 
-                self.state = ContainerState::Running {
+            let NetworkState::Built { id: network_id } = &network.state else {
+                panic!("unbuilt network passed to container builder")
+            };
+
+            container_network_endpoints.insert(
+                network_name.as_str(),
+                EndpointSettings {
+                    network_id: Some(network_id.to_string()),
+                    // TODO: add alias for the simple machine name too, currently does the
+                    // "fully qualified" name only, eg. "two_networked_containers___server"
+                    aliases: self.name.clone().map(|n| vec![n]),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let env = self
+            .env
+            .as_ref()
+            .map(|vars| vars.iter().map(|ev| ev.as_str()).collect());
+
+        let mounts = self.mounts.as_ref().map(|some_mounts| {
+            some_mounts
+                .iter()
+                .map(|(host_path, container_path)| Mount {
+                    source: Some(host_path.as_str().to_string()),
+                    target: Some(container_path.as_str().to_string()),
+                    typ: Some(MountTypeEnum::BIND),
+                    ..Default::default()
+                })
+                .collect()
+        });
+
+        let cmd = self
+            .cmd
+            .as_ref()
+            .map(|some_cmd| some_cmd.iter().map(|arg| arg.as_str()).collect());
+
+        let entrypoint = self
+            .entrypoint
+            .as_ref()
+            .map(|some_entrypoint| some_entrypoint.iter().map(|arg| arg.as_str()).collect());
+
+        let labels_ref = labels.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+        trace!(?container_network_endpoints);
+
+        // hook up GPU for GUI containers
+        let (mut devices, device_requests) = if self.gpu_cap {
+            if std::env::var("NVIDIA_GPU").is_ok() {
+                (
+                    None,
+                    Some(vec![DeviceRequest {
+                        capabilities: Some(vec![vec!["gpu".to_owned()]]),
+                        ..Default::default()
+                    }]),
+                )
+            } else {
+                (
+                    Some(vec![DeviceMapping {
+                        path_on_host: Some("/dev/dri".to_string()),
+                        path_in_container: Some("/dev/dri".to_string()),
+                        cgroup_permissions: Some("rwm".to_string()),
+                    }]),
+                    None,
+                )
+            }
+        } else {
+            (None, None)
+        };
+
+        // pass through the requested host devices
+        if !self.devices.is_empty() {
+            devices
+                .get_or_insert_with(Vec::new)
+                .extend(self.devices.iter().map(|d| DeviceMapping {
+                    path_on_host: Some(d.host_path.display().to_string()),
+                    path_in_container: Some(d.guest_path.display().to_string()),
+                    cgroup_permissions: Some(d.cgroup_permissions.clone()),
+                }));
+        }
+
+        let cap_add = (!self.capabilities.is_empty()).then(|| {
+            self.capabilities
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+        });
+
+        let container_config = container::Config {
+            image,
+            cmd,
+            entrypoint,
+            working_dir: self.workdir.as_deref(),
+            user: self.user.as_deref(),
+            tty: Some(true),
+            open_stdin: Some(true),
+            env,
+            host_config: Some(bollard::models::HostConfig {
+                network_mode: Some("host".to_owned()),
+                cap_add,
+                mounts,
+                devices,
+                device_requests,
+                nano_cpus: self.resources.nano_cpus,
+                cpuset_cpus: self.resources.cpuset_cpus.clone(),
+                memory: self.resources.memory,
+                memory_swap: self.resources.memory_swap,
+                pids_limit: self.resources.pids_limit,
+                ..Default::default()
+            }),
+            labels: Some(labels_ref),
+            // networking must be set here explicitly to implicitly disable the default
+            // bridge network
+            //
+            // TODO: reenable networking, need to figure out how to make GUI work without
+            //       host network mode
+            //
+            //networking_config: Some(NetworkingConfig {
+            //    endpoints_config: container_network_endpoints,
+            //}),
+            ..Default::default()
+        };
+
+        let container = client
+            .create_container::<&str, _>(
+                None, // disabled until deleting works
+                /*self.name
+                .as_ref()
+                .map(|n| container::CreateContainerOptions {
+                    name: n.clone(),
+                    ..Default::default()
+                })*/
+                container_config,
+            )
+            .await?;
+
+        trace!(?container, "created container");
+
+        self.state = ContainerState::Built {
+            image_id,
+            container_id: container.id,
+        };
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn start(&mut self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                // TODO: just do the build here
+                bail!("can't start unbuilt system");
+            }
+            ContainerState::Built {
+                container_id,
+                image_id,
+            }
+            | ContainerState::Exited {
+                container_id,
+                image_id,
+                ..
+            } => {
+                trace!(container_id, "start previously built container");
+                assert!(
+                    !container_id.is_empty(),
+                    "container id can't be the empty string"
+                );
+                let started_at = SystemTime::now();
+                client.start_container::<String>(container_id, None).await?;
+
+                self.started_at = Some(started_at);
+                self.state = ContainerState::Running {
                     container_id: container_id.clone(),
                     image_id: image_id.clone(),
                 };
@@ -756,6 +1157,309 @@ impl Container {
         Ok(())
     }
 
+    #[instrument]
+    pub async fn stop(&mut self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("can't stop unbuilt system");
+            }
+            ContainerState::Running {
+                container_id,
+                image_id,
+            } => {
+                trace!(container_id, "stop running container");
+                match client
+                    .stop_container(container_id, Some(StopContainerOptions { t: 10 }))
+                    .await
+                {
+                    // Already stopped, e.g. it crashed or was killed
+                    Ok(())
+                    | Err(bollard::errors::Error::DockerResponseServerError {
+                        status_code: 304,
+                        ..
+                    }) => (),
+                    Err(e) => return Err(e.into()),
+                }
+
+                let (exit_code, oom_killed) = exit_status(&client, container_id).await?;
+                self.state = ContainerState::Exited {
+                    container_id: container_id.clone(),
+                    image_id: image_id.clone(),
+                    exit_code,
+                    oom_killed,
+                };
+            }
+            ContainerState::Built { .. } | ContainerState::Exited { .. } => {
+                trace!("container not running, nothing to do");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the running container to exit, returning its exit code
+    #[instrument]
+    pub async fn wait(&mut self) -> Result<i64> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined | ContainerState::Built { .. } => {
+                bail!("can't wait for a container that hasn't started");
+            }
+            ContainerState::Running {
+                container_id,
+                image_id,
+            } => {
+                trace!(container_id, "wait for container to exit");
+                let mut wait = client.wait_container::<String>(container_id, None);
+                let exit_code = match wait.next().await {
+                    Some(Ok(resp)) => resp.status_code,
+                    // A non-zero exit is reported as an error
+                    Some(Err(bollard::errors::Error::DockerContainerWaitError {
+                        code, ..
+                    })) => code,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => bail!("container wait ended without a response"),
+                };
+
+                let (_, oom_killed) = exit_status(&client, container_id).await?;
+                self.state = ContainerState::Exited {
+                    container_id: container_id.clone(),
+                    image_id: image_id.clone(),
+                    exit_code,
+                    oom_killed,
+                };
+                Ok(exit_code)
+            }
+            ContainerState::Exited { exit_code, .. } => Ok(*exit_code),
+        }
+    }
+
+    /// Check whether a running container has since exited, e.g. it crashed or was
+    /// killed outside of conductor, or was recreated, updating its state
+    #[instrument]
+    pub async fn refresh(&mut self) -> Result<()> {
+        let ContainerState::Running {
+            container_id,
+            image_id,
+        } = &self.state
+        else {
+            return Ok(());
+        };
+
+        let client = self.client().await;
+        let mut container_id = container_id.clone();
+        let image_id = image_id.clone();
+        let inspected = match client.inspect_container(&container_id, None).await {
+            Ok(inspected) => inspected,
+            // Recreated by another conductor process, e.g. when loading a snapshot
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {
+                let Some(id) = self.find_by_name_label(&client).await? else {
+                    bail!("container '{container_id}' no longer exists");
+                };
+                trace!(container_id, id, "container was recreated");
+                container_id = id;
+                client.inspect_container(&container_id, None).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let state = inspected.state.unwrap_or_default();
+        if state.running == Some(false) {
+            trace!(container_id, "container has exited");
+            self.state = ContainerState::Exited {
+                container_id,
+                image_id,
+                exit_code: state.exit_code.unwrap_or_default(),
+                oom_killed: state.oom_killed.unwrap_or_default(),
+            };
+        } else {
+            self.state = ContainerState::Running {
+                container_id,
+                image_id,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// The id of the container labelled with this container's name, if any
+    async fn find_by_name_label(&self, client: &ContainerClient) -> Result<Option<String>> {
+        let Some(ref name) = self.name else {
+            return Ok(None);
+        };
+        let containers = client
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters: HashMap::from_iter([(
+                    "label".to_string(),
+                    vec![format!("{CONTAINER_NAME_LABEL}={name}")],
+                )]),
+                ..Default::default()
+            }))
+            .await?;
+        Ok(containers.into_iter().find_map(|c| c.id))
+    }
+
+    /// Remove the container, stopping it if it's running. Its image is kept.
+    #[instrument]
+    pub async fn remove(&mut self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                trace!("container not built, nothing to do");
+            }
+            ContainerState::Built { container_id, .. }
+            | ContainerState::Running { container_id, .. }
+            | ContainerState::Exited { container_id, .. } => {
+                trace!(container_id, "remove container");
+                client
+                    .remove_container(
+                        container_id,
+                        Some(RemoveContainerOptions {
+                            force: true,
+                            ..Default::default()
+                        }),
+                    )
+                    .await?;
+
+                self.state = ContainerState::Defined;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for a line of the container's output, printed since the given time, to
+    /// match the pattern, returning the line. The container runtime only tracks
+    /// whole seconds, so lines printed earlier in the same second may also match.
+    #[instrument]
+    pub async fn wait_for_output(&self, pattern: &Regex, since: SystemTime) -> Result<String> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't read its output");
+            }
+            ContainerState::Built { .. } => {
+                bail!("machine not running, can't read its output");
+            }
+            ContainerState::Exited { container_id, .. }
+            | ContainerState::Running { container_id, .. } => {
+                trace!(container_id, %pattern, "wait for container output");
+                let since = since
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or_default();
+                let mut stream = client.logs(
+                    container_id,
+                    Some(LogsOptions::<String> {
+                        follow: true,
+                        stdout: true,
+                        stderr: true,
+                        since,
+                        ..Default::default()
+                    }),
+                );
+                let mut partial_line = String::new();
+                while let Some(chunk) = stream.next().await {
+                    partial_line.push_str(&chunk?.to_string());
+                    while let Some(end) = partial_line.find('\n') {
+                        let line: String = partial_line.drain(..=end).collect();
+                        let line = line.trim_end();
+                        if pattern.is_match(line) {
+                            return Ok(line.to_owned());
+                        }
+                    }
+                    // Prompts and the like aren't newline terminated
+                    if pattern.is_match(&partial_line) {
+                        return Ok(partial_line);
+                    }
+                }
+                bail!("machine output ended without a line matching '{pattern}'");
+            }
+        }
+    }
+
+    /// Follow the container's output since it was started, carrying on with each
+    /// run after the container is restarted. The stream ends once the container
+    /// is removed.
+    #[instrument]
+    pub async fn follow_output(
+        &self,
+    ) -> Result<impl Stream<Item = Result<LogOutput>> + Send + 'static> {
+        type Logs = Pin<Box<dyn Stream<Item = Result<LogOutput, bollard::errors::Error>> + Send>>;
+
+        let client = self.client().await;
+
+        let container_id = match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't read its output");
+            }
+            ContainerState::Built { .. } => {
+                bail!("machine not running, can't read its output");
+            }
+            ContainerState::Exited { container_id, .. }
+            | ContainerState::Running { container_id, .. } => container_id.clone(),
+        };
+
+        trace!(container_id, "follow container output");
+        let state: Option<(i64, Option<Logs>)> = Some((0, None));
+        Ok(stream::unfold(state, move |state| {
+            let client = client.clone();
+            let container_id = container_id.clone();
+            async move {
+                let (mut since, mut logs) = state?;
+                loop {
+                    let Some(output) = logs.as_mut() else {
+                        logs = Some(Box::pin(client.logs(
+                            &container_id,
+                            Some(LogsOptions::<String> {
+                                follow: true,
+                                stdout: true,
+                                stderr: true,
+                                since,
+                                ..Default::default()
+                            }),
+                        )));
+                        continue;
+                    };
+                    match output.next().await {
+                        Some(Ok(output)) => return Some((Ok(output), Some((since, logs)))),
+                        Some(Err(e)) => return Some((Err(e.into()), None)),
+                        None => (),
+                    }
+
+                    // The run ended, pick the output back up from the next one. Docker's
+                    // `since` is inclusive and in whole seconds, round up so the lines
+                    // already printed aren't repeated.
+                    logs = None;
+                    since = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs() as i64 + 1)
+                        .unwrap_or_default();
+                    loop {
+                        match client.inspect_container(&container_id, None).await {
+                            Ok(c) if c.state.and_then(|s| s.running) == Some(true) => break,
+                            Ok(_) => (),
+                            // Removed, there won't be any more output
+                            Err(bollard::errors::Error::DockerResponseServerError {
+                                status_code: 404,
+                                ..
+                            }) => return None,
+                            Err(e) => return Some((Err(e.into()), None)),
+                        }
+                        tokio::time::sleep(FOLLOW_OUTPUT_INTERVAL).await;
+                    }
+                }
+            }
+        }))
+    }
+
     #[instrument]
     pub async fn attach(&self) -> Result<bollard::container::AttachContainerResults> {
         let client = self.client().await;
@@ -821,6 +1525,238 @@ impl Container {
         }
     }
 
+    /// Run a command to completion within the running container, returning its output
+    #[instrument]
+    pub async fn exec(&self, cmd: &[String]) -> Result<String> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't exec");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't exec");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, ?cmd, "exec in container");
+                let exec = client
+                    .create_exec(
+                        container_id,
+                        CreateExecOptions {
+                            cmd: Some(cmd.iter().map(|arg| arg.as_str()).collect()),
+                            attach_stdout: Some(true),
+                            attach_stderr: Some(true),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+
+                let mut output = String::new();
+                if let StartExecResults::Attached {
+                    output: mut stream, ..
+                } = client.start_exec(&exec.id, None).await?
+                {
+                    while let Some(chunk) = stream.next().await {
+                        output.push_str(&chunk?.to_string());
+                    }
+                }
+
+                match client.inspect_exec(&exec.id).await?.exit_code {
+                    Some(0) | None => Ok(output),
+                    Some(code) => bail!("command {cmd:?} exited with status {code}: {output}"),
+                }
+            }
+        }
+    }
+
+    /// Copy a file out of the running container into a host directory
+    #[instrument]
+    pub async fn copy_from(&self, guest_path: &str, host_dir: &Path) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't copy from it");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't copy from it");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, guest_path, "copy from container");
+                let mut stream = client.download_from_container(
+                    container_id,
+                    Some(DownloadFromContainerOptions { path: guest_path }),
+                );
+                let mut archive = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    archive.extend_from_slice(&chunk?);
+                }
+                fs::create_dir_all(host_dir)?;
+                tar::Archive::new(archive.as_slice()).unpack(host_dir)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Copy a host file into a directory of the running container
+    #[instrument]
+    pub async fn copy_to(&self, host_path: &Path, guest_dir: &str) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't copy to it");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't copy to it");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, guest_dir, "copy to container");
+                let file_name = host_path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("'{}' isn't a file", host_path.display()))?;
+                let mut archive = tar::Builder::new(Vec::new());
+                archive.append_path_with_name(host_path, file_name)?;
+                let archive = archive.into_inner()?;
+                client
+                    .upload_to_container(
+                        container_id,
+                        Some(UploadToContainerOptions {
+                            path: guest_dir,
+                            ..Default::default()
+                        }),
+                        archive.into(),
+                    )
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Freeze every process in the running container
+    #[instrument]
+    pub async fn pause(&self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't pause");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't pause");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, "pause container");
+                client.pause_container(container_id).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Kill the running container's processes.
+    ///
+    /// Like pausing, this doesn't change the container's state as far as we're concerned.
+    #[instrument]
+    pub async fn kill(&self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't kill");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't kill");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, "kill container");
+                client.kill_container::<String>(container_id, None).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Restart the running container, or start a killed one
+    #[instrument]
+    pub async fn restart(&self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't restart");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't restart");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, "restart container");
+                client.restart_container(container_id, None).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Resume a container frozen by [`Container::pause`]
+    #[instrument]
+    pub async fn unpause(&self) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't unpause");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't unpause");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, "unpause container");
+                client.unpause_container(container_id).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Commit the running container's filesystem to an image, `repo:tag`. The
+    /// container isn't paused for it, that's up to the caller.
+    #[instrument]
+    pub async fn commit(&self, repo: &str, tag: &str) -> Result<()> {
+        let client = self.client().await;
+
+        match &self.state {
+            ContainerState::Defined => {
+                bail!("machine not built or running, can't commit");
+            }
+            ContainerState::Exited { .. } | ContainerState::Built { .. } => {
+                bail!("machine not running, can't commit");
+            }
+            ContainerState::Running { container_id, .. } => {
+                trace!(container_id, repo, tag, "commit container");
+                client
+                    .commit_container(
+                        CommitContainerOptions {
+                            container: container_id.as_str(),
+                            repo,
+                            tag,
+                            pause: false,
+                            ..Default::default()
+                        },
+                        container::Config::<String>::default(),
+                    )
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Replace the container with one created from another image, e.g. one made by
+    /// [`Container::commit`], and start it
+    #[instrument]
+    pub async fn recreate_from_image(&mut self, image: &str) -> Result<()> {
+        let client = self.client().await;
+        self.remove().await?;
+        self.create(&client, image.to_owned(), Some(image)).await?;
+        self.start().await
+    }
+
     #[instrument]
     pub async fn stats(&self) -> Result<ContainerStats> {
         let stats = self.stats_inner().await?;
@@ -934,12 +1870,35 @@ mod tests {
     use std::path::Path;
     use tracing_test::traced_test;
 
+    #[test]
+    fn context_files_follow_root_ignore_file() -> Result<()> {
+        let context = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_resources/build-contexts/ignore")
+            .canonicalize()?;
+        let files: Vec<String> = context_files(&context)?
+            .iter()
+            .map(|p| p.strip_prefix(&context).unwrap().display().to_string())
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ".dockerignore",
+                "Containerfile",
+                "build/keep.txt",
+                "nested/.dockerignore",
+                "nested/data.txt",
+                "nested/scratch.txt",
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn internal_image() -> Result<()> {
-        const IMAGE: &str = "renode";
-
-        let mut container = Container::from_internal_image(IMAGE).resolve().await?;
+        let mut container = Container::from_internal_image(StockImage::Renode)
+            .resolve()
+            .await?;
 
         container.build().await
     }
@@ -981,9 +1940,7 @@ mod tests {
     #[tokio::test]
     #[traced_test]
     async fn command_in_container_from_image() -> Result<()> {
-        const IMAGE: &str = "renode";
-
-        let mut container = Container::from_internal_image(IMAGE)
+        let mut container = Container::from_internal_image(StockImage::Renode)
             .with_cmd(["whoami"])
             .resolve()
             .await?;
@@ -1,21 +1,27 @@
 use crate::{
-    config::{Connection, Global, MachineProvider, WorldProvider},
-    display,
+    config::{
+        Connection, ConnectorProperties, Global, MachineConnector, MachineProvider, WorldProvider,
+    },
+    display::{self, VncDisplay},
+    network_impairment::NetworkImpairment,
     provider::{
+        self,
         container::ContainerMachine,
-        gazebo::GazeboWorld,
+        gazebo::{self, GazeboWorld, TopicBridgeEndpoint},
         guest_component_resource_path,
-        qemu::QemuMachine,
+        qemu::{self, QemuMachine},
         renode::{self, guest_resc_path, PlatformDescription, RenodeMachine, RenodeScriptGen},
     },
+    resources::ResourceLimits,
+    restart::RestartPolicy,
     types::{
-        BridgeName, ComponentName, ConnectionKind, ConnectionName, ContainerRuntimeName,
-        EnvironmentVariableKeyValuePairs, HostToGuestAssetPaths, InterfaceName, ProviderKind,
-        SystemName, TapDevice,
+        BridgeName, Capability, ComponentName, ConnectionKind, ConnectionName,
+        ContainerRuntimeName, Device, EnvironmentVariableKeyValuePairs, HostToGuestAssetPaths,
+        InterfaceName, ProviderKind, PublishedPort, SystemName, TapDevice,
     },
     Component, ComponentGraph, WorldOrMachineComponent,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs,
@@ -29,6 +35,8 @@ use std::{
 pub struct DeploymentContainer<C> {
     pub name: ContainerRuntimeName,
     pub uses_host_display: bool,
+    /// The virtual display the container's GUI is served on, in place of the host's display
+    pub vnc: Option<VncDisplay>,
     pub environment_variables: EnvironmentVariableKeyValuePairs,
     pub assets: HostToGuestAssetPaths,
     pub generated_guest_files: BTreeMap<PathBuf, String>,
@@ -36,6 +44,16 @@ pub struct DeploymentContainer<C> {
     pub args: Vec<String>,
     pub connections: Vec<Connection>,
     pub taps_to_bridges: BTreeMap<TapDevice, BridgeName>,
+    pub tap_impairments: BTreeMap<TapDevice, NetworkImpairment>,
+    /// Impairments of the host bridges the container's host network traffic leaves through
+    pub bridge_impairments: BTreeMap<BridgeName, NetworkImpairment>,
+    pub can_interfaces: BTreeMap<ConnectionName, InterfaceName>,
+    pub resources: ResourceLimits,
+    pub restart: RestartPolicy,
+    pub devices: BTreeSet<Device>,
+    pub capabilities: BTreeSet<Capability>,
+    /// Ports the container listens on, on the host's network
+    pub ports: BTreeSet<PublishedPort>,
     pub components: Vec<C>,
 }
 
@@ -44,6 +62,7 @@ impl<C> DeploymentContainer<C> {
         DeploymentContainer {
             name,
             uses_host_display: false,
+            vnc: None,
             environment_variables: Default::default(),
             assets: Default::default(),
             generated_guest_files: Default::default(),
@@ -51,9 +70,75 @@ impl<C> DeploymentContainer<C> {
             args: Default::default(),
             connections: Vec::new(),
             taps_to_bridges: Default::default(),
+            tap_impairments: Default::default(),
+            bridge_impairments: Default::default(),
+            can_interfaces: Default::default(),
+            resources: Default::default(),
+            restart: Default::default(),
+            devices: Default::default(),
+            capabilities: Default::default(),
+            ports: Default::default(),
             components: Vec::new(),
         }
     }
+
+    /// Creating TAP devices requires the TUN device and, like creating CAN
+    /// interfaces and impairing bridges, CAP_NET_ADMIN
+    fn add_network_privileges(&mut self) {
+        if !self.taps_to_bridges.is_empty() {
+            self.devices
+                .insert(Device::new_passthrough(provider::TUN_DEVICE_PATH));
+        }
+        if !self.taps_to_bridges.is_empty()
+            || !self.can_interfaces.is_empty()
+            || !self.bridge_impairments.is_empty()
+        {
+            self.capabilities.insert(Capability::net_admin());
+        }
+    }
+}
+
+impl<C> DeploymentContainer<C> {
+    // TODO - using a pseudo tempdir on the host for
+    // ephemeral store of file generated for the guest so
+    // they can be treated as normal assets for now
+    //
+    // TODO use system name as part of arbitration
+    fn write_generated_guest_files(&mut self, system_name: &SystemName) -> Result<()> {
+        for (guest_path, content) in self.generated_guest_files.iter() {
+            let file_name = guest_path.file_name().unwrap();
+            let host_dir = PathBuf::from("/tmp")
+                .join("conductor_generated_assets")
+                .join(system_name.as_str())
+                .join(self.name.as_ref());
+            let host_path = host_dir.join(file_name);
+            fs::create_dir_all(&host_dir)?;
+
+            // TODO - don't need to make everything executable
+            #[cfg(unix)]
+            {
+                use std::io::Write;
+                use std::os::unix::fs::OpenOptionsExt;
+
+                let mut f = fs::OpenOptions::new()
+                    .mode(0o777)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&host_path)?;
+                f.write_all(content.as_bytes())?;
+            }
+
+            // TODO - we don't support windows yet
+            #[cfg(not(unix))]
+            {
+                fs::write(&host_path, content)?;
+            }
+
+            self.assets.insert(host_path, guest_path.clone())?;
+        }
+        Ok(())
+    }
 }
 
 // NOTE: already sanity checked
@@ -65,8 +150,8 @@ impl DeploymentContainer<GazeboWorld> {
 
 // NOTE: already sanity checked for consistency across multi-machine-per-container setups
 impl DeploymentContainer<RenodeMachine> {
-    pub(crate) fn base_image(&self) -> String {
-        self.components.get(0).unwrap().base_image()
+    pub(crate) fn machine(&self) -> &RenodeMachine {
+        self.components.get(0).unwrap()
     }
 }
 
@@ -92,6 +177,7 @@ pub struct Deployment {
     pub qemu_containers: Vec<DeploymentContainer<QemuMachine>>,
     pub container_containers: Vec<DeploymentContainer<ContainerMachine>>,
     pub wired_networks: BTreeSet<ConnectionName>,
+    pub can_buses: BTreeMap<ConnectionName, InterfaceName>,
 }
 
 impl Deployment {
@@ -118,6 +204,14 @@ impl Deployment {
             })
             .collect();
 
+        let can_buses: BTreeMap<ConnectionName, InterfaceName> = graph
+            .connections()
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, c))| c.is_can())
+            .map(|(idx, (name, _))| (name.clone(), InterfaceName::new_system_can_bus(idx)))
+            .collect();
+
         for container in graph.components_by_container().iter() {
             // Renode provider can have multiple machines so its fields can be merged
             // as we iterator over each machine
@@ -168,7 +262,7 @@ impl Deployment {
                             assets.insert(gw.provider.world_path.clone(), gw.guest_world())?;
 
                             // Add world path to args
-                            let mut args = gw.container_args();
+                            let mut args = gw.container_args(global_config.time_scale);
                             args.push(gw.guest_world().display().to_string());
 
                             // Add gazebo path env vars
@@ -198,21 +292,69 @@ impl Deployment {
                                 assets.insert(host_p.clone(), guest_p)?;
                             }
 
-                            gazebo_containers.push(DeploymentContainer {
+                            // Bridge topics onto the machine side of the world's connections
+                            let mut bridges = Vec::new();
+                            for wc in gw.base.connectors.iter() {
+                                if let Some(bridge) = &wc.bridge {
+                                    let endpoint = topic_bridge_endpoint(graph, &gw, &wc.name)?;
+                                    bridges.push((wc.name.clone(), bridge.clone(), endpoint));
+                                }
+                            }
+
+                            let mut command = gw.container_command();
+                            let mut generated_guest_files = BTreeMap::new();
+                            if !bridges.is_empty() {
+                                let script_guest_path = gw.guest_topic_bridge_script_path();
+                                generated_guest_files.insert(
+                                    script_guest_path.clone(),
+                                    gazebo::topic_bridge_script_content(&bridges),
+                                );
+
+                                // We convert '<cmd> <args>' into
+                                // 'bash -c "topic_bridge.sh ; <cmd> <args>"'
+                                let wrapped_args: Vec<String> = [
+                                    script_guest_path.display().to_string(),
+                                    ";".to_owned(),
+                                    command,
+                                ]
+                                .into_iter()
+                                .chain(args.drain(..))
+                                .collect();
+                                command = "/bin/bash".to_owned();
+                                args.push("-c".to_owned());
+                                args.push(wrapped_args.join(" "));
+                            }
+
+                            let mut gazebo_container = DeploymentContainer {
                                 name: ContainerRuntimeName::new_single(
                                     &system_name,
                                     component_name,
                                 ),
                                 uses_host_display: !gw.provider.headless.unwrap_or(true),
+                                vnc: None,
                                 environment_variables,
                                 assets,
-                                generated_guest_files: Default::default(),
-                                command: gw.container_command(),
+                                generated_guest_files,
+                                command,
                                 args,
                                 connections: connections.clone(),
                                 taps_to_bridges: Default::default(),
+                                tap_impairments: Default::default(),
+                                bridge_impairments: host_bridge_impairments(
+                                    graph,
+                                    &connections,
+                                    &[],
+                                )?,
+                                can_interfaces: Default::default(),
+                                resources: gw.base.resources.clone(),
+                                restart: gw.base.restart,
+                                devices: Default::default(),
+                                capabilities: Default::default(),
+                                ports: gw.base.ports.iter().copied().collect(),
                                 components: vec![gw],
-                            });
+                            };
+                            gazebo_container.write_generated_guest_files(&system_name)?;
+                            gazebo_containers.push(gazebo_container);
                         }
                     },
                     WorldOrMachineComponent::Machine(m) => match m.provider {
@@ -246,11 +388,49 @@ impl Deployment {
                                 todo!("Provider configs need to match up");
                             }
 
-                            // Merge assets and env vars
+                            // Merge assets, env vars and resource limits
                             renode_container.assets.merge(&rm.base.assets)?;
+                            for ms in rm.base.storage.iter() {
+                                renode_container.assets.insert(
+                                    ms.storage.image().to_owned(),
+                                    provider::guest_storage_image_path(&rm.base.name, &ms.storage),
+                                )?;
+                            }
                             renode_container
                                 .environment_variables
                                 .merge(&rm.base.environment_variables)?;
+                            renode_container.resources.merge(&rm.base.resources)?;
+                            if let Some(other) = renode_container
+                                .components
+                                .iter()
+                                .find(|m| m.base.restart != rm.base.restart)
+                            {
+                                bail!(
+                                    "Renode machines '{}' and '{}' share a container but don't agree on its restart policy",
+                                    other.base.name,
+                                    rm.base.name,
+                                );
+                            }
+                            renode_container.restart = rm.base.restart;
+                            renode_container
+                                .devices
+                                .extend(rm.base.devices.iter().cloned());
+                            renode_container
+                                .capabilities
+                                .extend(rm.base.capabilities.iter().cloned());
+                            renode_container.ports.extend(rm.published_ports());
+                            if let Some(gdb) = &rm.base.gdb_server {
+                                if let Some(other) = renode_container.components.iter().find(|m| {
+                                    m.base.gdb_server.as_ref().map(|g| g.port) == Some(gdb.port)
+                                }) {
+                                    bail!(
+                                        "Renode machines '{}' and '{}' share a container but use the same GDB server port {}",
+                                        other.base.name,
+                                        rm.base.name,
+                                        gdb.port
+                                    );
+                                }
+                            }
 
                             // Add bin as guest asset file
                             if renode_container
@@ -312,23 +492,86 @@ impl Deployment {
                                 .0
                                 .insert(qm.base.bin.as_ref().unwrap().clone(), qm.guest_bin());
 
+                            // Add storage images to assets
+                            for ms in qm.base.storage.iter() {
+                                assets.insert(
+                                    ms.storage.image().to_owned(),
+                                    provider::guest_storage_image_path(&qm.base.name, &ms.storage),
+                                )?;
+                            }
+
+                            let mut devices: BTreeSet<Device> =
+                                qm.base.devices.iter().cloned().collect();
+                            if qm.uses_kvm(global_config.time_scale) {
+                                devices.insert(Device::new_passthrough(qemu::KVM_DEVICE_PATH));
+                            }
+
                             // Add guest bin path to args
-                            let mut args = qm.container_args();
+                            let mut args = qm.container_args(global_config.time_scale);
                             args.push(qm.guest_bin().display().to_string());
 
+                            let (mut setup_commands, storage_args) = qm.storage_args();
+                            args.extend(storage_args);
+
+                            // CAN controllers are bridged to the host's virtual CAN interfaces
+                            let can_interfaces: BTreeMap<ConnectionName, InterfaceName> = qm
+                                .base
+                                .connectors
+                                .iter()
+                                .filter_map(|c| {
+                                    Some((c.name.clone(), can_buses.get(&c.name)?.clone()))
+                                })
+                                .collect();
+                            args.extend(qm.can_bus_args(&can_interfaces));
+
+                            // Wrap the command to create the storage overlays and CAN interfaces
+                            // first, we convert '<cmd> <args>' into
+                            // 'bash -c "<setup> ; <cmd> <args>"', quoting the command and its args
+                            setup_commands
+                                .extend(provider::can_bus_setup_commands(can_interfaces.values()));
+                            let mut command = qm.container_command();
+                            if !setup_commands.is_empty() {
+                                let wrapped_args: Vec<String> = setup_commands
+                                    .into_iter()
+                                    .chain(std::iter::once(
+                                        std::iter::once(command)
+                                            .chain(args.drain(..))
+                                            .map(|a| provider::sh_quote(&a))
+                                            .collect::<Vec<_>>()
+                                            .join(" "),
+                                    ))
+                                    .collect();
+                                command = "/bin/bash".to_owned();
+                                args.push("-c".to_owned());
+                                args.push(wrapped_args.join(" ; "));
+                            }
+
                             qemu_containers.push(DeploymentContainer {
                                 name: ContainerRuntimeName::new_single(
                                     &system_name,
                                     component_name,
                                 ),
                                 uses_host_display: !qm.provider.no_graphic.unwrap_or(true),
+                                vnc: None,
                                 environment_variables: qm.base.environment_variables.clone(),
                                 assets,
                                 generated_guest_files: Default::default(),
-                                command: qm.container_command(),
+                                command,
                                 args,
                                 connections: connections.clone(),
                                 taps_to_bridges: Default::default(),
+                                tap_impairments: Default::default(),
+                                bridge_impairments: host_bridge_impairments(
+                                    graph,
+                                    &connections,
+                                    &qm.base.connectors,
+                                )?,
+                                can_interfaces,
+                                resources: qm.base.resources.clone(),
+                                restart: qm.base.restart,
+                                devices,
+                                capabilities: qm.base.capabilities.iter().cloned().collect(),
+                                ports: qm.published_ports().into_iter().collect(),
                                 components: vec![qm],
                             });
                         }
@@ -337,22 +580,64 @@ impl Deployment {
                                 base: m.base,
                                 provider: p,
                             };
-                            // TODO
-                            // add path/to/guest bin to assets and args
-                            // whatever we need for this kind
+                            // Add bin path to assets
+                            let mut assets = cm.base.assets.clone();
+                            if let (Some(bin), Some(guest_bin)) =
+                                (cm.base.bin.as_ref(), cm.guest_bin())
+                            {
+                                assets.insert(bin.clone(), guest_bin)?;
+                            }
+
+                            let (command, args) = match cm.container_cmd() {
+                                Some(mut cmd) if !cmd.is_empty() => {
+                                    let command = cmd.remove(0);
+                                    (command, cmd)
+                                }
+                                _ => Default::default(),
+                            };
+
+                            // The CAN interfaces are created on the host's network before the
+                            // container starts, the env vars tell the guest which interface
+                            // backs each connector
+                            let mut environment_variables = cm.base.environment_variables.clone();
+                            let mut can_interfaces = BTreeMap::new();
+                            for c in cm.base.connectors.iter() {
+                                if let Some(can_iface) = can_buses.get(&c.name) {
+                                    let (k, v) = provider::container::can_interface_env_kv(
+                                        &c.interface,
+                                        can_iface,
+                                    );
+                                    environment_variables.insert(k, v)?;
+                                    can_interfaces.insert(c.name.clone(), can_iface.clone());
+                                }
+                            }
+
                             container_containers.push(DeploymentContainer {
                                 name: ContainerRuntimeName::new_single(
                                     &system_name,
                                     component_name,
                                 ),
                                 uses_host_display: false, // TODO - surface a config field for this
-                                environment_variables: cm.base.environment_variables.clone(),
-                                assets: cm.base.assets.clone(),
+                                vnc: None,
+                                environment_variables,
+                                assets,
                                 generated_guest_files: Default::default(),
-                                command: Default::default(),
-                                args: Default::default(),
+                                command,
+                                args,
                                 connections: connections.clone(),
                                 taps_to_bridges: Default::default(),
+                                tap_impairments: Default::default(),
+                                bridge_impairments: host_bridge_impairments(
+                                    graph,
+                                    &connections,
+                                    &cm.base.connectors,
+                                )?,
+                                can_interfaces,
+                                resources: cm.base.resources.clone(),
+                                restart: cm.base.restart,
+                                devices: cm.base.devices.iter().cloned().collect(),
+                                capabilities: cm.base.capabilities.iter().cloned().collect(),
+                                ports: cm.base.ports.iter().copied().collect(),
                                 components: vec![cm],
                             });
                         }
@@ -407,10 +692,64 @@ impl Deployment {
                     renode_container.taps_to_bridges.insert(tap, br);
                 }
 
-                if !renode_container.taps_to_bridges.is_empty() {
+                // Connector-level impairments take precedence over the connection-level ones,
+                // all the connectors within this container must agree since they share a tap
+                for (conn_name, tap_dev) in tap_devices.iter() {
+                    let mut impairment = graph
+                        .connection(conn_name)?
+                        .network_impairment()
+                        .cloned()
+                        .unwrap_or_default();
+                    let mut connector_impairments = BTreeSet::new();
+                    for c in renode_container
+                        .components
+                        .iter()
+                        .flat_map(|m| m.base.connectors.iter())
+                        .filter(|c| &c.name == conn_name)
+                    {
+                        if let Some(imp) = c.properties.network_impairment()? {
+                            connector_impairments.insert(imp);
+                        }
+                    }
+                    if connector_impairments.len() > 1 {
+                        bail!("Renode machines sharing a container have conflicting impairment settings for connection '{conn_name}'");
+                    }
+                    if let Some(connector_impairment) = connector_impairments.into_iter().next() {
+                        impairment = impairment.overridden_by(&connector_impairment);
+                    }
+                    if !impairment.is_empty() {
+                        renode_container
+                            .tap_impairments
+                            .insert(tap_dev.clone(), impairment);
+                    }
+                }
+
+                // CAN hubs of buses that leave the container are bridged to the host's
+                // virtual CAN interfaces, which the other containers' machines share
+                for conn_name in container.connections.iter() {
+                    let Some(can_iface) = can_buses.get(conn_name) else {
+                        continue;
+                    };
+                    let spans_containers = graph
+                        .connections_to_components()
+                        .get(conn_name)
+                        .map(|comps| !comps.is_subset(&container.components))
+                        .unwrap_or(false);
+                    if spans_containers {
+                        renode_container
+                            .can_interfaces
+                            .insert(conn_name.clone(), can_iface.clone());
+                    }
+                }
+
+                if !renode_container.taps_to_bridges.is_empty()
+                    || !renode_container.can_interfaces.is_empty()
+                {
                     let net_setup_guest_path = renode::guest_external_network_setup_script_path();
                     let net_setup_content = renode::external_network_setup_script_content(
                         &renode_container.taps_to_bridges,
+                        &renode_container.tap_impairments,
+                        &renode_container.can_interfaces,
                     );
                     renode_container
                         .generated_guest_files
@@ -445,11 +784,14 @@ impl Deployment {
                 }
 
                 let mut resc_content = Vec::new();
-                RenodeScriptGen::new(&mut resc_content).generate(
-                    &renode_container.components,
-                    &renode_container.connections,
-                    &tap_devices,
-                )?;
+                RenodeScriptGen::new(&mut resc_content)
+                    .with_time_scale(global_config.time_scale)
+                    .with_can_interfaces(renode_container.can_interfaces.clone())
+                    .generate(
+                        &renode_container.components,
+                        &renode_container.connections,
+                        &tap_devices,
+                    )?;
 
                 renode_container
                     .generated_guest_files
@@ -464,52 +806,25 @@ impl Deployment {
 
                 renode_container.name = ContainerRuntimeName::new_multi(&system_name, &comp_names);
 
-                // TODO - using a pseudo tempdir on the host for
-                // ephemeral store of file generated for the guest so
-                // they can be treated as normal assets for now
-                //
-                // TODO use system name as part of arbitration
-                if !renode_container.generated_guest_files.is_empty() {
-                    for (guest_path, content) in renode_container.generated_guest_files.iter() {
-                        let file_name = guest_path.file_name().unwrap();
-                        let host_dir = PathBuf::from("/tmp")
-                            .join("conductor_generated_assets")
-                            .join(system_name.as_str())
-                            .join(renode_container.name.as_ref());
-                        let host_path = host_dir.join(file_name);
-                        fs::create_dir_all(&host_dir)?;
-
-                        // TODO - don't need to make everything executable
-                        #[cfg(unix)]
-                        {
-                            use std::io::Write;
-                            use std::os::unix::fs::OpenOptionsExt;
-
-                            let mut f = fs::OpenOptions::new()
-                                .mode(0o777)
-                                .write(true)
-                                .create(true)
-                                .truncate(true)
-                                .open(&host_path)?;
-                            f.write_all(content.as_bytes())?;
-                        }
-
-                        // TODO - we don't support windows yet
-                        #[cfg(not(unix))]
-                        {
-                            fs::write(&host_path, content)?;
-                        }
-
-                        renode_container
-                            .assets
-                            .insert(host_path, guest_path.clone())?;
-                    }
-                }
+                renode_container.write_generated_guest_files(&system_name)?;
 
                 renode_containers.push(renode_container);
             }
         }
 
+        renode_containers
+            .iter_mut()
+            .for_each(DeploymentContainer::add_network_privileges);
+        qemu_containers
+            .iter_mut()
+            .for_each(DeploymentContainer::add_network_privileges);
+        gazebo_containers
+            .iter_mut()
+            .for_each(DeploymentContainer::add_network_privileges);
+        container_containers
+            .iter_mut()
+            .for_each(DeploymentContainer::add_network_privileges);
+
         if gazebo_containers
             .iter()
             .map(|c| c.components.len())
@@ -595,18 +910,35 @@ impl Deployment {
             .chain(container_containers.iter().map(|c| c.uses_host_display))
             .any(|uses_host_display| uses_host_display);
 
-        if at_least_one_uses_display {
-            // Setup xauth/display environement
-            let display = global_config.display.as_ref().expect("DISPLAY is required");
-            let xauthority = global_config
-                .xauthority
-                .as_ref()
-                .expect("Xauthority is required");
+        if let (true, Some(vnc)) = (at_least_one_uses_display, global_config.vnc) {
+            // Each GUI container gets its own virtual display and VNC server
+            let mut vnc_idx = 0;
+            use_vnc_displays(&system_name, &vnc, &mut vnc_idx, &mut gazebo_containers)?;
+            use_vnc_displays(&system_name, &vnc, &mut vnc_idx, &mut renode_containers)?;
+            use_vnc_displays(&system_name, &vnc, &mut vnc_idx, &mut qemu_containers)?;
+            use_vnc_displays(&system_name, &vnc, &mut vnc_idx, &mut container_containers)?;
+        } else if at_least_one_uses_display {
+            let wayland_socket = global_config.wayland_socket.as_ref();
+
+            // Setup xauth/display environement, when Wayland is also available
+            // X11 (i.e. XWayland) is the fallback
+            let x11 = match (&global_config.display, &global_config.xauthority) {
+                (Some(display), Some(xauthority)) => {
+                    // We create a system-specific guest xauth file, as a ro asset
+                    let guest_xauth_path = display::system_guest_xauth_file_path(&system_name);
+                    let mut guest_xauth = fs::File::create(&guest_xauth_path)?;
+                    display::write_guest_xauth(display, xauthority, &mut guest_xauth)?;
+                    Some((display, guest_xauth_path))
+                }
+                _ => None,
+            };
+
+            if wayland_socket.is_none() && x11.is_none() {
+                bail!("GUI components require a host display, but neither an X11 display and Xauthority nor a Wayland display were found");
+            }
 
-            // We create a system-specific guest xauth file, as a ro asset
-            let guest_xauth_path = display::system_guest_xauth_file_path(&system_name);
-            let mut guest_xauth = fs::File::create(&guest_xauth_path)?;
-            display::write_guest_xauth(display, xauthority, &mut guest_xauth)?;
+            let (qt_platform, gdk_backend) =
+                display::toolkit_backends(wayland_socket.is_some(), x11.is_some());
 
             for (env, assets) in gazebo_containers
                 .iter_mut()
@@ -627,17 +959,70 @@ impl Deployment {
                         .filter_map(env_and_assets_for_gui_container),
                 )
             {
-                env.insert(display::DISPLAY_ENV_VAR.to_owned(), display.clone())?;
-                env.insert(
-                    display::XAUTHORITY_ENV_VAR.to_owned(),
-                    guest_xauth_path.display().to_string(),
-                )?;
-
-                assets.insert(
-                    PathBuf::from(display::HOST_X11_DOMAIN_SOCKET),
-                    PathBuf::from(format!("{}:ro", display::HOST_X11_DOMAIN_SOCKET)),
-                )?;
-                assets.insert(guest_xauth_path.clone(), guest_xauth_path.clone())?;
+                if let Some(socket) = wayland_socket {
+                    env.insert(
+                        display::WAYLAND_DISPLAY_ENV_VAR.to_owned(),
+                        display::GUEST_WAYLAND_DISPLAY.to_owned(),
+                    )?;
+                    env.insert(
+                        display::XDG_RUNTIME_DIR_ENV_VAR.to_owned(),
+                        display::GUEST_WAYLAND_RUNTIME_DIR.to_owned(),
+                    )?;
+                    assets.insert(socket.clone(), display::guest_wayland_socket_path())?;
+                }
+
+                if let Some((display, guest_xauth_path)) = &x11 {
+                    env.insert(display::DISPLAY_ENV_VAR.to_owned(), (*display).clone())?;
+                    env.insert(
+                        display::XAUTHORITY_ENV_VAR.to_owned(),
+                        guest_xauth_path.display().to_string(),
+                    )?;
+
+                    assets.insert(
+                        PathBuf::from(display::HOST_X11_DOMAIN_SOCKET),
+                        PathBuf::from(format!("{}:ro", display::HOST_X11_DOMAIN_SOCKET)),
+                    )?;
+                    assets.insert(guest_xauth_path.clone(), guest_xauth_path.clone())?;
+                }
+
+                // Gazebo's GUI is Qt and Renode's XWT is GTK, components can override these
+                for (k, v) in [
+                    (display::QT_PLATFORM_ENV_VAR, qt_platform),
+                    (display::GDK_BACKEND_ENV_VAR, gdk_backend),
+                ] {
+                    if !env.0.contains_key(k) {
+                        env.insert(k.to_owned(), v.to_owned())?;
+                    }
+                }
+            }
+        }
+
+        // Containers share the host's network, so each port can only be used by one of them
+        let mut host_ports = BTreeMap::new();
+        for (name, port) in gazebo_containers
+            .iter()
+            .flat_map(published_ports)
+            .chain(renode_containers.iter().flat_map(published_ports))
+            .chain(qemu_containers.iter().flat_map(published_ports))
+            .chain(container_containers.iter().flat_map(published_ports))
+        {
+            if let Some(prev_name) = host_ports.insert(port, name) {
+                bail!("Host port {port} is used by both '{prev_name}' and '{name}'");
+            }
+        }
+
+        // Containers share the host's network, so each bridge has a single impairment
+        let mut impaired_bridges = BTreeMap::new();
+        for (name, bridge, impairment) in gazebo_containers
+            .iter()
+            .flat_map(bridge_impairments)
+            .chain(qemu_containers.iter().flat_map(bridge_impairments))
+            .chain(container_containers.iter().flat_map(bridge_impairments))
+        {
+            if let Some((prev_name, prev)) = impaired_bridges.insert(bridge, (name, impairment)) {
+                if prev != impairment {
+                    bail!("'{prev_name}' and '{name}' have conflicting impairment settings for the host's link to network bridge '{bridge}'");
+                }
             }
         }
 
@@ -648,10 +1033,178 @@ impl Deployment {
             qemu_containers,
             container_containers,
             wired_networks,
+            can_buses,
         })
     }
 }
 
+/// The host bridge backing a wired network connection
+pub(crate) fn wired_network_bridge(
+    graph: &ComponentGraph<WorldOrMachineComponent>,
+    conn_name: &ConnectionName,
+) -> Option<BridgeName> {
+    graph
+        .connections()
+        .keys()
+        .position(|c| c == conn_name)
+        .map(InterfaceName::new_system_wired_network)
+}
+
+/// Components outside of Renode use the host's network, so their traffic onto a wired
+/// network leaves through the connection's host bridge, which is impaired in their place.
+/// Connector-level impairments take precedence over the connection-level ones.
+fn host_bridge_impairments(
+    graph: &ComponentGraph<WorldOrMachineComponent>,
+    connections: &[Connection],
+    connectors: &[MachineConnector],
+) -> Result<BTreeMap<BridgeName, NetworkImpairment>> {
+    let mut impairments = BTreeMap::new();
+    for conn in connections.iter().filter(|c| c.is_network()) {
+        let mut impairment = conn.network_impairment().cloned().unwrap_or_default();
+        if let Some(connector) = connectors.iter().find(|c| &c.name == conn.name()) {
+            if let Some(imp) = connector.properties.network_impairment()? {
+                impairment = impairment.overridden_by(&imp);
+            }
+        }
+        if !impairment.is_empty() {
+            // NOTE: unwrap ok, the connection is from the graph
+            impairments.insert(
+                wired_network_bridge(graph, conn.name()).unwrap(),
+                impairment,
+            );
+        }
+    }
+    Ok(impairments)
+}
+
+/// Resolves the machine side of a world connector's topic bridge
+fn topic_bridge_endpoint(
+    graph: &ComponentGraph<WorldOrMachineComponent>,
+    world: &GazeboWorld,
+    conn_name: &ConnectionName,
+) -> Result<TopicBridgeEndpoint> {
+    let connection = graph.connection(conn_name)?;
+    let world_name: ComponentName = world.base.name.clone().into();
+
+    if connection.kind() == ConnectionKind::Network {
+        let connection_index_in_config = graph
+            .connections()
+            .keys()
+            .position(|c| c == conn_name)
+            .unwrap();
+        // NOTE: already sanity checked by the config
+        let port = world
+            .base
+            .connectors
+            .iter()
+            .find(|c| &c.name == conn_name)
+            .and_then(|c| c.bridge.as_ref())
+            .and_then(|b| b.port)
+            .unwrap();
+        return Ok(TopicBridgeEndpoint::NetworkUdp {
+            bridge: InterfaceName::new_system_wired_network(connection_index_in_config),
+            port,
+        });
+    }
+
+    let mut machines = Vec::new();
+    for comp_name in graph
+        .connections_to_components()
+        .get(conn_name)
+        .into_iter()
+        .flatten()
+        .filter(|c| *c != &world_name)
+    {
+        if let WorldOrMachineComponent::Machine(m) = graph.component(comp_name)? {
+            machines.push(m);
+        }
+    }
+    let m = match machines.as_slice() {
+        [m] => m,
+        _ => bail!(
+            "World '{}' bridges a topic onto connection '{conn_name}', which must connect to exactly one machine",
+            world.base.name
+        ),
+    };
+    let mc = m
+        .base
+        .connectors
+        .iter()
+        .find(|c| &c.name == conn_name)
+        .unwrap();
+
+    match (&m.provider, &mc.properties) {
+        (MachineProvider::Renode(_), ConnectorProperties::Uart(p)) => match p.port {
+            Some(port) => Ok(TopicBridgeEndpoint::UartSocket(port)),
+            None => bail!(
+                "World '{}' bridges a topic onto UART connection '{conn_name}', which requires a port on the connector of machine '{}'",
+                world.base.name,
+                m.base.name
+            ),
+        },
+        (MachineProvider::Renode(p), ConnectorProperties::Gpio(gp)) => {
+            match (p.cli.port, gp.destination_pin) {
+                (Some(monitor_port), Some(pin)) => Ok(TopicBridgeEndpoint::RenodeGpio {
+                    monitor_port,
+                    machine: m.base.name.clone(),
+                    interface: mc.interface.clone(),
+                    pin,
+                }),
+                _ => bail!(
+                    "World '{}' bridges a topic onto GPIO connection '{conn_name}', which requires a monitor port on machine '{}' and a destination pin on its connector",
+                    world.base.name,
+                    m.base.name
+                ),
+            }
+        }
+        _ => bail!(
+            "World '{}' bridges a topic onto connection '{conn_name}', which is only supported for Renode machines",
+            world.base.name
+        ),
+    }
+}
+
+fn use_vnc_displays<C>(
+    system_name: &SystemName,
+    vnc: &VncDisplay,
+    vnc_idx: &mut u16,
+    containers: &mut [DeploymentContainer<C>],
+) -> Result<()> {
+    for c in containers.iter_mut().filter(|c| c.uses_host_display) {
+        let c_vnc = vnc.nth(*vnc_idx);
+        *vnc_idx += 1;
+
+        // We convert '<cmd> <args>' into 'vnc_display.sh <cmd> <args>'
+        let guest_path = display::guest_vnc_setup_script_path();
+        c.generated_guest_files.insert(
+            guest_path.clone(),
+            display::vnc_setup_script_content(&c_vnc),
+        );
+        let command = std::mem::replace(&mut c.command, guest_path.display().to_string());
+        c.args.insert(0, command);
+        c.vnc = Some(c_vnc);
+        c.ports.insert(PublishedPort::new_tcp(c_vnc.port));
+        c.ports.extend(c_vnc.novnc_port.map(PublishedPort::new_tcp));
+
+        c.write_generated_guest_files(system_name)?;
+    }
+    Ok(())
+}
+
+fn published_ports<C>(
+    c: &DeploymentContainer<C>,
+) -> impl Iterator<Item = (&ContainerRuntimeName, PublishedPort)> {
+    c.ports.iter().map(move |p| (&c.name, *p))
+}
+
+fn bridge_impairments<C>(
+    c: &DeploymentContainer<C>,
+) -> impl Iterator<Item = (&ContainerRuntimeName, &BridgeName, &NetworkImpairment)> {
+    c.bridge_impairments
+        .iter()
+        .map(move |(b, i)| (&c.name, b, i))
+}
+
 fn env_and_assets_for_gui_container<C>(
     c: &mut DeploymentContainer<C>,
 ) -> Option<(
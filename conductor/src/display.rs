@@ -1,3 +1,4 @@
+use crate::provider::GUEST_RESOURCES_PATH;
 use std::io::{self, BufReader, ErrorKind, Read, Write};
 use std::{
     fs::File,
@@ -7,6 +8,117 @@ use std::{
 pub const XAUTHORITY_ENV_VAR: &str = "XAUTHORITY";
 pub const DISPLAY_ENV_VAR: &str = "DISPLAY";
 pub const HOST_X11_DOMAIN_SOCKET: &str = "/tmp/.X11-unix";
+pub const WAYLAND_DISPLAY_ENV_VAR: &str = "WAYLAND_DISPLAY";
+pub const XDG_RUNTIME_DIR_ENV_VAR: &str = "XDG_RUNTIME_DIR";
+pub const QT_PLATFORM_ENV_VAR: &str = "QT_QPA_PLATFORM";
+pub const GDK_BACKEND_ENV_VAR: &str = "GDK_BACKEND";
+
+/// The guest's runtime dir, the host's Wayland socket is mounted within
+pub const GUEST_WAYLAND_RUNTIME_DIR: &str = "/tmp/conductor-wayland";
+pub const GUEST_WAYLAND_DISPLAY: &str = "wayland-0";
+
+/// The `display` global config value that selects virtual VNC displays
+pub const VNC_DISPLAY_MODE: &str = "vnc";
+pub const DEFAULT_VNC_PORT: u16 = 5900;
+
+// Containers share the host's network namespace, and so its abstract X11 sockets,
+// each container gets its own display number starting here
+const VNC_BASE_DISPLAY_NUMBER: u16 = 100;
+const VNC_SETUP_FILE_NAME: &str = "vnc_display.sh";
+
+/// A virtual display served over VNC, and optionally noVNC, from within a container
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct VncDisplay {
+    pub display_number: u16,
+    pub port: u16,
+    pub novnc_port: Option<u16>,
+}
+
+impl VncDisplay {
+    pub(crate) fn new(port: u16, novnc_port: Option<u16>) -> Self {
+        Self {
+            display_number: VNC_BASE_DISPLAY_NUMBER,
+            port,
+            novnc_port,
+        }
+    }
+
+    /// The display of the `idx`-th GUI container in a system, relative to this one
+    pub(crate) fn nth(&self, idx: u16) -> Self {
+        Self {
+            display_number: self.display_number + idx,
+            port: self.port + idx,
+            novnc_port: self.novnc_port.map(|p| p + idx),
+        }
+    }
+
+    pub fn vnc_url(&self) -> String {
+        format!("vnc://localhost:{}", self.port)
+    }
+
+    pub fn novnc_url(&self) -> Option<String> {
+        self.novnc_port
+            .map(|p| format!("http://localhost:{p}/vnc.html?autoconnect=true"))
+    }
+}
+
+pub(crate) fn guest_vnc_setup_script_path() -> PathBuf {
+    PathBuf::from(GUEST_RESOURCES_PATH).join(VNC_SETUP_FILE_NAME)
+}
+
+// NOTE:
+// * on the guest, requires Xvfb, x11vnc and, for noVNC, websockify and novnc
+// * the script execs its arguments so the wrapped command remains the main process
+pub(crate) fn vnc_setup_script_content(vnc: &VncDisplay) -> String {
+    let mut script = format!(
+        indoc::indoc! {r#"
+            #!/usr/bin/env bash
+            set -euo pipefail
+            export {env}=:{num}
+            Xvfb :{num} -screen 0 1920x1080x24 -nolisten tcp &
+            while [ ! -e {sock}/X{num} ]; do sleep 0.1; done
+            x11vnc -display :{num} -rfbport {port} -forever -shared -nopw -quiet &
+        "#},
+        env = DISPLAY_ENV_VAR,
+        num = vnc.display_number,
+        sock = HOST_X11_DOMAIN_SOCKET,
+        port = vnc.port,
+    );
+    if let Some(novnc_port) = vnc.novnc_port {
+        script.push_str(&format!(
+            "websockify --web /usr/share/novnc {novnc_port} localhost:{} &\n",
+            vnc.port
+        ));
+    }
+    script.push_str("exec \"$@\"\n");
+    script
+}
+
+/// Resolves a Wayland display name into its socket path, relative names are
+/// within `$XDG_RUNTIME_DIR`
+pub(crate) fn host_wayland_socket_path(wayland_display: &str) -> Option<PathBuf> {
+    let display = Path::new(wayland_display);
+    if display.is_absolute() {
+        Some(display.to_path_buf())
+    } else {
+        std::env::var(XDG_RUNTIME_DIR_ENV_VAR)
+            .ok()
+            .map(|dir| PathBuf::from(dir).join(display))
+    }
+}
+
+pub(crate) fn guest_wayland_socket_path() -> PathBuf {
+    PathBuf::from(GUEST_WAYLAND_RUNTIME_DIR).join(GUEST_WAYLAND_DISPLAY)
+}
+
+/// The Qt platform and GDK backend to use, preferring Wayland when X11 is also available
+pub(crate) fn toolkit_backends(wayland: bool, x11: bool) -> (&'static str, &'static str) {
+    match (wayland, x11) {
+        (true, true) => ("wayland;xcb", "wayland,x11"),
+        (true, false) => ("wayland", "wayland"),
+        _ => ("xcb", "x11"),
+    }
+}
 
 pub(crate) fn system_guest_xauth_file_path<N: AsRef<str>>(system_name: N) -> PathBuf {
     PathBuf::from("/tmp").join(format!(".{}.xauth", system_name.as_ref(),))
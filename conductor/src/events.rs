@@ -0,0 +1,122 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const EVENTS_FILE_NAME: &str = "events.jsonl";
+
+/// Something that happened to a system, e.g. an injected fault or a restarted
+/// container
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Event {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub kind: EventKind,
+    /// The component or connection the event concerns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub message: String,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    /// The system was started
+    #[display(fmt = "start")]
+    Start,
+    #[display(fmt = "fault")]
+    Fault,
+    /// A supervised container exited
+    #[display(fmt = "exit")]
+    Exit,
+    /// A supervised container was restarted by its restart policy
+    #[display(fmt = "restart")]
+    Restart,
+}
+
+impl Event {
+    pub fn now(kind: EventKind, target: Option<String>, message: String) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Event {
+            timestamp_ms,
+            kind,
+            target,
+            message,
+        }
+    }
+}
+
+/// A system's event log, a JSON object per line in its state directory
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    path: PathBuf,
+}
+
+impl EventLog {
+    pub fn new(state_dir: &Path) -> Self {
+        EventLog {
+            path: state_dir.join(EVENTS_FILE_NAME),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, event: &Event) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// The logged events, oldest first. A system without a log has no events.
+    pub fn read(&self) -> io::Result<Vec<Event>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(io::Error::from))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = EventLog::new(&dir.path().join("my_system"));
+        assert!(log.read().unwrap().is_empty());
+
+        let a = Event::now(
+            EventKind::Fault,
+            Some("m0".to_owned()),
+            "kill 'm0'".to_owned(),
+        );
+        let b = Event::now(EventKind::Fault, None, "something".to_owned());
+        log.append(&a).unwrap();
+        log.append(&b).unwrap();
+        assert_eq!(log.read().unwrap(), vec![a, b]);
+    }
+}
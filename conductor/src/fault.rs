@@ -0,0 +1,181 @@
+use crate::{
+    network_impairment::Percentage,
+    types::{ComponentName, ConnectionName, InterfaceName, MachineName},
+};
+use conductor_config::{FaultConfig, FaultKind};
+use std::fmt;
+
+#[derive(Clone, Eq, PartialEq, Debug, thiserror::Error)]
+pub enum FaultError {
+    #[error("Invalid fault target '{_0}'")]
+    InvalidTarget(String),
+    #[error("Invalid fault interface '{_0}'")]
+    InvalidInterface(String),
+    #[error("The {_0} fault requires a {_1}")]
+    Missing(&'static str, &'static str),
+    #[error("Invalid fault percentage '{_0}', expected a percentage like '5%'")]
+    InvalidPercentage(String),
+}
+
+/// A fault injected into a running system, see [`System::inject_fault`](crate::System::inject_fault)
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Fault {
+    /// Kill a component's container, it isn't restarted
+    Kill(ComponentName),
+    Restart(ComponentName),
+    /// Freeze every process in a component's container
+    Freeze(ComponentName),
+    Thaw(ComponentName),
+    /// Take down the links of a network connection, i.e. its host bridge and the
+    /// Renode tap devices attached to it
+    Disconnect(ConnectionName),
+    Reconnect(ConnectionName),
+    /// Disconnect the Renode machines from a UART connection
+    CutUart(ConnectionName),
+    RestoreUart(ConnectionName),
+    Gpio {
+        machine: MachineName,
+        interface: InterfaceName,
+        pin: u16,
+        value: bool,
+    },
+    /// Corrupt a percentage of the packets on a network connection, replacing
+    /// its impairment
+    Corrupt {
+        connection: ConnectionName,
+        percentage: Percentage,
+    },
+}
+
+impl Fault {
+    pub fn from_config(cfg: FaultConfig) -> Result<Self, FaultError> {
+        let name = cfg.fault.as_str();
+        let target = cfg.target;
+        let component = || {
+            ComponentName::new_canonicalize(&target)
+                .ok_or_else(|| FaultError::InvalidTarget(target.clone()))
+        };
+        let connection = || {
+            ConnectionName::new_canonicalize(&target)
+                .ok_or_else(|| FaultError::InvalidTarget(target.clone()))
+        };
+        Ok(match cfg.fault {
+            FaultKind::Kill => Fault::Kill(component()?),
+            FaultKind::Restart => Fault::Restart(component()?),
+            FaultKind::Freeze => Fault::Freeze(component()?),
+            FaultKind::Thaw => Fault::Thaw(component()?),
+            FaultKind::Disconnect => Fault::Disconnect(connection()?),
+            FaultKind::Reconnect => Fault::Reconnect(connection()?),
+            FaultKind::CutUart => Fault::CutUart(connection()?),
+            FaultKind::RestoreUart => Fault::RestoreUart(connection()?),
+            FaultKind::Gpio => {
+                let interface = cfg
+                    .interface
+                    .ok_or(FaultError::Missing(name, "interface"))?;
+                Fault::Gpio {
+                    machine: MachineName::new_canonicalize(&target)
+                        .ok_or_else(|| FaultError::InvalidTarget(target.clone()))?,
+                    interface: InterfaceName::new_canonicalize(&interface)
+                        .ok_or(FaultError::InvalidInterface(interface))?,
+                    pin: cfg.pin.ok_or(FaultError::Missing(name, "pin"))?,
+                    value: cfg.value.ok_or(FaultError::Missing(name, "value"))?,
+                }
+            }
+            FaultKind::Corrupt => {
+                let percentage = cfg
+                    .percentage
+                    .ok_or(FaultError::Missing(name, "percentage"))?;
+                Fault::Corrupt {
+                    connection: connection()?,
+                    percentage: percentage
+                        .parse()
+                        .map_err(|_| FaultError::InvalidPercentage(percentage))?,
+                }
+            }
+        })
+    }
+
+    /// The component or connection the fault is injected into
+    pub fn target(&self) -> &str {
+        match self {
+            Fault::Kill(c) | Fault::Restart(c) | Fault::Freeze(c) | Fault::Thaw(c) => c.as_str(),
+            Fault::Disconnect(c)
+            | Fault::Reconnect(c)
+            | Fault::CutUart(c)
+            | Fault::RestoreUart(c)
+            | Fault::Corrupt { connection: c, .. } => c.as_str(),
+            Fault::Gpio { machine, .. } => machine.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Kill(c) => write!(f, "kill '{c}'"),
+            Fault::Restart(c) => write!(f, "restart '{c}'"),
+            Fault::Freeze(c) => write!(f, "freeze '{c}'"),
+            Fault::Thaw(c) => write!(f, "thaw '{c}'"),
+            Fault::Disconnect(c) => write!(f, "disconnect '{c}'"),
+            Fault::Reconnect(c) => write!(f, "reconnect '{c}'"),
+            Fault::CutUart(c) => write!(f, "cut UART '{c}'"),
+            Fault::RestoreUart(c) => write!(f, "restore UART '{c}'"),
+            Fault::Gpio {
+                machine,
+                interface,
+                pin,
+                value,
+            } => write!(f, "set '{machine}' GPIO {interface} pin {pin} to {value}"),
+            Fault::Corrupt {
+                connection,
+                percentage,
+            } => write!(f, "corrupt {percentage} of the packets on '{connection}'"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg(fault: FaultKind, target: &str) -> FaultConfig {
+        FaultConfig {
+            fault,
+            target: target.to_owned(),
+            interface: None,
+            pin: None,
+            value: None,
+            percentage: None,
+        }
+    }
+
+    #[test]
+    fn fault_from_config() {
+        let f = Fault::from_config(cfg(FaultKind::Kill, "my machine")).unwrap();
+        assert_eq!(f.to_string(), "kill 'my_machine'");
+        assert_eq!(f.target(), "my_machine");
+
+        let f = Fault::from_config(FaultConfig {
+            percentage: Some("2.5%".to_owned()),
+            ..cfg(FaultKind::Corrupt, "net")
+        })
+        .unwrap();
+        assert_eq!(f.to_string(), "corrupt 2.50% of the packets on 'net'");
+
+        assert_eq!(
+            Fault::from_config(FaultConfig {
+                interface: Some("sysbus.gpioPortA".to_owned()),
+                pin: Some(1),
+                ..cfg(FaultKind::Gpio, "m0")
+            }),
+            Err(FaultError::Missing("gpio", "value"))
+        );
+        assert_eq!(
+            Fault::from_config(FaultConfig {
+                percentage: Some("lots".to_owned()),
+                ..cfg(FaultKind::Corrupt, "net")
+            }),
+            Err(FaultError::InvalidPercentage("lots".to_owned()))
+        );
+    }
+}
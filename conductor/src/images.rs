@@ -0,0 +1,65 @@
+use crate::containers::{Container, ContainerBuilder};
+use derive_more::Display;
+use std::str::FromStr;
+
+/// The stock provider images, their containerfiles are embedded in the binary so they
+/// can be built locally
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+pub enum StockImage {
+    #[display(fmt = "{}", "self.as_str()")]
+    Gazebo,
+    #[display(fmt = "{}", "self.as_str()")]
+    Qemu,
+    #[display(fmt = "{}", "self.as_str()")]
+    Renode,
+}
+
+impl StockImage {
+    pub const ALL: [Self; 3] = [Self::Gazebo, Self::Qemu, Self::Renode];
+
+    pub fn as_str(self) -> &'static str {
+        use StockImage::*;
+        match self {
+            Gazebo => "gazebo",
+            Qemu => "qemu",
+            Renode => "renode",
+        }
+    }
+
+    pub fn containerfile(self) -> &'static str {
+        use StockImage::*;
+        match self {
+            Gazebo => include_str!("../../images/gazebo/Containerfile"),
+            Qemu => include_str!("../../images/qemu/Containerfile"),
+            Renode => include_str!("../../images/renode/Containerfile"),
+        }
+    }
+
+    /// The published image providers use by default
+    pub fn remote_image(self) -> String {
+        format!("ghcr.io/auxoncorp/conductor-{}", self.as_str())
+    }
+
+    /// The locally built image, tagged with the conductor version
+    pub fn local_image(self) -> String {
+        format!("conductor/{}:{}", self.as_str(), env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Builds the local image from the embedded containerfile
+    pub fn builder(self) -> ContainerBuilder {
+        Container::builder()
+            .with_image(self.local_image())
+            .with_containerfile_content(self.containerfile())
+    }
+}
+
+impl FromStr for StockImage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|i| i.as_str() == s.trim())
+            .ok_or_else(|| format!("Unknown provider image '{s}'"))
+    }
+}
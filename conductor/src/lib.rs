@@ -5,8 +5,17 @@ pub mod containers;
 pub mod deployment;
 pub mod display;
 pub(crate) mod envsub;
+pub mod events;
+pub mod fault;
+pub mod images;
+pub mod network_impairment;
 pub mod provider;
+pub mod resources;
+pub mod restart;
+pub mod scenario;
+pub mod snapshot;
 pub mod system;
+pub mod time_control;
 pub mod types;
 
 pub use component::{Component, ComponentConnector, WorldOrMachineComponent};
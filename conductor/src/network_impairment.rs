@@ -0,0 +1,268 @@
+use crate::time_control::parse_duration;
+use conductor_config::NetworkImpairmentConfig;
+use derive_more::Display;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{fmt, str::FromStr, time::Duration};
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, thiserror::Error)]
+pub enum NetworkImpairmentError {
+    #[error("Invalid network impairment {0} '{1}', expected a time like '100ms' or '1s'")]
+    Time(&'static str, String),
+    #[error("Invalid network impairment {0} '{1}', expected a percentage like '5%'")]
+    Percentage(&'static str, String),
+    #[error("Invalid network impairment rate '{0}', expected a rate like '1mbit' or '100kbps'")]
+    Rate(String),
+    #[error("The network impairment {0} setting requires a delay")]
+    RequiresDelay(&'static str),
+}
+
+/// Link impairments for a network connection, applied with `tc netem`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct NetworkImpairment {
+    pub delay: Option<Duration>,
+    pub jitter: Option<Duration>,
+    pub loss: Option<Percentage>,
+    pub duplicate: Option<Percentage>,
+    pub reorder: Option<Percentage>,
+    /// Percentage of packets with a random bit flipped
+    pub corrupt: Option<Percentage>,
+    pub rate: Option<Rate>,
+}
+
+/// A percentage with a resolution of a hundredth of a percent
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Percentage(u32);
+
+/// A `tc` rate, e.g. `1mbit`
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+pub struct Rate(String);
+
+impl NetworkImpairment {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns a copy of self with any settings present in `other` taking precedence
+    pub fn overridden_by(&self, other: &Self) -> Self {
+        Self {
+            delay: other.delay.or(self.delay),
+            jitter: other.jitter.or(self.jitter),
+            loss: other.loss.or(self.loss),
+            duplicate: other.duplicate.or(self.duplicate),
+            reorder: other.reorder.or(self.reorder),
+            corrupt: other.corrupt.or(self.corrupt),
+            rate: other.rate.clone().or_else(|| self.rate.clone()),
+        }
+    }
+
+    /// The `tc qdisc ... netem` arguments, an empty impairment is a zero-delay netem
+    /// so that a previously applied impairment can be cleared with `replace`
+    pub fn netem_args(&self) -> Vec<String> {
+        let mut args = vec!["netem".to_owned()];
+        if let Some(delay) = self.delay {
+            args.push("delay".to_owned());
+            args.push(tc_time(delay));
+            if let Some(jitter) = self.jitter {
+                args.push(tc_time(jitter));
+            }
+        }
+        if let Some(loss) = self.loss {
+            args.push("loss".to_owned());
+            args.push(loss.to_string());
+        }
+        if let Some(duplicate) = self.duplicate {
+            args.push("duplicate".to_owned());
+            args.push(duplicate.to_string());
+        }
+        if let Some(reorder) = self.reorder {
+            args.push("reorder".to_owned());
+            args.push(reorder.to_string());
+        }
+        if let Some(corrupt) = self.corrupt {
+            args.push("corrupt".to_owned());
+            args.push(corrupt.to_string());
+        }
+        if let Some(rate) = &self.rate {
+            args.push("rate".to_owned());
+            args.push(rate.to_string());
+        }
+        args
+    }
+
+    /// The `tc` command that applies this impairment to a device, replacing any existing
+    /// root qdisc
+    pub fn tc_command<D: AsRef<str>>(&self, device: D) -> Vec<String> {
+        ["tc", "qdisc", "replace", "dev", device.as_ref(), "root"]
+            .into_iter()
+            .map(str::to_owned)
+            .chain(self.netem_args())
+            .collect()
+    }
+}
+
+impl TryFrom<&NetworkImpairmentConfig> for NetworkImpairment {
+    type Error = NetworkImpairmentError;
+
+    fn try_from(value: &NetworkImpairmentConfig) -> Result<Self, Self::Error> {
+        let time = |field: &'static str, v: &Option<String>| {
+            v.as_deref()
+                .map(|s| {
+                    parse_duration(s).ok_or_else(|| NetworkImpairmentError::Time(field, s.into()))
+                })
+                .transpose()
+        };
+        let percentage = |field: &'static str, v: &Option<String>| {
+            v.as_deref()
+                .map(|s| {
+                    s.parse::<Percentage>()
+                        .map_err(|_| NetworkImpairmentError::Percentage(field, s.into()))
+                })
+                .transpose()
+        };
+        let impairment = Self {
+            delay: time("delay", &value.delay)?,
+            jitter: time("jitter", &value.jitter)?,
+            loss: percentage("loss", &value.loss)?,
+            duplicate: percentage("duplicate", &value.duplicate)?,
+            reorder: percentage("reorder", &value.reorder)?,
+            corrupt: percentage("corrupt", &value.corrupt)?,
+            rate: value.rate.as_deref().map(str::parse).transpose()?,
+        };
+        if impairment.delay.is_none() {
+            if impairment.jitter.is_some() {
+                return Err(NetworkImpairmentError::RequiresDelay("jitter"));
+            }
+            if impairment.reorder.is_some() {
+                return Err(NetworkImpairmentError::RequiresDelay("reorder"));
+            }
+        }
+        Ok(impairment)
+    }
+}
+
+impl fmt::Display for Percentage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (whole, frac) = (self.0 / 100, self.0 % 100);
+        if frac == 0 {
+            write!(f, "{whole}%")
+        } else {
+            write!(f, "{whole}.{frac:02}%")
+        }
+    }
+}
+
+impl FromStr for Percentage {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v: f64 = s.trim().trim_end_matches('%').parse().map_err(|_| ())?;
+        if !(0.0..=100.0).contains(&v) {
+            return Err(());
+        }
+        Ok(Percentage((v * 100.0).round() as u32))
+    }
+}
+
+impl FromStr for Rate {
+    type Err = NetworkImpairmentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RATE_RE: Regex = Regex::new(r"^[0-9]+(\.[0-9]+)?([kKmMgGtT]i?)?(bit|bps)$")
+                .expect("Could not construct rate Regex");
+        }
+        let s = s.trim();
+        if RATE_RE.is_match(s) {
+            Ok(Rate(s.to_owned()))
+        } else {
+            Err(NetworkImpairmentError::Rate(s.to_owned()))
+        }
+    }
+}
+
+fn tc_time(d: Duration) -> String {
+    format!("{}us", d.as_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netem_args() {
+        let cfg = NetworkImpairmentConfig {
+            delay: Some("100ms".to_owned()),
+            jitter: Some("1.5ms".to_owned()),
+            loss: Some("5%".to_owned()),
+            duplicate: Some("0.25".to_owned()),
+            reorder: None,
+            corrupt: Some("0.1%".to_owned()),
+            rate: Some("1mbit".to_owned()),
+        };
+        let imp = NetworkImpairment::try_from(&cfg).unwrap();
+        assert_eq!(
+            imp.tc_command("renode-tap0").join(" "),
+            "tc qdisc replace dev renode-tap0 root netem delay 100000us 1500us loss 5% duplicate 0.25% corrupt 0.10% rate 1mbit"
+        );
+        assert_eq!(NetworkImpairment::default().netem_args(), vec!["netem"]);
+    }
+
+    #[test]
+    fn overrides() {
+        let conn = NetworkImpairment::try_from(&NetworkImpairmentConfig {
+            delay: Some("10ms".to_owned()),
+            loss: Some("1%".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        let connector = NetworkImpairment::try_from(&NetworkImpairmentConfig {
+            loss: Some("20%".to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        let merged = conn.overridden_by(&connector);
+        assert_eq!(merged.delay, Some(Duration::from_millis(10)));
+        assert_eq!(merged.loss, "20%".parse().ok());
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let bad = |cfg: NetworkImpairmentConfig| NetworkImpairment::try_from(&cfg).unwrap_err();
+        assert_eq!(
+            bad(NetworkImpairmentConfig {
+                delay: Some("10 parsecs".to_owned()),
+                ..Default::default()
+            }),
+            NetworkImpairmentError::Time("delay", "10 parsecs".to_owned())
+        );
+        assert_eq!(
+            bad(NetworkImpairmentConfig {
+                delay: Some("99999999999999999999s".to_owned()),
+                ..Default::default()
+            }),
+            NetworkImpairmentError::Time("delay", "99999999999999999999s".to_owned())
+        );
+        assert_eq!(
+            bad(NetworkImpairmentConfig {
+                loss: Some("120%".to_owned()),
+                ..Default::default()
+            }),
+            NetworkImpairmentError::Percentage("loss", "120%".to_owned())
+        );
+        assert_eq!(
+            bad(NetworkImpairmentConfig {
+                rate: Some("fast".to_owned()),
+                ..Default::default()
+            }),
+            NetworkImpairmentError::Rate("fast".to_owned())
+        );
+        assert_eq!(
+            bad(NetworkImpairmentConfig {
+                reorder: Some("25%".to_owned()),
+                ..Default::default()
+            }),
+            NetworkImpairmentError::RequiresDelay("reorder")
+        );
+    }
+}
@@ -1,9 +1,9 @@
-// TODO - this is currently just a stub
-
 use crate::config::BaseMachine;
-use crate::types::ProviderKind;
+use crate::provider::guest_component_resource_path;
+use crate::types::{InterfaceName, ProviderKind};
 use conductor_config::ContainerMachineProvider;
 use derive_more::Display;
+use std::path::PathBuf;
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
 #[display(fmt = "{}:{}", "ProviderKind::Container", "self.base.name")]
@@ -11,3 +11,42 @@ pub struct ContainerMachine {
     pub base: BaseMachine,
     pub provider: ContainerMachineProvider,
 }
+
+impl ContainerMachine {
+    pub(crate) fn guest_bin(&self) -> Option<PathBuf> {
+        let bin_file_name = self.base.bin.as_ref()?.file_name()?;
+        Some(guest_component_resource_path(&self.base.name).join(bin_file_name))
+    }
+
+    /// The command to run, the configured command takes precedence over the bin.
+    /// None means the image's own command is used.
+    pub(crate) fn container_cmd(&self) -> Option<Vec<String>> {
+        match &self.provider.command {
+            Some(command) => Some(command.to_argv()),
+            None => self.guest_bin().map(|bin| vec![bin.display().to_string()]),
+        }
+    }
+}
+
+/// The environment variable that tells a container machine which virtual CAN interface
+/// backs its connector, e.g. `CONDUCTOR_CAN_IFACE_CAN0=conductorcan2`
+pub(crate) fn can_interface_env_kv(
+    connector_iface: &InterfaceName,
+    can_iface: &InterfaceName,
+) -> (String, String) {
+    let suffix: String = connector_iface
+        .as_str()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    (
+        format!("CONDUCTOR_CAN_IFACE_{suffix}"),
+        can_iface.to_string(),
+    )
+}
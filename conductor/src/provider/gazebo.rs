@@ -1,11 +1,16 @@
-use crate::{config::BaseWorld, provider::guest_component_resource_path, types::ProviderKind};
-use conductor_config::GazeboWorldProvider;
+use crate::{
+    config::{BaseWorld, TopicBridge},
+    provider::guest_component_resource_path,
+    types::{BridgeName, ConnectionName, InterfaceName, MachineName, ProviderKind},
+};
+use conductor_config::{Decimal, GazeboWorldProvider, TopicDirection};
 use derive_more::Display;
 use std::path::PathBuf;
 
 const COMMAND: &str = "gz";
 
-const DEFAULT_BASE_IMAGE: &str = "ghcr.io/auxoncorp/conductor-gazebo";
+/// The update rate of a world with the default 1ms physics step running in real time
+pub(crate) const REAL_TIME_UPDATE_RATE_HZ: f64 = 1000.0;
 
 // See https://gazebosim.org/api/transport/12.0/envvars.html
 // and https://gazebosim.org/api/gazebo/7/resources.html
@@ -21,6 +26,23 @@ const RES_PATH_ENV_VAR: &str = "GZ_SIM_RESOURCE_PATH";
 const SYS_PLUGIN_DIR: &str = "system_plugins";
 const RES_DIR: &str = "resources";
 const GUI_CONFIG_FILE_NAME: &str = "gui.config";
+const TOPIC_BRIDGE_FILE_NAME: &str = "topic_bridge.sh";
+
+/// The machine side of a world connector's topic bridge
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum TopicBridgeEndpoint {
+    /// A Renode UART server socket terminal
+    UartSocket(u16),
+    /// A Renode machine GPIO pin, driven through the Renode monitor
+    RenodeGpio {
+        monitor_port: u16,
+        machine: MachineName,
+        interface: InterfaceName,
+        pin: u16,
+    },
+    /// UDP datagrams broadcast on the connection's bridge
+    NetworkUdp { bridge: BridgeName, port: u16 },
+}
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
 #[display(fmt = "{}:{}", "ProviderKind::Gazebo", "self.base.name")]
@@ -30,15 +52,11 @@ pub struct GazeboWorld {
 }
 
 impl GazeboWorld {
-    pub(crate) fn base_image(&self) -> String {
-        DEFAULT_BASE_IMAGE.to_owned()
-    }
-
     pub(crate) fn container_command(&self) -> String {
         COMMAND.to_owned()
     }
 
-    pub(crate) fn container_args(&self) -> Vec<String> {
+    pub(crate) fn container_args(&self, time_scale: Option<Decimal>) -> Vec<String> {
         let mut args: Vec<String> = Vec::new();
         args.push("sim".to_owned());
         if let Some(cfg) = self.guest_gui_config_path() {
@@ -52,6 +70,11 @@ impl GazeboWorld {
             args.push("--headless-rendering".to_owned());
             args.push("-s".to_owned());
         }
+        // The real time factor is the update rate times the physics step size
+        if let Some(scale) = time_scale {
+            args.push("-z".to_owned());
+            args.push((scale.0 * REAL_TIME_UPDATE_RATE_HZ).to_string());
+        }
         if let Some(verbose) = self.provider.verbose {
             args.push("--verbose".to_owned());
             if verbose > 0 {
@@ -95,6 +118,10 @@ impl GazeboWorld {
         }
     }
 
+    pub(crate) fn guest_topic_bridge_script_path(&self) -> PathBuf {
+        guest_component_resource_path(&self.base.name).join(TOPIC_BRIDGE_FILE_NAME)
+    }
+
     // NOTE: default partition is the world's name if not provided since it's
     // already checked for uniqueness within the system
     pub(crate) fn partition_env_kv(&self) -> (&str, String) {
@@ -117,3 +144,121 @@ impl GazeboWorld {
             .map(|p| (RES_PATH_ENV_VAR, p.display().to_string()))
     }
 }
+
+// NOTE:
+// * on the guest, requires gz-transport's `gz topic` and socat
+// * each bridge runs in the background for the life of the container
+// * topics are sent to the machine as one JSON document per line, and lines
+//   from the machine are published as-is, so they must be in the protobuf text format
+// * GPIO pins are set from the `data` field of a gz.msgs.Boolean-like message
+pub(crate) fn topic_bridge_script_content(
+    bridges: &[(ConnectionName, TopicBridge, TopicBridgeEndpoint)],
+) -> String {
+    const PRE: &str = indoc::indoc! {r#"
+        #!/usr/bin/env bash
+        set -uo pipefail
+    "#};
+    let mut script = String::new();
+    script.push_str(PRE);
+    for (conn, bridge, endpoint) in bridges.iter() {
+        let topic = &bridge.topic;
+        let msg_type = &bridge.message_type;
+        script.push_str(&format!("# {conn}: {topic} ({msg_type})\n"));
+        let line = match (bridge.direction, endpoint) {
+            (TopicDirection::ToMachine, TopicBridgeEndpoint::UartSocket(port)) => format!(
+                "gz topic -e -t '{topic}' --json-output | socat -u - TCP:127.0.0.1:{port},forever,interval=1 &"
+            ),
+            (TopicDirection::FromMachine, TopicBridgeEndpoint::UartSocket(port)) => format!(
+                "socat -u TCP:127.0.0.1:{port},forever,interval=1 - | while IFS= read -r line; do gz topic -t '{topic}' -m '{msg_type}' -p \"$line\"; done &"
+            ),
+            (TopicDirection::ToMachine, TopicBridgeEndpoint::NetworkUdp { bridge, port }) => format!(
+                "gz topic -e -t '{topic}' --json-output | socat -u - UDP-DATAGRAM:255.255.255.255:{port},broadcast,so-bindtodevice={bridge} &"
+            ),
+            (TopicDirection::FromMachine, TopicBridgeEndpoint::NetworkUdp { bridge, port }) => format!(
+                "socat -u UDP-RECV:{port},so-bindtodevice={bridge} - | while IFS= read -r line; do gz topic -t '{topic}' -m '{msg_type}' -p \"$line\"; done &"
+            ),
+            (
+                TopicDirection::ToMachine,
+                TopicBridgeEndpoint::RenodeGpio {
+                    monitor_port,
+                    machine,
+                    interface,
+                    pin,
+                },
+            ) => format!(
+                "gz topic -e -t '{topic}' --json-output | while IFS= read -r line; do case \"$line\" in *true*) state=true ;; *) state=false ;; esac; printf 'mach set \"{machine}\"\\n{interface} OnGPIO {pin} %s\\n' \"$state\" | socat - TCP:127.0.0.1:{monitor_port}; done &"
+            ),
+            // NOTE: rejected by the config, Renode doesn't expose GPIO output
+            // state changes to the monitor
+            (TopicDirection::FromMachine, TopicBridgeEndpoint::RenodeGpio { .. }) => continue,
+        };
+        script.push_str(&line);
+        script.push('\n');
+    }
+    script.push_str("exit 0\n");
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn topic_bridge_script() {
+        let bridges = vec![
+            (
+                ConnectionName::new_canonicalize("imu-uart").unwrap(),
+                TopicBridge {
+                    topic: "/imu".to_owned(),
+                    message_type: "gz.msgs.IMU".to_owned(),
+                    direction: TopicDirection::ToMachine,
+                    port: None,
+                },
+                TopicBridgeEndpoint::UartSocket(4321),
+            ),
+            (
+                ConnectionName::new_canonicalize("motor").unwrap(),
+                TopicBridge {
+                    topic: "/motor/enable".to_owned(),
+                    message_type: "gz.msgs.Boolean".to_owned(),
+                    direction: TopicDirection::ToMachine,
+                    port: None,
+                },
+                TopicBridgeEndpoint::RenodeGpio {
+                    monitor_port: 1234,
+                    machine: MachineName::new_canonicalize("m0").unwrap(),
+                    interface: InterfaceName::new_canonicalize("sysbus.gpioPortA").unwrap(),
+                    pin: 3,
+                },
+            ),
+            (
+                ConnectionName::new_canonicalize("net").unwrap(),
+                TopicBridge {
+                    topic: "/cmd_vel".to_owned(),
+                    message_type: "gz.msgs.Twist".to_owned(),
+                    direction: TopicDirection::FromMachine,
+                    port: Some(9000),
+                },
+                TopicBridgeEndpoint::NetworkUdp {
+                    bridge: InterfaceName::new_system_wired_network(0),
+                    port: 9000,
+                },
+            ),
+        ];
+        assert_eq!(
+            topic_bridge_script_content(&bridges),
+            indoc::indoc! {r#"
+            #!/usr/bin/env bash
+            set -uo pipefail
+            # imu-uart: /imu (gz.msgs.IMU)
+            gz topic -e -t '/imu' --json-output | socat -u - TCP:127.0.0.1:4321,forever,interval=1 &
+            # motor: /motor/enable (gz.msgs.Boolean)
+            gz topic -e -t '/motor/enable' --json-output | while IFS= read -r line; do case "$line" in *true*) state=true ;; *) state=false ;; esac; printf 'mach set "m0"\nsysbus.gpioPortA OnGPIO 3 %s\n' "$state" | socat - TCP:127.0.0.1:1234; done &
+            # net: /cmd_vel (gz.msgs.Twist)
+            socat -u UDP-RECV:9000,so-bindtodevice=conductor0 - | while IFS= read -r line; do gz topic -t '/cmd_vel' -m 'gz.msgs.Twist' -p "$line"; done &
+            exit 0
+            "#}
+        );
+    }
+}
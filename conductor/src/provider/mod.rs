@@ -1,7 +1,15 @@
+use crate::{
+    config::Storage,
+    containers::ContainerBuilder,
+    images::StockImage,
+    types::{ContainerRuntimeName, InterfaceName},
+};
+use conductor_config::ProviderImage;
 use std::path::PathBuf;
 
 pub mod container;
 pub mod gazebo;
+pub(crate) mod monitor;
 pub mod qemu;
 pub mod renode;
 
@@ -12,3 +20,72 @@ pub const GUEST_RESOURCES_PATH: &str = "/conductor_resources";
 pub fn guest_component_resource_path<N: AsRef<str>>(component_name: N) -> PathBuf {
     PathBuf::from(GUEST_RESOURCES_PATH).join(component_name.as_ref())
 }
+
+/// The build arg an extended provider image's containerfile uses to refer to the base image
+pub const BASE_IMAGE_BUILD_ARG: &str = "BASE_IMAGE";
+
+/// Sets a provider's image on the builder, which defaults to the stock image.
+/// An extended image is built from the containerfile and context on top of the
+/// base image, and is tagged after the runtime container it's for
+pub(crate) fn set_provider_image(
+    builder: &mut ContainerBuilder,
+    container_name: &ContainerRuntimeName,
+    stock: StockImage,
+    cfg: &ProviderImage,
+) {
+    let base_image = cfg.image.clone().unwrap_or_else(|| stock.remote_image());
+    if cfg.is_extended() {
+        builder.set_image(format!("conductor/{}", container_name.to_lowercase()));
+        builder.set_build_arg(BASE_IMAGE_BUILD_ARG, base_image);
+        if let Some(containerfile) = &cfg.containerfile {
+            builder.set_containerfile(containerfile);
+        }
+        if let Some(context) = &cfg.context {
+            builder.set_context(context);
+        }
+    } else {
+        builder.set_image(base_image);
+        // Air-gapped hosts can't pull the stock image, but can build it
+        if cfg.image.is_none() {
+            builder.set_fallback_containerfile_content(stock.containerfile());
+        }
+    }
+}
+
+/// Storage images are placed in the component's resource directory on the guest
+pub fn guest_storage_image_path<N: AsRef<str>>(component_name: N, storage: &Storage) -> PathBuf {
+    // NOTE: unwrap ok, config checks the image is a file
+    let image_file_name = storage.image().file_name().unwrap();
+    guest_component_resource_path(component_name)
+        .join("storage")
+        .join(storage.name().as_str())
+        .join(image_file_name)
+}
+
+/// The TUN/TAP device, needed by containers that create TAP devices
+pub const TUN_DEVICE_PATH: &str = "/dev/net/tun";
+
+/// Shell commands that create, if needed, and bring up the virtual CAN interfaces backing
+/// CAN connections.
+///
+/// Containers run in host network mode, so these interfaces are shared by every container
+/// on the bus. Requires CAP_NET_ADMIN and the vcan kernel module on the host.
+pub(crate) fn can_bus_setup_commands<'a, I>(ifaces: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a InterfaceName>,
+{
+    ifaces
+        .into_iter()
+        .flat_map(|iface| {
+            [
+                format!("ip link show dev {iface} > /dev/null 2>&1 || ip link add dev {iface} type vcan"),
+                format!("ip link set dev {iface} up"),
+            ]
+        })
+        .collect()
+}
+
+/// Quotes a string as a single shell word
+pub(crate) fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
@@ -0,0 +1,118 @@
+// NOTE: these run in the emulator's container and require socat on the guest
+use crate::provider::sh_quote;
+use std::time::Duration;
+
+const GUEST_MONITOR_DIR: &str = "/tmp/conductor_monitor";
+
+/// Created by Renode once the monitor commands preceding it have completed
+const GUEST_RENODE_DONE_MARKER: &str = "/tmp/conductor_monitor/done";
+
+const QMP_CAPABILITIES: &str = r#"{"execute":"qmp_capabilities"}"#;
+
+/// Sends commands to a Renode monitor, the output is the monitor's
+pub(crate) fn renode_monitor_command<S: AsRef<str>>(port: u16, cmds: &[S]) -> Vec<String> {
+    vec![
+        "sh".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "printf '%s\\n' {} | socat - TCP:127.0.0.1:{port}",
+            quoted_lines(cmds)
+        ),
+    ]
+}
+
+/// Sends commands to a Renode monitor and waits for them to complete. Renode creates
+/// a marker file after the commands, the connection is held open until it exists.
+pub(crate) fn renode_monitor_sync_command<S: AsRef<str>>(
+    port: u16,
+    cmds: &[S],
+    timeout: Duration,
+) -> Vec<String> {
+    let marker_cmd = format!("python \"open('{GUEST_RENODE_DONE_MARKER}', 'w').close()\"");
+    let timeout = timeout.as_secs();
+    vec![
+        "sh".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "mkdir -p {GUEST_MONITOR_DIR} && rm -f {GUEST_RENODE_DONE_MARKER} && \
+            {{ printf '%s\\n' {} {}; timeout {timeout} sh -c 'until [ -f {GUEST_RENODE_DONE_MARKER} ]; do sleep 0.1; done'; }} \
+            | socat - TCP:127.0.0.1:{port} > /dev/null; [ -f {GUEST_RENODE_DONE_MARKER} ]",
+            quoted_lines(cmds),
+            sh_quote(&marker_cmd)
+        ),
+    ]
+}
+
+/// Sends a QMP command, e.g. `{"execute":"stop"}`, the output is the server's
+/// responses. QMP requires the capabilities negotiation before any other command.
+pub(crate) fn qmp_command(port: u16, command: &str) -> Vec<String> {
+    vec![
+        "sh".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "printf '%s\\n' {} {} | socat - TCP:127.0.0.1:{port}",
+            sh_quote(QMP_CAPABILITIES),
+            sh_quote(command)
+        ),
+    ]
+}
+
+/// Runs a QEMU human monitor command over QMP and waits for its response, the
+/// human monitor reports errors as a non-empty return string
+pub(crate) fn qmp_human_monitor_sync_command(
+    port: u16,
+    cmd: &str,
+    timeout: Duration,
+) -> Vec<String> {
+    let execute = format!(
+        "{{\"execute\":\"human-monitor-command\",\"arguments\":{{\"command-line\":\"{cmd}\"}}}}"
+    );
+    let timeout = timeout.as_secs();
+    vec![
+        "bash".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "coproc QMP {{ socat - TCP:127.0.0.1:{port}; }}; \
+            printf '%s\\n' {} {} >&\"${{QMP[1]}}\"; \
+            while IFS= read -r -t {timeout} line <&\"${{QMP[0]}}\"; do \
+            case \"$line\" in *'\"return\": \"\"'*) exit 0 ;; *'\"return\": \"'*|*'\"error\"'*) echo \"$line\"; exit 1 ;; esac; \
+            done; exit 1",
+            sh_quote(QMP_CAPABILITIES),
+            sh_quote(&execute)
+        ),
+    ]
+}
+
+fn quoted_lines<S: AsRef<str>>(lines: &[S]) -> String {
+    lines
+        .iter()
+        .map(|l| sh_quote(l.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_commands() {
+        assert_eq!(
+            renode_monitor_command(4321, &["mach set \"m0\"", "sysbus.gpioPortA OnGPIO 2 true"])
+                [2],
+            "printf '%s\\n' 'mach set \"m0\"' 'sysbus.gpioPortA OnGPIO 2 true' | socat - TCP:127.0.0.1:4321"
+        );
+        assert_eq!(
+            renode_monitor_sync_command(1234, &["Save @/tmp/s.save"], Duration::from_secs(600))
+                [2],
+            "mkdir -p /tmp/conductor_monitor && rm -f /tmp/conductor_monitor/done && \
+            { printf '%s\\n' 'Save @/tmp/s.save' 'python \"open('\\''/tmp/conductor_monitor/done'\\'', '\\''w'\\'').close()\"'; \
+            timeout 600 sh -c 'until [ -f /tmp/conductor_monitor/done ]; do sleep 0.1; done'; } \
+            | socat - TCP:127.0.0.1:1234 > /dev/null; [ -f /tmp/conductor_monitor/done ]"
+        );
+        assert_eq!(
+            qmp_command(4444, r#"{"execute":"query-status"}"#)[2],
+            r#"printf '%s\n' '{"execute":"qmp_capabilities"}' '{"execute":"query-status"}' | socat - TCP:127.0.0.1:4444"#
+        );
+    }
+}
@@ -1,13 +1,34 @@
-use crate::{config::BaseMachine, provider::guest_component_resource_path, types::ProviderKind};
-use conductor_config::QemuMachineProvider;
+use crate::{
+    config::{BaseMachine, ConnectorProperties, Storage},
+    provider::{guest_component_resource_path, guest_storage_image_path, sh_quote},
+    types::{ConnectionName, InterfaceName, ProviderKind, PublishedPort},
+};
+use conductor_config::{Decimal, QemuMachineProvider};
 use derive_more::Display;
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-// TODO - determine this based on the bin field or explicit
+// TODO - determine this based on the bin field
 // if bin is ELF, see what kind it is
-const COMMAND: &str = "qemu-system-arm";
+const DEFAULT_ARCH: &str = "arm";
 
-const DEFAULT_BASE_IMAGE: &str = "ghcr.io/auxoncorp/conductor-qemu";
+pub(crate) const KVM_DEVICE_PATH: &str = "/dev/kvm";
+
+const DEFAULT_CAN_CONTROLLER_MODEL: &str = "kvaser_pci";
+
+const DEFAULT_DRIVE_INTERFACE: &str = "virtio";
+
+/// The instruction counting shift that roughly keeps a busy guest at real time,
+/// each instruction advances the virtual clock by 2^shift ns
+const REAL_TIME_ICOUNT_SHIFT: f64 = 3.0;
+
+const MAX_ICOUNT_SHIFT: f64 = 10.0;
+
+/// Copy-on-write overlays live in the container's own filesystem, so they're thrown
+/// away with the container
+const GUEST_STORAGE_OVERLAY_PATH: &str = "/tmp/conductor_storage_overlays";
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
 #[display(fmt = "{}:{}", "ProviderKind::Qemu", "self.base.name")]
@@ -17,17 +38,23 @@ pub struct QemuMachine {
 }
 
 impl QemuMachine {
-    pub(crate) fn base_image(&self) -> String {
-        DEFAULT_BASE_IMAGE.to_owned()
+    pub(crate) fn arch(&self) -> &str {
+        self.provider.arch.as_deref().unwrap_or(DEFAULT_ARCH)
     }
 
-    // TODO - determine this based on the bin field or explicit
-    // if bin is ELF, see what kind it is
     pub(crate) fn container_command(&self) -> String {
-        COMMAND.to_owned()
+        format!("qemu-system-{}", self.arch())
+    }
+
+    /// KVM acceleration is used when the guest architecture matches the host's
+    /// and the host has KVM, unless time is scaled, which requires instruction counting
+    pub(crate) fn uses_kvm(&self, time_scale: Option<Decimal>) -> bool {
+        time_scale.is_none()
+            && self.arch() == std::env::consts::ARCH
+            && Path::new(KVM_DEVICE_PATH).exists()
     }
 
-    pub(crate) fn container_args(&self) -> Vec<String> {
+    pub(crate) fn container_args(&self, time_scale: Option<Decimal>) -> Vec<String> {
         let mut args: Vec<String> = Vec::new();
         // TODO
         // args that are local to qemu machine config
@@ -49,12 +76,147 @@ impl QemuMachine {
         if self.provider.no_graphic.unwrap_or(false) {
             args.push("-nographic".to_owned());
         }
+        if self.uses_kvm(time_scale) {
+            args.push("-accel".to_owned());
+            args.push("kvm".to_owned());
+        }
+        // Instruction counting scales the virtual clock by powers of two while the guest
+        // is busy, an idle guest sleeps in real time
+        if let Some(scale) = time_scale {
+            args.push("-icount".to_owned());
+            args.push(format!("shift={},sleep=on", icount_shift(scale)));
+        }
+        if let Some(port) = self.provider.qmp.port {
+            let on_off = |b: bool| if b { "on" } else { "off" };
+            args.push("-qmp".to_owned());
+            args.push(format!(
+                "tcp::{port},server={},wait={}",
+                on_off(self.provider.qmp.server.unwrap_or(true)),
+                on_off(self.provider.qmp.wait.unwrap_or(false))
+            ));
+        }
+        if let Some(gdb) = &self.base.gdb_server {
+            args.push("-gdb".to_owned());
+            args.push(format!("tcp::{}", gdb.port));
+            if gdb.wait {
+                args.push("-S".to_owned());
+            }
+        }
         args
     }
 
+    /// The port QEMU serves QMP on, if any
+    pub(crate) fn qmp_server_port(&self) -> Option<u16> {
+        self.provider
+            .qmp
+            .port
+            .filter(|_| self.provider.qmp.server.unwrap_or(true))
+    }
+
+    /// The configured ports along with the QMP and GDB server ports
+    pub(crate) fn published_ports(&self) -> Vec<PublishedPort> {
+        let mut ports = self.base.ports.clone();
+        ports.extend(self.qmp_server_port().map(PublishedPort::new_tcp));
+        ports.extend(
+            self.base
+                .gdb_server
+                .as_ref()
+                .map(|g| PublishedPort::new_tcp(g.port)),
+        );
+        ports
+    }
+
+    /// Each CAN connector gets a CAN bus object bridged to the host's virtual CAN
+    /// interface and a controller attached to it
+    pub(crate) fn can_bus_args(
+        &self,
+        can_interfaces: &BTreeMap<ConnectionName, InterfaceName>,
+    ) -> Vec<String> {
+        let mut args = Vec::new();
+        for c in self.base.connectors.iter() {
+            let (ConnectorProperties::Can(p), Some(can_iface)) =
+                (&c.properties, can_interfaces.get(&c.name))
+            else {
+                continue;
+            };
+            let model = p.model.as_deref().unwrap_or(DEFAULT_CAN_CONTROLLER_MODEL);
+            let bus = format!("canbus-{}", c.name);
+            args.push("-object".to_owned());
+            args.push(format!("can-bus,id={bus}"));
+            args.push("-object".to_owned());
+            args.push(format!(
+                "can-host-socketcan,id=canhost-{},if={can_iface},canbus={bus}",
+                c.name
+            ));
+            args.push("-device".to_owned());
+            args.push(format!("{model},canbus={bus}"));
+        }
+        args
+    }
+
+    /// The `-drive` args for each attached storage, along with the shell commands
+    /// that need to run beforehand to create any copy-on-write overlays
+    pub(crate) fn storage_args(&self) -> (Vec<String>, Vec<String>) {
+        let mut setup_commands = Vec::new();
+        let mut args = Vec::new();
+        for ms in self.base.storage.iter() {
+            let Storage::Virtio(vs) = &ms.storage;
+            let image = guest_storage_image_path(&self.base.name, &ms.storage);
+            let image_format = image_format(&image);
+            let (file, format) = if vs.copy_on_write && !vs.read_only {
+                let overlay =
+                    PathBuf::from(GUEST_STORAGE_OVERLAY_PATH).join(format!("{}.qcow2", vs.name));
+                setup_commands.push(format!("mkdir -p {GUEST_STORAGE_OVERLAY_PATH}"));
+                setup_commands.push(format!(
+                    "qemu-img create -q -f qcow2 -F {image_format} -b {} {}",
+                    sh_quote(&image.display().to_string()),
+                    sh_quote(&overlay.display().to_string())
+                ));
+                (overlay, "qcow2")
+            } else {
+                (image, image_format)
+            };
+            let interface = ms
+                .interface
+                .as_ref()
+                .map(|i| i.as_str())
+                .unwrap_or(DEFAULT_DRIVE_INTERFACE);
+            let mut drive = format!("file={},if={interface},format={format}", file.display());
+            if vs.read_only {
+                drive.push_str(",readonly=on");
+            }
+            args.push("-drive".to_owned());
+            args.push(drive);
+        }
+        (setup_commands, args)
+    }
+
+    /// Whether any drive is a writable qcow2 image, which `savevm` requires to store
+    /// VM snapshots in. Copy-on-write overlays are always qcow2.
+    pub(crate) fn has_writable_qcow2_drive(&self) -> bool {
+        self.base.storage.iter().any(|ms| {
+            let Storage::Virtio(vs) = &ms.storage;
+            let image = guest_storage_image_path(&self.base.name, &ms.storage);
+            !vs.read_only && (vs.copy_on_write || image_format(&image) == "qcow2")
+        })
+    }
+
     pub(crate) fn guest_bin(&self) -> PathBuf {
         // TODO - unwrap ok, already checked by config
         let bin_file_name = self.base.bin.as_ref().and_then(|b| b.file_name()).unwrap();
         guest_component_resource_path(&self.base.name).join(bin_file_name)
     }
 }
+
+fn image_format(image: &Path) -> &'static str {
+    match image.extension().and_then(|e| e.to_str()) {
+        Some("qcow2") => "qcow2",
+        _ => "raw",
+    }
+}
+
+fn icount_shift(time_scale: Decimal) -> u8 {
+    (REAL_TIME_ICOUNT_SHIFT + time_scale.0.log2())
+        .round()
+        .clamp(0.0, MAX_ICOUNT_SHIFT) as u8
+}
@@ -1,7 +1,8 @@
 use crate::{
-    config::BaseMachine,
-    provider::{guest_component_resource_path, GUEST_RESOURCES_PATH},
-    types::{BridgeName, ConnectionName, ProviderKind, TapDevice},
+    config::{BaseMachine, ConnectorProperties},
+    network_impairment::NetworkImpairment,
+    provider::{self, guest_component_resource_path, GUEST_RESOURCES_PATH},
+    types::{BridgeName, ConnectionName, InterfaceName, ProviderKind, PublishedPort, TapDevice},
 };
 use conductor_config::RenodeMachineProvider;
 use derive_more::{AsRef, Deref, Display, From};
@@ -12,14 +13,13 @@ pub use resc::RenodeScriptGen;
 
 mod platform_description;
 mod resc;
+pub(crate) mod robot;
 
 const COMMAND: &str = "renode";
 const RESC_FILE_NAME: &str = "renode_script.resc";
 const NET_SETUP_FILE_NAME: &str = "net_setup.sh";
 const NET_TEARDOWN_FILE_NAME: &str = "net_teardown.sh";
 
-const DEFAULT_BASE_IMAGE: &str = "ghcr.io/auxoncorp/conductor-renode";
-
 // NOTE: this will be expanded to deal with URIs and other types later
 //pub enum Executable { Elf(PathBuf), ... }
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, AsRef, Deref, From)]
@@ -43,10 +43,6 @@ pub struct RenodeMachine {
 }
 
 impl RenodeMachine {
-    pub(crate) fn base_image(&self) -> String {
-        DEFAULT_BASE_IMAGE.to_owned()
-    }
-
     pub(crate) fn guest_bin(&self) -> PathBuf {
         // TODO - unwrap ok, already checked by config
         let bin_file_name = self.executable.file_name().unwrap();
@@ -62,6 +58,29 @@ impl RenodeMachine {
         COMMAND.to_owned()
     }
 
+    /// The configured ports along with the ports Renode opens, i.e. the monitor,
+    /// the GDB server and the UART socket terminals
+    pub(crate) fn published_ports(&self) -> Vec<PublishedPort> {
+        let mut ports = self.base.ports.clone();
+        ports.extend(self.provider.cli.port.map(PublishedPort::new_tcp));
+        ports.extend(
+            self.base
+                .gdb_server
+                .as_ref()
+                .map(|g| PublishedPort::new_tcp(g.port)),
+        );
+        ports.extend(
+            self.base
+                .connectors
+                .iter()
+                .filter_map(|c| match &c.properties {
+                    ConnectorProperties::Uart(p) => p.port.map(PublishedPort::new_tcp),
+                    _ => None,
+                }),
+        );
+        ports
+    }
+
     pub(crate) fn container_args(&self) -> Vec<String> {
         const DEFAULT_HEADLESS_CONSOLE: bool = true;
         let mut args: Vec<String> = Vec::new();
@@ -112,8 +131,14 @@ impl RenodeMachine {
 // NOTE:
 // * on the host, this requires CAP_NET_ADMIN (docker --cap-add=NET_ADMIN)
 // * on the guest, requires things from the iproute2 and bridge-utils packages
+// * impairments are applied with tc netem on the tap, so they only affect
+//   traffic leaving the container, not guest-to-guest traffic on a Renode switch
+// * the virtual CAN interfaces the CAN hubs are bridged to are shared with other
+//   containers, so they're left in place by the teardown
 pub(crate) fn external_network_setup_script_content(
     taps_to_bridges: &BTreeMap<TapDevice, BridgeName>,
+    tap_impairments: &BTreeMap<TapDevice, NetworkImpairment>,
+    can_interfaces: &BTreeMap<ConnectionName, InterfaceName>,
 ) -> String {
     // 10, 200 is device code for TAP/TUN
     // https://git.kernel.org/pub/scm/linux/kernel/git/torvalds/linux.git/tree/Documentation/networking/tuntap.rst
@@ -121,7 +146,7 @@ pub(crate) fn external_network_setup_script_content(
         #!/usr/bin/env bash
         set -euo pipefail
         mkdir -p /dev/net
-        mknod /dev/net/tun c 10 200
+        [ -e /dev/net/tun ] || mknod /dev/net/tun c 10 200
     "#};
     let mut script = String::new();
     script.push_str(PRE);
@@ -135,6 +160,14 @@ pub(crate) fn external_network_setup_script_content(
             bridge = bridge,
             tap = tap
         ));
+        if let Some(impairment) = tap_impairments.get(tap) {
+            script.push_str(&impairment.tc_command(tap).join(" "));
+            script.push('\n');
+        }
+    }
+    for cmd in provider::can_bus_setup_commands(can_interfaces.values()) {
+        script.push_str(&cmd);
+        script.push('\n');
     }
     script.push_str("exit 0\n");
     script
@@ -1,20 +1,101 @@
 use crate::{
-    config::{Connection, ConnectorProperties},
+    config::{Connection, ConnectorProperties, Storage},
     provider::{
-        guest_component_resource_path,
+        guest_component_resource_path, guest_storage_image_path,
         renode::{PlatformDescription, RenodeMachine, TapDevice},
     },
     types::{ConnectionKind, ConnectionName, InterfaceName},
 };
+use conductor_config::{BusRole, Decimal, WirelessMedium};
 use std::{collections::BTreeMap, io, path::Path};
 
+/// Defines `bridge_bus`, which registers a proxy for a target peripheral of one
+/// machine with the I2C or SPI controller of another, at the target's address
+/// or chip select. The target peripheral must implement the bus's peripheral
+/// interface, e.g. an MCU's I2C controller in target mode.
+const BUS_BRIDGE_PYTHON: &str = r#""""
+from Antmicro.Renode.Core import EmulationManager, IPeripheral
+from Antmicro.Renode.Core.Structure import NumberRegistrationPoint
+from Antmicro.Renode.Peripherals.I2C import II2CPeripheral
+from Antmicro.Renode.Peripherals.SPI import ISPIPeripheral
+
+class I2CBusBridge(II2CPeripheral):
+    def __init__(self, target):
+        self.target = target
+    def Write(self, data):
+        self.target.Write(data)
+    def Read(self, count=1):
+        return self.target.Read(count)
+    def FinishTransmission(self):
+        self.target.FinishTransmission()
+    def Reset(self):
+        pass
+
+class SPIBusBridge(ISPIPeripheral):
+    def __init__(self, target):
+        self.target = target
+    def Transmit(self, data):
+        return self.target.Transmit(data)
+    def FinishTransmission(self):
+        self.target.FinishTransmission()
+    def Reset(self):
+        pass
+
+def find_peripheral(machine_name, name):
+    found, machine = EmulationManager.Instance.CurrentEmulation.TryGetMachineByName(machine_name)
+    if not found:
+        raise Exception("No machine '%s'" % machine_name)
+    found, peripheral = machine.TryGetByName[IPeripheral](name)
+    if not found:
+        raise Exception("No peripheral '%s' on machine '%s'" % (name, machine_name))
+    return machine, peripheral
+
+def bridge_bus(kind, controller_machine, controller, target_machine, target, address):
+    machine, controller = find_peripheral(controller_machine, controller)
+    _, target = find_peripheral(target_machine, target)
+    bridge = I2CBusBridge(target) if kind == "i2c" else SPIBusBridge(target)
+    machine.RegisterAsAChildOf(controller, bridge, NumberRegistrationPoint[int](address))
+""""#;
+
 pub struct RenodeScriptGen<'a, T: io::Write> {
     w: &'a mut T,
+    start_emulation: bool,
+    time_scale: Option<Decimal>,
+    can_interfaces: BTreeMap<ConnectionName, InterfaceName>,
 }
 
 impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
     pub fn new(writer: &'a mut T) -> Self {
-        Self { w: writer }
+        Self {
+            w: writer,
+            start_emulation: true,
+            time_scale: None,
+            can_interfaces: BTreeMap::new(),
+        }
+    }
+
+    /// Whether the script ends by starting the emulation, on by default.
+    /// Scripts loaded by a test harness leave that to the tests.
+    pub fn with_start_emulation(mut self, start_emulation: bool) -> Self {
+        self.start_emulation = start_emulation;
+        self
+    }
+
+    /// The ratio of virtual time to host time, by default virtual time runs as
+    /// fast as the host allows
+    pub fn with_time_scale(mut self, time_scale: Option<Decimal>) -> Self {
+        self.time_scale = time_scale;
+        self
+    }
+
+    /// The host's virtual CAN interfaces to bridge CAN hubs to, for buses shared
+    /// with other containers
+    pub fn with_can_interfaces(
+        mut self,
+        can_interfaces: BTreeMap<ConnectionName, InterfaceName>,
+    ) -> Self {
+        self.can_interfaces = can_interfaces;
+        self
     }
 
     // TODO
@@ -54,6 +135,16 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
             })
             .collect();
 
+        // The master time source paces every machine in the emulation
+        if let Some(scale) = self.time_scale {
+            writeln!(self.w, "emulation SetGlobalAdvanceImmediately false")?;
+            writeln!(
+                self.w,
+                "python \"from Antmicro.Renode.Core import EmulationManager; EmulationManager.Instance.CurrentEmulation.MasterTimeSource.Performance = {scale}\""
+            )?;
+            writeln!(self.w)?;
+        }
+
         for c in connections.iter() {
             self.gen_connection_create(c)?;
         }
@@ -70,6 +161,20 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
             writeln!(self.w)?;
         }
 
+        for (idx, (hub_name, can_iface)) in self.can_interfaces.iter().enumerate() {
+            writeln!(
+                self.w,
+                "emulation CreateSocketCANBridge \"socketcan{idx}\" canInterfaceName=\"{can_iface}\""
+            )?;
+            writeln!(
+                self.w,
+                "connector Connect host.socketcan{idx} \"{hub_name}\""
+            )?;
+        }
+        if !self.can_interfaces.is_empty() {
+            writeln!(self.w)?;
+        }
+
         for m in machines.iter() {
             self.gen_machine_create(m)?;
             self.gen_machine_set(m)?;
@@ -89,6 +194,9 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
                 )?;
             }
             for c in m.base.connectors.iter() {
+                if c.properties.kind().is_controller_target_bus() {
+                    continue;
+                }
                 let con_exists = connections.iter().any(|conn| conn.name() == &c.name);
                 if !con_exists {
                     tracing::warn!(
@@ -102,6 +210,20 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
                 self.gen_connector_properties(&c.name, &c.interface, &c.properties)?;
             }
 
+            for ms in m.base.storage.iter() {
+                let Storage::Virtio(vs) = &ms.storage;
+                // Non-persistent images keep writes in memory, so each run starts from
+                // the pristine image
+                let persistent = !(vs.copy_on_write || vs.read_only);
+                if let Some(iface) = &ms.interface {
+                    writeln!(
+                        self.w,
+                        "{iface} LoadImage {} {persistent}",
+                        resc_path(guest_storage_image_path(&m.base.name, &ms.storage))
+                    )?;
+                }
+            }
+
             for cmd in m.provider.resc.commands.iter() {
                 writeln!(self.w, "{cmd}")?;
             }
@@ -113,10 +235,23 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
                 .unwrap_or("sysbus LoadELF $bin");
             writeln!(self.w, "macro reset \"{reset_macro}\"")?;
             writeln!(self.w, "runMacro $reset")?;
+            if let Some(gdb) = &m.base.gdb_server {
+                writeln!(self.w, "machine StartGdbServer {}", gdb.port)?;
+            }
             writeln!(self.w)?;
         }
 
-        writeln!(self.w, "start")?;
+        self.gen_cross_machine_buses(machines)?;
+
+        // The GDB server starts the emulation once a debugger connects, which
+        // holds every machine in the container
+        if self.start_emulation
+            && !machines
+                .iter()
+                .any(|m| m.base.gdb_server.as_ref().map(|g| g.wait).unwrap_or(false))
+        {
+            writeln!(self.w, "start")?;
+        }
 
         Ok(())
     }
@@ -139,17 +274,79 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
                 "emulation CreateServerSocketTerminal {port} \"{name}\" {emit_cfg}",
                 name = c.name()
             ),
+            GuestToGuest(Connection::WirelessNetwork(c)) => {
+                let op = match c.medium {
+                    WirelessMedium::Ble => "CreateBLEMedium",
+                    WirelessMedium::Ieee802_15_4 => "CreateIEEE802_15_4Medium",
+                };
+                writeln!(self.w, "emulation {op} \"{name}\"", name = c.name)?;
+                if let Some(range) = c.range {
+                    writeln!(
+                        self.w,
+                        "{name} SetRangeWirelessFunction {range}",
+                        name = c.name
+                    )?;
+                }
+                Ok(())
+            }
             GuestToGuest(c) => {
                 let op = match c.kind() {
                     Uart => "CreateUARTHub",
                     Gpio => "CreateGPIOConnector",
                     Network => "CreateSwitch",
+                    Can => "CreateCANHub",
+                    // Buses have no emulation-level object, see gen_cross_machine_buses
+                    I2c | Spi => return Ok(()),
+                    WirelessNetwork => unreachable!("Wireless mediums are handled above"),
                 };
                 writeln!(self.w, "emulation {op} \"{name}\"", name = c.name())
             }
         }
     }
 
+    /// Targets on another machine are registered with the bus controller through a
+    /// bridge peripheral that forwards each transfer, buses within a machine are
+    /// wired up by its platform description
+    fn gen_cross_machine_buses(&mut self, machines: &[RenodeMachine]) -> io::Result<()> {
+        let connectors = || {
+            machines.iter().flat_map(|m| {
+                m.base.connectors.iter().filter_map(move |c| {
+                    c.properties
+                        .bus_role_and_address()
+                        .map(|(role, addr)| (&m.base.name, c, role, addr))
+                })
+            })
+        };
+        let mut bridged = false;
+        for (ctrl_machine, ctrl, _, _) in
+            connectors().filter(|(_, _, role, _)| *role == BusRole::Controller)
+        {
+            for (target_machine, target, _, addr) in connectors().filter(|(m, c, role, _)| {
+                *m != ctrl_machine && c.name == ctrl.name && *role == BusRole::Target
+            }) {
+                // Targets without an address are rejected by the config
+                let Some(addr) = addr else {
+                    continue;
+                };
+                if !bridged {
+                    writeln!(self.w, "python {BUS_BRIDGE_PYTHON}")?;
+                    bridged = true;
+                }
+                writeln!(
+                    self.w,
+                    "python \"bridge_bus('{kind}', '{ctrl_machine}', '{ctrl_iface}', '{target_machine}', '{target_iface}', {addr:#04x})\"",
+                    kind = ctrl.properties.kind().as_str(),
+                    ctrl_iface = ctrl.interface,
+                    target_iface = target.interface,
+                )?;
+            }
+        }
+        if bridged {
+            writeln!(self.w)?;
+        }
+        Ok(())
+    }
+
     fn gen_connector_connect(
         &mut self,
         name: &ConnectionName,
@@ -184,6 +381,22 @@ impl<'a, T: io::Write> RenodeScriptGen<'a, T> {
                     }
                 }
             }
+            ConnectorProperties::Can(_p) => {
+                // The controller model is only relevant to QEMU machines
+            }
+            ConnectorProperties::I2c(_) | ConnectorProperties::Spi(_) => {
+                // Wired up by the machine's platform description, or bridged
+                // across machines once they're all created
+            }
+            ConnectorProperties::WirelessNetwork(p) => {
+                if let Some(pos) = &p.position {
+                    writeln!(
+                        self.w,
+                        "{name} SetPosition {iface} {} {} {}",
+                        pos.x, pos.y, pos.z
+                    )?;
+                }
+            }
         }
         Ok(())
     }
@@ -224,13 +437,16 @@ mod tests {
     use super::*;
     use crate::{
         config::{
-            BaseMachine, GpioConnection, MachineConnector, NetworkConnection, UartConnection,
+            BaseMachine, CanConnection, GdbServer, GpioConnection, I2cConnection, MachineConnector,
+            MachineStorage, NetworkConnection, UartConnection, VirtioStorage,
+            WirelessNetworkConnection,
         },
-        types::MachineName,
+        types::{MachineName, StorageName},
     };
     use conductor_config::{
-        GpioConnectorProperties, NetworkConnectorProperties, RenodeMachineProvider,
-        RenodeScriptConfig, UartConnectorProperties,
+        BusRole, GpioConnectorProperties, I2cConnectorProperties, NetworkConnectorProperties,
+        Position, RenodeMachineProvider, RenodeScriptConfig, UartConnectorProperties,
+        WirelessNetworkConnectorProperties,
     };
     use indoc::indoc;
     use pretty_assertions::assert_eq;
@@ -241,6 +457,9 @@ mod tests {
         emulation CreateServerSocketTerminal 1234 "foo-uart-socket" false
         emulation CreateGPIOConnector "foo-gpio"
         emulation CreateSwitch "foo-net"
+        emulation CreateBLEMedium "foo-ble"
+        foo-ble SetRangeWirelessFunction 12.5
+        emulation CreateCANHub "foo-can"
 
         emulation CreateTap "my_tap" "tap0"
         connector Connect host.tap0 "foo-net"
@@ -254,6 +473,10 @@ mod tests {
         foo-gpio SelectSourcePin sysbus.gpioPortA 2
         connector Connect sysbus.usart2 "foo-uart-socket"
         connector Connect sysbus.ethernet "foo-net"
+        connector Connect sysbus.radio "foo-ble"
+        foo-ble SetPosition sysbus.radio 0 1.5 -2
+        connector Connect sysbus.can0 "foo-can"
+        sysbus.virtio LoadImage @/conductor_resources/my-m0/storage/m0-disk/disk.img false
         cpu PerformanceInMips 1
         macro reset "sysbus LoadHEX $bin"
         runMacro $reset
@@ -267,8 +490,10 @@ mod tests {
         foo-gpio SelectDestinationPin sysbus.gpioPortB 4
         connector Connect sysbus.eth2 "foo-net"
         foo-net EnablePromiscuousMode sysbus.eth2
+        connector Connect sysbus.radio "foo-ble"
         macro reset "sysbus LoadELF $bin"
         runMacro $reset
+        machine StartGdbServer 3333
 
         start
     "#};
@@ -286,6 +511,18 @@ mod tests {
             }),
             Connection::Network(NetworkConnection {
                 name: ConnectionName::new_canonicalize("foo-net").unwrap(),
+                impairment: Default::default(),
+            }),
+            Connection::WirelessNetwork(WirelessNetworkConnection {
+                name: ConnectionName::new_canonicalize("foo-ble").unwrap(),
+                medium: WirelessMedium::Ble,
+                range: Some(12.5.into()),
+            }),
+            Connection::Can(CanConnection {
+                name: ConnectionName::new_canonicalize("foo-can").unwrap(),
+            }),
+            Connection::I2c(I2cConnection {
+                name: ConnectionName::new_canonicalize("foo-i2c").unwrap(),
             }),
         ]
     }
@@ -327,7 +564,56 @@ mod tests {
                             interface: InterfaceName::new_canonicalize("sysbus.ethernet").unwrap(),
                             properties: ConnectorProperties::Network(Default::default()),
                         },
+                        MachineConnector {
+                            name: ConnectionName::new_canonicalize("foo-ble").unwrap(),
+                            interface: InterfaceName::new_canonicalize("sysbus.radio").unwrap(),
+                            properties: ConnectorProperties::WirelessNetwork(
+                                WirelessNetworkConnectorProperties {
+                                    position: Some(Position {
+                                        x: 0.0.into(),
+                                        y: 1.5.into(),
+                                        z: (-2.0).into(),
+                                    }),
+                                },
+                            ),
+                        },
+                        MachineConnector {
+                            name: ConnectionName::new_canonicalize("foo-can").unwrap(),
+                            interface: InterfaceName::new_canonicalize("sysbus.can0").unwrap(),
+                            properties: ConnectorProperties::Can(Default::default()),
+                        },
+                        MachineConnector {
+                            name: ConnectionName::new_canonicalize("foo-i2c").unwrap(),
+                            interface: InterfaceName::new_canonicalize("sysbus.i2c1").unwrap(),
+                            properties: ConnectorProperties::I2c(I2cConnectorProperties {
+                                role: BusRole::Controller,
+                                address: None,
+                            }),
+                        },
+                        MachineConnector {
+                            name: ConnectionName::new_canonicalize("foo-i2c").unwrap(),
+                            interface: InterfaceName::new_canonicalize("sysbus.i2c0").unwrap(),
+                            properties: ConnectorProperties::I2c(I2cConnectorProperties {
+                                role: BusRole::Target,
+                                address: Some(0x40),
+                            }),
+                        },
                     ],
+                    storage: vec![MachineStorage {
+                        storage: Storage::Virtio(VirtioStorage {
+                            name: StorageName::new_canonicalize("m0-disk").unwrap(),
+                            image: PathBuf::from("path/to/disk.img"),
+                            copy_on_write: true,
+                            read_only: false,
+                        }),
+                        interface: InterfaceName::new_canonicalize("sysbus.virtio"),
+                    }],
+                    resources: Default::default(),
+                    restart: Default::default(),
+                    devices: Vec::new(),
+                    capabilities: Vec::new(),
+                    ports: Vec::new(),
+                    gdb_server: None,
                 },
                 provider: RenodeMachineProvider {
                     cli: Default::default(),
@@ -337,6 +623,7 @@ mod tests {
                         reset_macro: Some("sysbus LoadHEX $bin".to_string()),
                         ..Default::default()
                     },
+                    base_image: Default::default(),
                 },
                 platform_descriptions: vec![PlatformDescription::ProvidedByRenode(PathBuf::from(
                     "platforms/cpus/stm32f429.repl",
@@ -370,9 +657,26 @@ mod tests {
                             interface: InterfaceName::new_canonicalize("sysbus.eth2").unwrap(),
                             properties: ConnectorProperties::Network(NetworkConnectorProperties {
                                 promiscuous_mode: Some(true),
+                                ..Default::default()
                             }),
                         },
+                        MachineConnector {
+                            name: ConnectionName::new_canonicalize("foo-ble").unwrap(),
+                            interface: InterfaceName::new_canonicalize("sysbus.radio").unwrap(),
+                            properties: ConnectorProperties::WirelessNetwork(Default::default()),
+                        },
                     ],
+                    storage: Vec::new(),
+                    resources: Default::default(),
+                    restart: Default::default(),
+                    devices: Vec::new(),
+                    capabilities: Vec::new(),
+                    ports: Vec::new(),
+                    gdb_server: Some(GdbServer {
+                        port: 3333,
+                        wait: false,
+                        init_commands: Vec::new(),
+                    }),
                 },
                 provider: RenodeMachineProvider {
                     cli: Default::default(),
@@ -380,6 +684,7 @@ mod tests {
                         platform_descriptions: vec!["platforms/cpus/stm32f411.repl".to_string()],
                         ..Default::default()
                     },
+                    base_image: Default::default(),
                 },
                 platform_descriptions: vec![PlatformDescription::ProvidedByRenode(PathBuf::from(
                     "platforms/cpus/stm32f411.repl",
@@ -405,4 +710,85 @@ mod tests {
         let out = str::from_utf8(&resc).unwrap();
         assert_eq!(out, RESC);
     }
+
+    #[test]
+    fn renode_script_without_start() {
+        let mut resc = Vec::new();
+        RenodeScriptGen::new(&mut resc)
+            .with_start_emulation(false)
+            .generate(&machines(), &connections(), &BTreeMap::new())
+            .unwrap();
+        let out = str::from_utf8(&resc).unwrap();
+        assert!(out.contains("mach create \"my-m0\"\n"));
+        assert!(!out.lines().any(|l| l == "start"));
+    }
+
+    #[test]
+    fn renode_script_gdb_server_wait() {
+        let mut resc = Vec::new();
+        let mut machines = machines();
+        machines[1].base.gdb_server.as_mut().unwrap().wait = true;
+        RenodeScriptGen::new(&mut resc)
+            .generate(&machines, &connections(), &BTreeMap::new())
+            .unwrap();
+        let out = str::from_utf8(&resc).unwrap();
+        assert!(out.contains("machine StartGdbServer 3333\n"));
+        assert!(!out.lines().any(|l| l == "start"));
+    }
+
+    #[test]
+    fn renode_script_time_scale() {
+        let mut resc = Vec::new();
+        RenodeScriptGen::new(&mut resc)
+            .with_time_scale(Some(0.5.into()))
+            .generate(&machines(), &connections(), &BTreeMap::new())
+            .unwrap();
+        let out = str::from_utf8(&resc).unwrap();
+        assert!(out.starts_with(
+            "emulation SetGlobalAdvanceImmediately false\n\
+            python \"from Antmicro.Renode.Core import EmulationManager; \
+            EmulationManager.Instance.CurrentEmulation.MasterTimeSource.Performance = 0.5\"\n\n"
+        ));
+    }
+
+    #[test]
+    fn renode_script_can_bridge() {
+        let mut resc = Vec::new();
+        let can_interfaces = BTreeMap::from_iter(std::iter::once((
+            ConnectionName::new_canonicalize("foo-can").unwrap(),
+            InterfaceName::new_canonicalize("conductorcan0").unwrap(),
+        )));
+        RenodeScriptGen::new(&mut resc)
+            .with_can_interfaces(can_interfaces)
+            .generate(&machines(), &connections(), &BTreeMap::new())
+            .unwrap();
+        let out = str::from_utf8(&resc).unwrap();
+        assert!(out.contains(
+            "emulation CreateSocketCANBridge \"socketcan0\" canInterfaceName=\"conductorcan0\"\n\
+            connector Connect host.socketcan0 \"foo-can\"\n"
+        ));
+    }
+
+    #[test]
+    fn renode_script_cross_machine_bus() {
+        let mut resc = Vec::new();
+        let mut machines = machines();
+        let target_idx = machines[0]
+            .base
+            .connectors
+            .iter()
+            .position(|c| c.interface.as_str() == "sysbus.i2c0")
+            .unwrap();
+        let target = machines[0].base.connectors.remove(target_idx);
+        machines[1].base.connectors.push(target);
+        RenodeScriptGen::new(&mut resc)
+            .generate(&machines, &connections(), &BTreeMap::new())
+            .unwrap();
+        let out = str::from_utf8(&resc).unwrap();
+        assert!(out.contains("python \"\"\"\nfrom Antmicro.Renode.Core import"));
+        assert!(out.ends_with(
+            "python \"bridge_bus('i2c', 'my-m0', 'sysbus.i2c1', 'my-m1', 'sysbus.i2c0', 0x40)\"\n\n\
+            start\n"
+        ));
+    }
 }
@@ -0,0 +1,123 @@
+use crate::provider::{
+    renode::{
+        guest_external_network_setup_script_path, guest_external_network_teardown_script_path,
+    },
+    sh_quote,
+};
+use std::path::{Path, PathBuf};
+
+const COMMAND: &str = "renode-test";
+pub(crate) const GUEST_ROBOT_PATH: &str = "/conductor_robot";
+pub(crate) const GUEST_ROBOT_RESULTS_PATH: &str = "/conductor_robot_results";
+pub(crate) const RESC_FILE_NAME: &str = "conductor_robot.resc";
+pub(crate) const RESOURCE_FILE_NAME: &str = "conductor.resource";
+
+/// Included by suites with `Resource  ${CONDUCTOR_RESOURCE}`, in place of
+/// `${RENODEKEYWORDS}`
+pub(crate) const RESOURCE_CONTENT: &str = indoc::indoc! {r#"
+    *** Settings ***
+    Documentation     Keywords for Robot suites run by conductor.
+    ...               Machines are named as in the conductor configuration, e.g. machine=m0
+    Resource          ${RENODEKEYWORDS}
+
+    *** Keywords ***
+    Load Conductor System
+        [Documentation]    Create the system's machines and connections with the
+        ...                conductor-generated script, the emulation isn't started
+        Execute Script     ${CONDUCTOR_RESC}
+"#};
+
+pub(crate) fn guest_suites_path(index: usize) -> PathBuf {
+    PathBuf::from(GUEST_ROBOT_PATH)
+        .join("suites")
+        .join(index.to_string())
+}
+
+/// The command running `renode-test` on the suites, wrapped with the network
+/// setup and teardown scripts when the container has external networks.
+/// renode-test's exit status is kept.
+pub(crate) fn container_command(guest_suites: &[PathBuf], external_networks: bool) -> Vec<String> {
+    let robot_path = Path::new(GUEST_ROBOT_PATH);
+    let mut args = vec![
+        COMMAND.to_owned(),
+        "--variable".to_owned(),
+        format!(
+            "CONDUCTOR_RESC:{}",
+            robot_path.join(RESC_FILE_NAME).display()
+        ),
+        "--variable".to_owned(),
+        format!(
+            "CONDUCTOR_RESOURCE:{}",
+            robot_path.join(RESOURCE_FILE_NAME).display()
+        ),
+        "--results-dir".to_owned(),
+        GUEST_ROBOT_RESULTS_PATH.to_owned(),
+    ];
+    args.extend(guest_suites.iter().map(|s| s.display().to_string()));
+
+    if !external_networks {
+        return args;
+    }
+    let args: Vec<String> = args.iter().map(|a| sh_quote(a)).collect();
+    vec![
+        "/bin/bash".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "{} ; {} ; rc=$? ; {} ; exit $rc",
+            guest_external_network_setup_script_path().display(),
+            args.join(" "),
+            guest_external_network_teardown_script_path().display()
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn robot_container_command() {
+        let suites = vec![guest_suites_path(0).join("uart echo.robot")];
+        assert_eq!(
+            container_command(&suites, false),
+            vec![
+                "renode-test",
+                "--variable",
+                "CONDUCTOR_RESC:/conductor_robot/conductor_robot.resc",
+                "--variable",
+                "CONDUCTOR_RESOURCE:/conductor_robot/conductor.resource",
+                "--results-dir",
+                "/conductor_robot_results",
+                "/conductor_robot/suites/0/uart echo.robot",
+            ]
+        );
+        assert_eq!(
+            container_command(&suites, true)[2],
+            "/conductor_resources/net_setup.sh ; 'renode-test' '--variable' \
+            'CONDUCTOR_RESC:/conductor_robot/conductor_robot.resc' '--variable' \
+            'CONDUCTOR_RESOURCE:/conductor_robot/conductor.resource' '--results-dir' \
+            '/conductor_robot_results' '/conductor_robot/suites/0/uart echo.robot' ; \
+            rc=$? ; /conductor_resources/net_teardown.sh ; exit $rc"
+        );
+    }
+
+    #[test]
+    fn renode_system_suite_uses_conductor_resource() {
+        let suite_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test_resources/systems/renode/uart.robot");
+        let suite = std::fs::read_to_string(&suite_path).unwrap();
+        assert!(suite
+            .lines()
+            .any(|l| l.starts_with("Resource") && l.ends_with("${CONDUCTOR_RESOURCE}")));
+        // The suite's keyword is the one the conductor resource provides
+        assert!(suite.contains("    Load Conductor System\n"));
+        assert!(RESOURCE_CONTENT.contains("\nLoad Conductor System\n"));
+
+        let guest_suite = guest_suites_path(0).join(suite_path.file_name().unwrap());
+        assert_eq!(
+            container_command(&[guest_suite], false).last().unwrap(),
+            "/conductor_robot/suites/0/uart.robot"
+        );
+    }
+}
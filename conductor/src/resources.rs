@@ -0,0 +1,240 @@
+use conductor_config::ResourceLimitsConfig;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, thiserror::Error)]
+pub enum ResourceLimitsError {
+    #[error("Invalid resource limit cpus '{0}', must be positive")]
+    Cpus(String),
+    #[error("Invalid resource limit cpuset '{0}', expected a CPU list like '0-3' or '1,3'")]
+    Cpuset(String),
+    #[error("Invalid resource limit {0} '{1}', expected a size like '512m' or '2g'")]
+    Size(&'static str, String),
+    #[error("Invalid resource limit pids-limit '{0}', must be positive")]
+    PidsLimit(i64),
+    #[error("The resource limit memory-swap requires a memory limit")]
+    SwapRequiresMemory,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to merge resource limit {_0} '{_1}', already set to '{_2}'")]
+pub struct ResourceLimitsMergeConflict(pub &'static str, pub String, pub String);
+
+/// Container resource limits, applied to the container's host config.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct ResourceLimits {
+    /// CPU quota in units of 10^-9 CPUs
+    pub nano_cpus: Option<i64>,
+    pub cpuset_cpus: Option<String>,
+    /// Memory limit in bytes
+    pub memory: Option<i64>,
+    /// Memory plus swap limit in bytes, -1 for unlimited swap
+    pub memory_swap: Option<i64>,
+    pub pids_limit: Option<i64>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Merges the limits of another component sharing the same container, each
+    /// limit set by both must agree
+    pub(crate) fn merge(&mut self, other: &Self) -> Result<(), ResourceLimitsMergeConflict> {
+        merge_limit("cpus", &mut self.nano_cpus, &other.nano_cpus)?;
+        merge_limit("cpuset", &mut self.cpuset_cpus, &other.cpuset_cpus)?;
+        merge_limit("memory", &mut self.memory, &other.memory)?;
+        merge_limit("memory-swap", &mut self.memory_swap, &other.memory_swap)?;
+        merge_limit("pids-limit", &mut self.pids_limit, &other.pids_limit)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<&ResourceLimitsConfig> for ResourceLimits {
+    type Error = ResourceLimitsError;
+
+    fn try_from(value: &ResourceLimitsConfig) -> Result<Self, Self::Error> {
+        let nano_cpus = value
+            .cpus
+            .map(|cpus| {
+                if cpus.0.is_finite() && cpus.0 > 0.0 {
+                    Ok((cpus.0 * 1e9).round() as i64)
+                } else {
+                    Err(ResourceLimitsError::Cpus(cpus.to_string()))
+                }
+            })
+            .transpose()?;
+        let cpuset_cpus = value
+            .cpuset
+            .as_deref()
+            .map(|s| {
+                lazy_static! {
+                    static ref CPUSET_RE: Regex =
+                        Regex::new(r"^[0-9]+(-[0-9]+)?(,[0-9]+(-[0-9]+)?)*$")
+                            .expect("Could not construct cpuset Regex");
+                }
+                let s = s.trim();
+                if CPUSET_RE.is_match(s) {
+                    Ok(s.to_owned())
+                } else {
+                    Err(ResourceLimitsError::Cpuset(s.to_owned()))
+                }
+            })
+            .transpose()?;
+        let memory = value
+            .memory
+            .as_deref()
+            .map(|s| parse_size(s).ok_or_else(|| ResourceLimitsError::Size("memory", s.into())))
+            .transpose()?;
+        let memory_swap = value
+            .memory_swap
+            .as_deref()
+            .map(|s| match s.trim() {
+                "-1" => Ok(-1),
+                _ => {
+                    parse_size(s).ok_or_else(|| ResourceLimitsError::Size("memory-swap", s.into()))
+                }
+            })
+            .transpose()?;
+        if memory_swap.is_some() && memory.is_none() {
+            return Err(ResourceLimitsError::SwapRequiresMemory);
+        }
+        let pids_limit = value
+            .pids_limit
+            .map(|l| {
+                if l > 0 {
+                    Ok(l)
+                } else {
+                    Err(ResourceLimitsError::PidsLimit(l))
+                }
+            })
+            .transpose()?;
+        Ok(Self {
+            nano_cpus,
+            cpuset_cpus,
+            memory,
+            memory_swap,
+            pids_limit,
+        })
+    }
+}
+
+fn merge_limit<T: Clone + PartialEq + fmt::Display>(
+    name: &'static str,
+    limit: &mut Option<T>,
+    other: &Option<T>,
+) -> Result<(), ResourceLimitsMergeConflict> {
+    match (limit.as_ref(), other) {
+        (Some(prev), Some(v)) if prev != v => Err(ResourceLimitsMergeConflict(
+            name,
+            v.to_string(),
+            prev.to_string(),
+        )),
+        (None, Some(v)) => {
+            *limit = Some(v.clone());
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parses a size in bytes with an optional binary unit suffix, e.g. '512m'
+fn parse_size(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: i64 = value.parse().ok()?;
+    let multiplier: i64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None,
+    };
+    value.checked_mul(multiplier).filter(|v| *v > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_limits() {
+        let cfg = ResourceLimitsConfig {
+            cpus: Some(1.5.into()),
+            cpuset: Some("0,2-3".to_owned()),
+            memory: Some("512m".to_owned()),
+            memory_swap: Some("1G".to_owned()),
+            pids_limit: Some(64),
+        };
+        assert_eq!(
+            ResourceLimits::try_from(&cfg).unwrap(),
+            ResourceLimits {
+                nano_cpus: Some(1_500_000_000),
+                cpuset_cpus: Some("0,2-3".to_owned()),
+                memory: Some(512 * 1024 * 1024),
+                memory_swap: Some(1024 * 1024 * 1024),
+                pids_limit: Some(64),
+            }
+        );
+        assert!(ResourceLimits::try_from(&ResourceLimitsConfig::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_limits() {
+        let bad = |cfg: ResourceLimitsConfig| ResourceLimits::try_from(&cfg).unwrap_err();
+        assert_eq!(
+            bad(ResourceLimitsConfig {
+                cpus: Some(0.0.into()),
+                ..Default::default()
+            }),
+            ResourceLimitsError::Cpus("0".to_owned())
+        );
+        assert_eq!(
+            bad(ResourceLimitsConfig {
+                cpuset: Some("0-".to_owned()),
+                ..Default::default()
+            }),
+            ResourceLimitsError::Cpuset("0-".to_owned())
+        );
+        assert_eq!(
+            bad(ResourceLimitsConfig {
+                memory: Some("lots".to_owned()),
+                ..Default::default()
+            }),
+            ResourceLimitsError::Size("memory", "lots".to_owned())
+        );
+        assert_eq!(
+            bad(ResourceLimitsConfig {
+                memory_swap: Some("-1".to_owned()),
+                ..Default::default()
+            }),
+            ResourceLimitsError::SwapRequiresMemory
+        );
+    }
+
+    #[test]
+    fn merge_limits() {
+        let mut limits = ResourceLimits {
+            memory: Some(1024),
+            ..Default::default()
+        };
+        limits
+            .merge(&ResourceLimits {
+                memory: Some(1024),
+                pids_limit: Some(32),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(limits.pids_limit, Some(32));
+        assert!(limits
+            .merge(&ResourceLimits {
+                memory: Some(2048),
+                ..Default::default()
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::time_control::parse_duration;
+use conductor_config::RestartConfig;
+pub use conductor_config::RestartPolicyKind;
+use std::time::Duration;
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, thiserror::Error)]
+pub enum RestartPolicyError {
+    #[error("Invalid restart-backoff '{0}', expected a duration like '500ms' or '2s'")]
+    Backoff(String),
+    #[error("The restart-max-retries and restart-backoff settings require a restart policy other than 'no'")]
+    RequiresRestart,
+}
+
+/// When a supervised component's container is restarted after it exits
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct RestartPolicy {
+    pub kind: RestartPolicyKind,
+    /// The number of consecutive restarts before giving up, unlimited when not set
+    pub max_retries: Option<u32>,
+    /// The delay before the first restart, doubled for each consecutive restart
+    pub backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+    /// The longest delay between restarts, however many there have been
+    pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    /// Whether a container that exited should be restarted, given how many times
+    /// it has already been restarted
+    pub fn should_restart(&self, exit_code: i64, oom_killed: bool, restarts: u32) -> bool {
+        let wants_restart = match self.kind {
+            RestartPolicyKind::No => false,
+            RestartPolicyKind::OnFailure => exit_code != 0 || oom_killed,
+            RestartPolicyKind::Always => true,
+        };
+        wants_restart && !matches!(self.max_retries, Some(max) if restarts >= max)
+    }
+
+    /// The delay before restarting a container that has already been restarted
+    /// `restarts` times
+    pub fn backoff(&self, restarts: u32) -> Duration {
+        self.backoff
+            .checked_mul(2_u32.saturating_pow(restarts))
+            .map_or(Self::MAX_BACKOFF, |b| b.min(Self::MAX_BACKOFF))
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            kind: RestartPolicyKind::No,
+            max_retries: None,
+            backoff: Self::DEFAULT_BACKOFF,
+        }
+    }
+}
+
+impl TryFrom<&RestartConfig> for RestartPolicy {
+    type Error = RestartPolicyError;
+
+    fn try_from(value: &RestartConfig) -> Result<Self, Self::Error> {
+        let kind = value.restart.unwrap_or_default();
+        if kind == RestartPolicyKind::No
+            && (value.restart_max_retries.is_some() || value.restart_backoff.is_some())
+        {
+            return Err(RestartPolicyError::RequiresRestart);
+        }
+        let backoff = value
+            .restart_backoff
+            .as_deref()
+            .map(|s| parse_duration(s).ok_or_else(|| RestartPolicyError::Backoff(s.to_owned())))
+            .transpose()?
+            .unwrap_or(Self::DEFAULT_BACKOFF);
+        Ok(Self {
+            kind,
+            max_retries: value.restart_max_retries,
+            backoff,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_policy() {
+        let cfg = RestartConfig {
+            restart: Some(RestartPolicyKind::OnFailure),
+            restart_max_retries: Some(3),
+            restart_backoff: Some("500ms".to_owned()),
+        };
+        assert_eq!(
+            RestartPolicy::try_from(&cfg).unwrap(),
+            RestartPolicy {
+                kind: RestartPolicyKind::OnFailure,
+                max_retries: Some(3),
+                backoff: Duration::from_millis(500),
+            }
+        );
+        assert_eq!(
+            RestartPolicy::try_from(&RestartConfig::default()).unwrap(),
+            RestartPolicy::default()
+        );
+        assert_eq!(
+            RestartPolicy::try_from(&RestartConfig {
+                restart: Some(RestartPolicyKind::Always),
+                restart_backoff: Some("soon".to_owned()),
+                ..Default::default()
+            }),
+            Err(RestartPolicyError::Backoff("soon".to_owned()))
+        );
+        assert_eq!(
+            RestartPolicy::try_from(&RestartConfig {
+                restart_max_retries: Some(1),
+                ..Default::default()
+            }),
+            Err(RestartPolicyError::RequiresRestart)
+        );
+    }
+
+    #[test]
+    fn restart_decisions() {
+        let on_failure = RestartPolicy {
+            kind: RestartPolicyKind::OnFailure,
+            max_retries: Some(2),
+            backoff: Duration::from_secs(1),
+        };
+        assert!(!on_failure.should_restart(0, false, 0));
+        assert!(on_failure.should_restart(0, true, 0));
+        assert!(on_failure.should_restart(1, false, 1));
+        assert!(!on_failure.should_restart(1, false, 2));
+        assert!(RestartPolicy {
+            kind: RestartPolicyKind::Always,
+            ..Default::default()
+        }
+        .should_restart(0, false, 100));
+        assert!(!RestartPolicy::default().should_restart(1, false, 0));
+
+        assert_eq!(on_failure.backoff(0), Duration::from_secs(1));
+        assert_eq!(on_failure.backoff(3), Duration::from_secs(8));
+        assert_eq!(on_failure.backoff(40), RestartPolicy::MAX_BACKOFF);
+    }
+}
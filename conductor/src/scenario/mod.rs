@@ -0,0 +1,370 @@
+use crate::{
+    fault::{Fault, FaultError},
+    time_control::parse_duration,
+    types::{ConnectionName, InterfaceName, MachineName},
+};
+use conductor_config::{ConfigReadError, ScenarioConfig, ScenarioStep};
+use regex::Regex;
+use std::{fmt, path::Path, time::Duration};
+
+pub use report::{junit_xml, tap};
+pub use run::{run, ScenarioResult, StepOutcome, StepResult};
+
+mod report;
+mod run;
+
+/// Used by steps that wait for something, when they don't set a timeout
+pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScenarioError {
+    #[error(transparent)]
+    Read(#[from] ConfigReadError),
+    #[error("Step {_0} has an invalid machine name '{_1}'")]
+    InvalidMachineName(usize, String),
+    #[error("Step {_0} has an invalid connection name '{_1}'")]
+    InvalidConnectionName(usize, String),
+    #[error("Step {_0} has an invalid interface '{_1}'")]
+    InvalidInterface(usize, String),
+    #[error("Step {_0} has an invalid duration '{_1}', expected e.g. '100ms' or '2s'")]
+    InvalidDuration(usize, String),
+    #[error("Step {_0} has an invalid pattern")]
+    InvalidPattern(usize, #[source] regex::Error),
+    #[error("Step {_0} has an empty command")]
+    EmptyCommand(usize),
+    #[error("Step {_0} neither writes to nor expects output from the UART")]
+    EmptyUartStep(usize),
+    #[error("Step {_0} has an invalid fault")]
+    Fault(usize, #[source] FaultError),
+    #[error("Fault {_0} is invalid")]
+    ScheduledFault(usize, #[source] FaultError),
+    #[error("Fault {_0} needs exactly one of an 'at' time or an 'on-log' trigger")]
+    FaultTrigger(usize),
+    #[error("Fault {_0} has an invalid trigger '{_1}'")]
+    InvalidFaultTrigger(usize, String),
+}
+
+/// A sequence of steps run against a system, see [`run`]
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub start_system: bool,
+    pub steps: Vec<Step>,
+    /// Injected while the steps run
+    pub faults: Vec<ScheduledFault>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Step {
+    WaitForLog {
+        machine: MachineName,
+        pattern: Regex,
+        timeout: Duration,
+    },
+    /// Runs in the machine's container, so `expect` is an extended regular
+    /// expression matched by grep
+    Uart {
+        machine: MachineName,
+        connection: ConnectionName,
+        write: Option<String>,
+        expect: Option<String>,
+        timeout: Duration,
+    },
+    Exec {
+        machine: MachineName,
+        command: Vec<String>,
+        expect: Option<Regex>,
+        timeout: Duration,
+    },
+    Gpio {
+        machine: MachineName,
+        interface: InterfaceName,
+        pin: u16,
+        value: bool,
+    },
+    Pause,
+    Resume,
+    Step(Duration),
+    Sleep(Duration),
+    Fault(Fault),
+}
+
+#[derive(Clone, Debug)]
+pub struct ScheduledFault {
+    pub fault: Fault,
+    pub trigger: FaultTrigger,
+}
+
+#[derive(Clone, Debug)]
+pub enum FaultTrigger {
+    /// Time since the first step started
+    At(Duration),
+    /// A line of the machine's container output matches
+    OnLog {
+        machine: MachineName,
+        pattern: Regex,
+    },
+}
+
+impl Scenario {
+    /// Reads a scenario file, the scenario is named after the file unless it sets a name
+    pub fn read<P: AsRef<Path>>(scenario_path: P) -> Result<Self, ScenarioError> {
+        let path = scenario_path.as_ref();
+        let cfg = ScenarioConfig::read(path)?;
+        let default_name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_config(cfg, default_name)
+    }
+
+    pub fn from_config(cfg: ScenarioConfig, default_name: String) -> Result<Self, ScenarioError> {
+        let steps = cfg
+            .steps
+            .into_iter()
+            .enumerate()
+            .map(|(idx, s)| Step::from_config(idx + 1, s))
+            .collect::<Result<Vec<_>, _>>()?;
+        let faults = cfg
+            .faults
+            .into_iter()
+            .enumerate()
+            .map(|(idx, f)| ScheduledFault::from_config(idx + 1, f))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Scenario {
+            name: cfg.name.unwrap_or(default_name),
+            start_system: cfg.start_system.unwrap_or(true),
+            steps,
+            faults,
+        })
+    }
+}
+
+impl ScheduledFault {
+    /// Faults are numbered from 1 in errors
+    fn from_config(
+        number: usize,
+        cfg: conductor_config::ScheduledFault,
+    ) -> Result<Self, ScenarioError> {
+        let invalid = |s: String| ScenarioError::InvalidFaultTrigger(number, s);
+        let trigger = match (cfg.at, cfg.on_log) {
+            (Some(at), None) => FaultTrigger::At(parse_duration(&at).ok_or_else(|| invalid(at))?),
+            (None, Some(on_log)) => FaultTrigger::OnLog {
+                machine: MachineName::new_canonicalize(&on_log.machine)
+                    .ok_or_else(|| invalid(on_log.machine.clone()))?,
+                pattern: Regex::new(&on_log.pattern).map_err(|_| invalid(on_log.pattern))?,
+            },
+            _ => return Err(ScenarioError::FaultTrigger(number)),
+        };
+        Ok(ScheduledFault {
+            fault: Fault::from_config(cfg.fault)
+                .map_err(|e| ScenarioError::ScheduledFault(number, e))?,
+            trigger,
+        })
+    }
+}
+
+impl Step {
+    /// Steps are numbered from 1 in errors
+    fn from_config(number: usize, step: ScenarioStep) -> Result<Self, ScenarioError> {
+        let machine = |s: String| {
+            MachineName::new_canonicalize(&s).ok_or(ScenarioError::InvalidMachineName(number, s))
+        };
+        let duration =
+            |s: String| parse_duration(&s).ok_or(ScenarioError::InvalidDuration(number, s));
+        let timeout = |s: Option<String>| s.map(duration).unwrap_or(Ok(DEFAULT_STEP_TIMEOUT));
+        let pattern =
+            |s: String| Regex::new(&s).map_err(|e| ScenarioError::InvalidPattern(number, e));
+
+        Ok(match step {
+            ScenarioStep::WaitForLog(s) => Step::WaitForLog {
+                machine: machine(s.machine)?,
+                pattern: pattern(s.pattern)?,
+                timeout: timeout(s.timeout)?,
+            },
+            ScenarioStep::Uart(s) => {
+                if s.write.is_none() && s.expect.is_none() {
+                    return Err(ScenarioError::EmptyUartStep(number));
+                }
+                Step::Uart {
+                    machine: machine(s.machine)?,
+                    connection: ConnectionName::new_canonicalize(&s.connection)
+                        .ok_or(ScenarioError::InvalidConnectionName(number, s.connection))?,
+                    write: s.write,
+                    expect: s.expect,
+                    timeout: timeout(s.timeout)?,
+                }
+            }
+            ScenarioStep::Exec(s) => {
+                if s.command.is_empty() {
+                    return Err(ScenarioError::EmptyCommand(number));
+                }
+                Step::Exec {
+                    machine: machine(s.machine)?,
+                    command: s.command,
+                    expect: s.expect.map(pattern).transpose()?,
+                    timeout: timeout(s.timeout)?,
+                }
+            }
+            ScenarioStep::Gpio(s) => Step::Gpio {
+                machine: machine(s.machine)?,
+                interface: InterfaceName::new_canonicalize(&s.interface)
+                    .ok_or(ScenarioError::InvalidInterface(number, s.interface))?,
+                pin: s.pin,
+                value: s.value,
+            },
+            ScenarioStep::Pause => Step::Pause,
+            ScenarioStep::Resume => Step::Resume,
+            ScenarioStep::Step(s) => Step::Step(duration(s.duration)?),
+            ScenarioStep::Sleep(s) => Step::Sleep(duration(s.duration)?),
+            ScenarioStep::Fault(f) => {
+                Step::Fault(Fault::from_config(f).map_err(|e| ScenarioError::Fault(number, e))?)
+            }
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::WaitForLog {
+                machine, pattern, ..
+            } => write!(f, "wait for '{pattern}' in the output of '{machine}'"),
+            Step::Uart {
+                machine,
+                connection,
+                write,
+                expect,
+                ..
+            } => {
+                write!(f, "'{machine}' UART '{connection}'")?;
+                if let Some(w) = write {
+                    write!(f, ", write {w:?}")?;
+                }
+                if let Some(e) = expect {
+                    write!(f, ", expect '{e}'")?;
+                }
+                Ok(())
+            }
+            Step::Exec {
+                machine, command, ..
+            } => write!(f, "exec {command:?} in '{machine}'"),
+            Step::Gpio {
+                machine,
+                interface,
+                pin,
+                value,
+            } => write!(f, "set '{machine}' GPIO {interface} pin {pin} to {value}"),
+            Step::Pause => f.write_str("pause"),
+            Step::Resume => f.write_str("resume"),
+            Step::Step(d) => write!(f, "step {d:?}"),
+            Step::Sleep(d) => write!(f, "sleep {d:?}"),
+            Step::Fault(fault) => write!(f, "fault: {fault}"),
+        }
+    }
+}
+
+impl fmt::Display for ScheduledFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.trigger {
+            FaultTrigger::At(at) => write!(f, "fault: {} at {at:?}", self.fault),
+            FaultTrigger::OnLog { machine, pattern } => write!(
+                f,
+                "fault: {} on '{pattern}' in the output of '{machine}'",
+                self.fault
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conductor_config::{DurationStep, ExecStep, FaultConfig, FaultKind, LogTrigger, UartStep};
+
+    #[test]
+    fn scenario_from_config() {
+        let cfg = ScenarioConfig {
+            name: None,
+            start_system: None,
+            steps: vec![
+                ScenarioStep::Exec(ExecStep {
+                    machine: "my machine".to_owned(),
+                    command: vec!["true".to_owned()],
+                    expect: None,
+                    timeout: Some("2s".to_owned()),
+                }),
+                ScenarioStep::Sleep(DurationStep {
+                    duration: "100ms".to_owned(),
+                }),
+            ],
+            faults: vec![conductor_config::ScheduledFault {
+                fault: fault_cfg(FaultKind::Freeze, "m1"),
+                at: None,
+                on_log: Some(LogTrigger {
+                    machine: "m0".to_owned(),
+                    pattern: "ready".to_owned(),
+                }),
+            }],
+        };
+        let s = Scenario::from_config(cfg, "boot".to_owned()).unwrap();
+        assert_eq!(s.name, "boot");
+        assert!(s.start_system);
+        assert_eq!(s.steps[0].to_string(), "exec [\"true\"] in 'my_machine'");
+        assert_eq!(s.steps[1].to_string(), "sleep 100ms");
+        assert_eq!(
+            s.faults[0].to_string(),
+            "fault: freeze 'm1' on 'ready' in the output of 'm0'"
+        );
+
+        let cfg = ScenarioConfig {
+            steps: vec![ScenarioStep::Uart(UartStep {
+                machine: "m".to_owned(),
+                connection: "c".to_owned(),
+                write: None,
+                expect: None,
+                timeout: None,
+            })],
+            ..Default::default()
+        };
+        assert!(matches!(
+            Scenario::from_config(cfg, "x".to_owned()),
+            Err(ScenarioError::EmptyUartStep(1))
+        ));
+
+        let cfg = ScenarioConfig {
+            steps: vec![ScenarioStep::Step(DurationStep {
+                duration: "soon".to_owned(),
+            })],
+            ..Default::default()
+        };
+        assert!(matches!(
+            Scenario::from_config(cfg, "x".to_owned()),
+            Err(ScenarioError::InvalidDuration(1, _))
+        ));
+
+        let cfg = ScenarioConfig {
+            faults: vec![conductor_config::ScheduledFault {
+                fault: fault_cfg(FaultKind::Kill, "m0"),
+                at: None,
+                on_log: None,
+            }],
+            ..Default::default()
+        };
+        assert!(matches!(
+            Scenario::from_config(cfg, "x".to_owned()),
+            Err(ScenarioError::FaultTrigger(1))
+        ));
+    }
+
+    fn fault_cfg(fault: FaultKind, target: &str) -> FaultConfig {
+        FaultConfig {
+            fault,
+            target: target.to_owned(),
+            interface: None,
+            pin: None,
+            value: None,
+            percentage: None,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::{ScenarioResult, StepOutcome};
+use std::fmt::Write;
+
+/// A JUnit XML report with a test case per scenario, each with the log of its
+/// steps as its output
+pub fn junit_xml(results: &[ScenarioResult]) -> String {
+    let tests = results.len();
+    let failures = results.iter().filter(|r| !r.passed()).count();
+    let time: f64 = results.iter().map(|r| r.duration.as_secs_f64()).sum();
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        xml,
+        r#"<testsuites name="conductor" tests="{tests}" failures="{failures}" time="{time:.3}">"#
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        r#"  <testsuite name="conductor" tests="{tests}" failures="{failures}" time="{time:.3}">"#
+    )
+    .unwrap();
+    for r in results.iter() {
+        writeln!(
+            xml,
+            r#"    <testcase name="{}" classname="conductor" time="{:.3}">"#,
+            xml_escape(&r.name),
+            r.duration.as_secs_f64()
+        )
+        .unwrap();
+        if let Some((step, reason)) = r.failure() {
+            writeln!(
+                xml,
+                r#"      <failure message="{}">{}</failure>"#,
+                xml_escape(&format!("{}: {reason}", step.description)),
+                xml_escape(reason)
+            )
+            .unwrap();
+        }
+        writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            xml_escape(&step_log(r))
+        )
+        .unwrap();
+        writeln!(xml, "    </testcase>").unwrap();
+    }
+    writeln!(xml, "  </testsuite>").unwrap();
+    writeln!(xml, "</testsuites>").unwrap();
+    xml
+}
+
+/// A TAP version 13 report with a test point per scenario, failures include
+/// the failed step in a YAML block
+pub fn tap(results: &[ScenarioResult]) -> String {
+    let mut tap = String::new();
+    writeln!(tap, "TAP version 13").unwrap();
+    writeln!(tap, "1..{}", results.len()).unwrap();
+    for (idx, r) in results.iter().enumerate() {
+        let number = idx + 1;
+        match r.failure() {
+            None => writeln!(tap, "ok {number} - {}", r.name).unwrap(),
+            Some((step, reason)) => {
+                writeln!(tap, "not ok {number} - {}", r.name).unwrap();
+                writeln!(tap, "  ---").unwrap();
+                writeln!(tap, "  step: {:?}", step.description).unwrap();
+                writeln!(tap, "  message: {:?}", reason).unwrap();
+                writeln!(tap, "  duration_ms: {}", r.duration.as_millis()).unwrap();
+                writeln!(tap, "  ...").unwrap();
+            }
+        }
+    }
+    tap
+}
+
+fn step_log(r: &ScenarioResult) -> String {
+    let mut log = String::new();
+    for s in r.steps.iter() {
+        let outcome = match &s.outcome {
+            StepOutcome::Passed => "passed".to_owned(),
+            StepOutcome::Failed(reason) => format!("failed: {reason}"),
+            StepOutcome::Skipped => "skipped".to_owned(),
+        };
+        writeln!(
+            log,
+            "[{:.3}s] {}: {outcome}",
+            s.duration.as_secs_f64(),
+            s.description
+        )
+        .unwrap();
+    }
+    log
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::StepResult;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn results() -> Vec<ScenarioResult> {
+        vec![
+            ScenarioResult {
+                name: "boot".to_owned(),
+                duration: Duration::from_millis(1500),
+                steps: vec![StepResult {
+                    description: "sleep 1s".to_owned(),
+                    duration: Duration::from_millis(1000),
+                    outcome: StepOutcome::Passed,
+                }],
+            },
+            ScenarioResult {
+                name: "echo <uart>".to_owned(),
+                duration: Duration::from_millis(250),
+                steps: vec![
+                    StepResult {
+                        description: "exec [\"false\"] in 'm0'".to_owned(),
+                        duration: Duration::from_millis(250),
+                        outcome: StepOutcome::Failed("exited with status 1".to_owned()),
+                    },
+                    StepResult {
+                        description: "pause".to_owned(),
+                        duration: Duration::ZERO,
+                        outcome: StepOutcome::Skipped,
+                    },
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn junit_report() {
+        assert_eq!(
+            junit_xml(&results()),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="conductor" tests="2" failures="1" time="1.750">
+  <testsuite name="conductor" tests="2" failures="1" time="1.750">
+    <testcase name="boot" classname="conductor" time="1.500">
+      <system-out>[1.000s] sleep 1s: passed
+</system-out>
+    </testcase>
+    <testcase name="echo &lt;uart&gt;" classname="conductor" time="0.250">
+      <failure message="exec [&quot;false&quot;] in &apos;m0&apos;: exited with status 1">exited with status 1</failure>
+      <system-out>[0.250s] exec [&quot;false&quot;] in &apos;m0&apos;: failed: exited with status 1
+[0.000s] pause: skipped
+</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn tap_report() {
+        assert_eq!(
+            tap(&results()),
+            r#"TAP version 13
+1..2
+ok 1 - boot
+not ok 2 - echo <uart>
+  ---
+  step: "exec [\"false\"] in 'm0'"
+  message: "exited with status 1"
+  duration_ms: 250
+  ...
+"#
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use super::{FaultTrigger, Scenario, ScheduledFault, Step};
+use crate::System;
+use anyhow::{anyhow, bail, Result};
+use futures_util::future::{join, join_all};
+use std::{
+    future::Future,
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::sync::watch;
+
+#[derive(Clone, Debug)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub duration: Duration,
+    pub steps: Vec<StepResult>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepResult {
+    pub description: String,
+    pub duration: Duration,
+    pub outcome: StepOutcome,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum StepOutcome {
+    Passed,
+    Failed(String),
+    /// An earlier step failed, or a scheduled fault didn't trigger before the
+    /// last step completed
+    Skipped,
+}
+
+impl ScenarioResult {
+    pub fn passed(&self) -> bool {
+        self.failure().is_none()
+    }
+
+    /// The first failed step, and why it failed
+    pub fn failure(&self) -> Option<(&StepResult, &str)> {
+        self.steps.iter().find_map(|s| match &s.outcome {
+            StepOutcome::Failed(reason) => Some((s, reason.as_str())),
+            _ => None,
+        })
+    }
+}
+
+/// Run a scenario against the system.
+///
+/// When the scenario starts the system, it's started before the first step and
+/// stopped after the last one, even if a step failed. The steps after a failed
+/// step are skipped.
+///
+/// Scheduled faults are injected while the steps run, their results follow the
+/// steps'. Faults that haven't triggered by the time the last step completes are
+/// skipped.
+pub async fn run(system: &mut System, scenario: &Scenario) -> ScenarioResult {
+    let started = Instant::now();
+    // Only output printed during the scenario, including the system booting, is
+    // waited for
+    let output_since = SystemTime::now();
+    let mut steps = Vec::new();
+
+    if scenario.start_system {
+        let start = run_step("start system".to_owned(), false, async {
+            system.build().await?;
+            system.start().await
+        })
+        .await;
+        steps.push(start);
+    }
+
+    let skip = steps.iter().any(|s| s.outcome != StepOutcome::Passed);
+    let (step_results, fault_results) =
+        run_steps_and_faults(system, scenario, skip, output_since).await;
+    steps.extend(step_results);
+    steps.extend(fault_results);
+
+    if scenario.start_system {
+        steps.push(run_step("stop system".to_owned(), false, system.stop()).await);
+    }
+
+    ScenarioResult {
+        name: scenario.name.clone(),
+        duration: started.elapsed(),
+        steps,
+    }
+}
+
+async fn run_steps_and_faults(
+    system: &System,
+    scenario: &Scenario,
+    skip: bool,
+    output_since: SystemTime,
+) -> (Vec<StepResult>, Vec<StepResult>) {
+    let started = Instant::now();
+    let (done_tx, done_rx) = watch::channel(false);
+
+    let steps = async {
+        let mut results: Vec<StepResult> = Vec::new();
+        // A step waits for output printed since the previous step began, so that
+        // lines printed while e.g. the system was started still count
+        let mut since = output_since;
+        for step in scenario.steps.iter() {
+            let skip = skip || results.iter().any(|s| s.outcome != StepOutcome::Passed);
+            let step_started = SystemTime::now();
+            let step_run = run_scenario_step(system, step, since);
+            results.push(run_step(step.to_string(), skip, step_run).await);
+            since = step_started;
+        }
+        let _ = done_tx.send(true);
+        results
+    };
+
+    let faults = join_all(scenario.faults.iter().map(|f| {
+        let mut done = done_rx.clone();
+        async move {
+            let fault_started = Instant::now();
+            let outcome = if skip {
+                StepOutcome::Skipped
+            } else {
+                tokio::select! {
+                    res = run_scheduled_fault(system, f, started, output_since) => match res {
+                        Ok(()) => StepOutcome::Passed,
+                        Err(e) => StepOutcome::Failed(format!("{e:#}")),
+                    },
+                    _ = done.changed() => StepOutcome::Skipped,
+                }
+            };
+            StepResult {
+                description: f.to_string(),
+                duration: fault_started.elapsed(),
+                outcome,
+            }
+        }
+    }));
+
+    join(steps, faults).await
+}
+
+async fn run_scheduled_fault(
+    system: &System,
+    f: &ScheduledFault,
+    started: Instant,
+    output_since: SystemTime,
+) -> Result<()> {
+    match &f.trigger {
+        FaultTrigger::At(at) => tokio::time::sleep_until((started + *at).into()).await,
+        FaultTrigger::OnLog { machine, pattern } => {
+            let container = system.component_container(&machine.clone().into())?;
+            container.wait_for_output(pattern, output_since).await?;
+        }
+    }
+    system.inject_fault(&f.fault).await
+}
+
+async fn run_step<F>(description: String, skip: bool, step: F) -> StepResult
+where
+    F: Future<Output = Result<()>>,
+{
+    let started = Instant::now();
+    let outcome = if skip {
+        StepOutcome::Skipped
+    } else {
+        match step.await {
+            Ok(()) => StepOutcome::Passed,
+            Err(e) => StepOutcome::Failed(format!("{e:#}")),
+        }
+    };
+    StepResult {
+        description,
+        duration: started.elapsed(),
+        outcome,
+    }
+}
+
+async fn run_scenario_step(system: &System, step: &Step, output_since: SystemTime) -> Result<()> {
+    match step {
+        Step::WaitForLog {
+            machine,
+            pattern,
+            timeout,
+        } => {
+            let container = system.get_container_by_component_name(machine)?;
+            tokio::time::timeout(*timeout, container.wait_for_output(pattern, output_since))
+                .await
+                .map_err(|_| anyhow!("no output matched '{pattern}' within {timeout:?}"))??;
+        }
+        Step::Uart {
+            machine,
+            connection,
+            write,
+            expect,
+            timeout,
+        } => {
+            system
+                .uart_exchange(
+                    machine,
+                    connection,
+                    write.as_deref(),
+                    expect.as_deref(),
+                    *timeout,
+                )
+                .await?;
+        }
+        Step::Exec {
+            machine,
+            command,
+            expect,
+            timeout,
+        } => {
+            let container = system.get_container_by_component_name(machine)?;
+            let output = tokio::time::timeout(*timeout, container.exec(command))
+                .await
+                .map_err(|_| anyhow!("command didn't complete within {timeout:?}"))??;
+            if let Some(expect) = expect {
+                if !expect.is_match(&output) {
+                    bail!("command output didn't match '{expect}': {output}");
+                }
+            }
+        }
+        Step::Gpio {
+            machine,
+            interface,
+            pin,
+            value,
+        } => {
+            let cmd = format!("{interface} OnGPIO {pin} {value}");
+            system.renode_monitor(machine, &[cmd]).await?;
+        }
+        Step::Pause => system.pause().await?,
+        Step::Resume => system.resume().await?,
+        Step::Step(duration) => system.step(*duration).await?,
+        Step::Sleep(duration) => tokio::time::sleep(*duration).await,
+        Step::Fault(fault) => system.inject_fault(fault).await?,
+    }
+    Ok(())
+}
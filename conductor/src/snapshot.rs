@@ -0,0 +1,164 @@
+use crate::Deployment;
+use data_encoding::HEXLOWER;
+use derive_more::{AsRef, Display};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+const SNAPSHOTS_DIR: &str = "snapshots";
+const METADATA_FILE_NAME: &str = "metadata.toml";
+
+/// Where snapshot files are written to and read from within a container
+pub(crate) const GUEST_SNAPSHOT_DIR: &str = "/tmp/conductor_snapshot";
+
+/// The image repository container machines are committed to
+pub(crate) const CONTAINER_SNAPSHOT_REPO: &str = "conductor-snapshot";
+
+/// How long a snapshot command may take to complete
+pub(crate) const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A snapshot name, also used as a file name, so restricted to alphanumerics,
+/// '-', '_' and '.'
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, AsRef, Display)]
+pub struct SnapshotName(String);
+
+impl FromStr for SnapshotName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = !s.is_empty()
+            && !s.starts_with('.')
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if valid {
+            Ok(Self(s.to_owned()))
+        } else {
+            Err(format!(
+                "Invalid snapshot name '{s}', only alphanumerics, '-', '_' and '.' are allowed"
+            ))
+        }
+    }
+}
+
+/// Describes a saved snapshot, stored alongside its files
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotMetadata {
+    pub name: String,
+    pub system: String,
+    /// Digest of the deployment the snapshot was taken from
+    pub deployment_digest: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    #[serde(default, rename = "container")]
+    pub containers: Vec<SnapshotContainer>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotContainer {
+    pub name: String,
+    pub kind: SnapshotKind,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapshotKind {
+    /// A Renode emulation save file, stored in the snapshot directory
+    RenodeSave,
+    /// A QEMU VM snapshot, stored in the machine's qcow2 storage
+    QemuVm,
+    /// A container machine's filesystem, committed to a local image
+    ContainerCommit,
+}
+
+impl SnapshotMetadata {
+    pub fn read(snapshot_dir: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(snapshot_dir.join(METADATA_FILE_NAME))?;
+        toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn write(&self, snapshot_dir: &Path) -> io::Result<()> {
+        let content =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(snapshot_dir.join(METADATA_FILE_NAME), content)
+    }
+}
+
+pub(crate) fn snapshot_dir(state_dir: &Path, name: &SnapshotName) -> PathBuf {
+    state_dir.join(SNAPSHOTS_DIR).join(name.as_ref())
+}
+
+/// Where a snapshot is saved to before it replaces the snapshot of the same name.
+/// Snapshot names can't start with a '.', so it can't clash with another snapshot.
+pub(crate) fn partial_snapshot_dir(state_dir: &Path, name: &SnapshotName) -> PathBuf {
+    state_dir
+        .join(SNAPSHOTS_DIR)
+        .join(format!(".{}.partial", name.as_ref()))
+}
+
+/// Snapshots are only valid for the deployment they were taken from
+pub(crate) fn deployment_digest(deployment: &Deployment) -> String {
+    HEXLOWER.encode(digest(&SHA256, format!("{deployment:?}").as_bytes()).as_ref())
+}
+
+/// The guest path of a Renode container's save file
+pub(crate) fn guest_renode_save_path(container_name: &str) -> String {
+    format!("{GUEST_SNAPSHOT_DIR}/{container_name}.save")
+}
+
+/// The tag of a QEMU VM snapshot
+pub(crate) fn qemu_vm_tag(name: &SnapshotName) -> String {
+    format!("conductor-{name}")
+}
+
+/// The image tag a container machine is committed to, within [`CONTAINER_SNAPSHOT_REPO`]
+pub(crate) fn container_snapshot_tag(container_name: &str, name: &SnapshotName) -> String {
+    format!("{container_name}.{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_names() {
+        assert!("boot-done_1.0".parse::<SnapshotName>().is_ok());
+        assert!("".parse::<SnapshotName>().is_err());
+        assert!("..".parse::<SnapshotName>().is_err());
+        assert!("a/b".parse::<SnapshotName>().is_err());
+        assert!("a b".parse::<SnapshotName>().is_err());
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = SnapshotMetadata {
+            name: "booted".to_owned(),
+            system: "my-system".to_owned(),
+            deployment_digest: "abcd".to_owned(),
+            created: 1234,
+            containers: vec![
+                SnapshotContainer {
+                    name: "my-system_m0".to_owned(),
+                    kind: SnapshotKind::RenodeSave,
+                },
+                SnapshotContainer {
+                    name: "my-system_m1".to_owned(),
+                    kind: SnapshotKind::QemuVm,
+                },
+                SnapshotContainer {
+                    name: "my-system_m2".to_owned(),
+                    kind: SnapshotKind::ContainerCommit,
+                },
+            ],
+        };
+        metadata.write(dir.path()).unwrap();
+        assert_eq!(SnapshotMetadata::read(dir.path()).unwrap(), metadata);
+    }
+}
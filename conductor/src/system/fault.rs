@@ -0,0 +1,159 @@
+use super::System;
+use crate::{
+    config::ConnectorProperties,
+    containers::Container,
+    deployment,
+    events::{Event, EventKind},
+    fault::Fault,
+    network_impairment::NetworkImpairment,
+    types::{ComponentName, ConnectionName},
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+
+impl System {
+    /// Inject a fault into the running system, recording it in the event log.
+    ///
+    /// Container faults apply to the component's whole container, i.e. to every
+    /// Renode machine sharing it.
+    pub async fn inject_fault(&self, fault: &Fault) -> Result<()> {
+        match fault {
+            Fault::Kill(c) => self.component_container(c)?.kill().await?,
+            Fault::Restart(c) => self.component_container(c)?.restart().await?,
+            Fault::Freeze(c) => self.component_container(c)?.pause().await?,
+            Fault::Thaw(c) => self.component_container(c)?.unpause().await?,
+            Fault::Disconnect(c) => self.set_network_links_up(c, false).await?,
+            Fault::Reconnect(c) => self.set_network_links_up(c, true).await?,
+            Fault::CutUart(c) => self.set_uart_connected(c, false).await?,
+            Fault::RestoreUart(c) => self.set_uart_connected(c, true).await?,
+            Fault::Gpio {
+                machine,
+                interface,
+                pin,
+                value,
+            } => {
+                let cmd = format!("{interface} OnGPIO {pin} {value}");
+                self.renode_monitor(machine, &[cmd]).await?;
+            }
+            Fault::Corrupt {
+                connection,
+                percentage,
+            } => {
+                let impairment = NetworkImpairment {
+                    corrupt: Some(*percentage),
+                    ..Default::default()
+                };
+                self.impair_connection(connection, &impairment).await?;
+            }
+        }
+
+        self.event_log().append(&Event::now(
+            EventKind::Fault,
+            Some(fault.target().to_owned()),
+            fault.to_string(),
+        ))?;
+        Ok(())
+    }
+
+    /// The container a world or machine runs in
+    pub fn component_container(&self, component: &ComponentName) -> Result<&Container> {
+        let deployment = self.deployment()?;
+        let is = |name: &str| name == component.as_str();
+        let container_name = deployment
+            .gazebo_containers
+            .iter()
+            .find(|c| c.components.iter().any(|w| is(&w.base.name)))
+            .map(|c| &c.name)
+            .or_else(|| {
+                deployment
+                    .renode_containers
+                    .iter()
+                    .find(|c| c.components.iter().any(|m| is(&m.base.name)))
+                    .map(|c| &c.name)
+            })
+            .or_else(|| {
+                deployment
+                    .qemu_containers
+                    .iter()
+                    .find(|c| c.components.iter().any(|m| is(&m.base.name)))
+                    .map(|c| &c.name)
+            })
+            .or_else(|| {
+                deployment
+                    .container_containers
+                    .iter()
+                    .find(|c| c.components.iter().any(|m| is(&m.base.name)))
+                    .map(|c| &c.name)
+            })
+            .ok_or_else(|| anyhow!("component '{component}' not found"))?;
+        self.find_container(container_name)
+    }
+
+    /// Brings the host bridge backing a network connection, and the Renode tap devices
+    /// attached to it, up or down. Components other than Renode machines use the host's
+    /// network, so taking the bridge down is what cuts their links.
+    async fn set_network_links_up(&self, connection: &ConnectionName, up: bool) -> Result<()> {
+        let is_network_connection = self
+            .config
+            .connections
+            .iter()
+            .any(|c| c.name() == connection && c.is_network());
+        if !is_network_connection {
+            bail!("network connection '{connection}' not found");
+        }
+
+        // NOTE: unwrap ok, it's a network connection of the graph
+        let bridge = deployment::wired_network_bridge(&self.graph()?, connection).unwrap();
+        self.run_host_network_script("link-setup", link_set_command(&bridge, up).join(" "))
+            .await
+            .with_context(|| format!("setting the link of bridge '{bridge}' failed"))?;
+
+        let deployment = self.deployment()?;
+        for c in deployment.renode_containers.iter() {
+            let Some(tap) = c
+                .components
+                .iter()
+                .find_map(|m| m.tap_devices.get(connection))
+            else {
+                continue;
+            };
+            self.find_container(&c.name)?
+                .exec(&link_set_command(tap, up))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Connects or disconnects the Renode machines on a UART connection
+    async fn set_uart_connected(&self, connection: &ConnectionName, connected: bool) -> Result<()> {
+        let op = if connected { "Connect" } else { "Disconnect" };
+        let deployment = self.deployment()?;
+        let mut applied = false;
+        for m in deployment
+            .renode_containers
+            .iter()
+            .flat_map(|c| c.components.iter())
+        {
+            for c in m.base.connectors.iter().filter(|c| &c.name == connection) {
+                if !matches!(c.properties, ConnectorProperties::Uart(_)) {
+                    bail!("connection '{connection}' isn't a UART connection");
+                }
+                let cmd = format!("connector {op} {} \"{connection}\"", c.interface);
+                self.renode_monitor(&m.base.name, &[cmd]).await?;
+                applied = true;
+            }
+        }
+
+        if !applied {
+            bail!("UART connection '{connection}' has no Renode machines");
+        }
+        Ok(())
+    }
+}
+
+fn link_set_command<D: AsRef<str>>(device: D, up: bool) -> Vec<String> {
+    let state = if up { "up" } else { "down" };
+    ["ip", "link", "set", "dev", device.as_ref(), state]
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
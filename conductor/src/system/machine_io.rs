@@ -0,0 +1,162 @@
+use super::System;
+use crate::{
+    config::ConnectorProperties,
+    containers::Container,
+    provider::{
+        monitor::{qmp_command, renode_monitor_command},
+        sh_quote,
+    },
+    types::{ConnectionName, MachineName},
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use std::time::Duration;
+
+// NOTE: like time control, these run socat in the machine's container
+impl System {
+    /// Write to a Renode machine's UART and/or wait for a line of its output to match
+    /// an extended regular expression, in the same session so a quick response isn't
+    /// missed.
+    ///
+    /// The UART's connector must have a socket terminal `port`.
+    pub async fn uart_exchange(
+        &self,
+        machine: &MachineName,
+        connection: &ConnectionName,
+        write: Option<&str>,
+        expect: Option<&str>,
+        timeout: Duration,
+    ) -> Result<()> {
+        let (container, port) = self.uart_socket(machine, connection)?;
+        let res = container
+            .exec(&uart_command(port, write, expect, timeout))
+            .await;
+        match expect {
+            Some(expect) => {
+                res.with_context(|| {
+                    format!("no UART output matched '{expect}' within {timeout:?}")
+                })?;
+            }
+            None => {
+                res?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run Renode monitor commands in the context of a machine, returning the
+    /// monitor's output. Requires the machine's monitor port.
+    pub async fn renode_monitor(&self, machine: &MachineName, cmds: &[String]) -> Result<String> {
+        let deployment = self.deployment()?;
+        let c = deployment
+            .renode_containers
+            .iter()
+            .find(|c| c.components.iter().any(|m| &m.base.name == machine))
+            .ok_or_else(|| anyhow!("Renode machine '{machine}' not found"))?;
+        let port = c
+            .machine()
+            .provider
+            .cli
+            .port
+            .ok_or_else(|| anyhow!("machine '{machine}' requires a Renode monitor port"))?;
+        let mut lines = vec![format!("mach set \"{machine}\"")];
+        lines.extend(cmds.iter().cloned());
+        self.find_container(&c.name)?
+            .exec(&renode_monitor_command(port, &lines))
+            .await
+    }
+
+    /// Send a QMP command, e.g. `{"execute":"query-status"}`, to a QEMU machine,
+    /// returning the server's responses. Requires the machine's QMP server.
+    pub async fn qmp(&self, machine: &MachineName, command: &str) -> Result<String> {
+        let deployment = self.deployment()?;
+        let c = deployment
+            .qemu_containers
+            .iter()
+            .find(|c| &c.machine().base.name == machine)
+            .ok_or_else(|| anyhow!("QEMU machine '{machine}' not found"))?;
+        let port = c
+            .machine()
+            .qmp_server_port()
+            .ok_or_else(|| anyhow!("machine '{machine}' requires a QMP server port"))?;
+        self.find_container(&c.name)?
+            .exec(&qmp_command(port, command))
+            .await
+    }
+
+    fn uart_socket(
+        &self,
+        machine: &MachineName,
+        connection: &ConnectionName,
+    ) -> Result<(&Container, u16)> {
+        let deployment = self.deployment()?;
+        let (c, m) = deployment
+            .renode_containers
+            .iter()
+            .find_map(|c| {
+                c.components
+                    .iter()
+                    .find(|m| &m.base.name == machine)
+                    .map(|m| (c, m))
+            })
+            .ok_or_else(|| anyhow!("Renode machine '{machine}' not found"))?;
+        let connector = m
+            .base
+            .connectors
+            .iter()
+            .find(|c| &c.name == connection)
+            .ok_or_else(|| anyhow!("machine '{machine}' isn't connected to '{connection}'"))?;
+        let port = match &connector.properties {
+            ConnectorProperties::Uart(p) => p.port.ok_or_else(|| {
+                anyhow!("machine '{machine}' has no socket terminal port for UART '{connection}'")
+            })?,
+            _ => bail!("connection '{connection}' isn't a UART connection"),
+        };
+        Ok((self.find_container(&c.name)?, port))
+    }
+}
+
+fn uart_command(
+    port: u16,
+    write: Option<&str>,
+    expect: Option<&str>,
+    timeout: Duration,
+) -> Vec<String> {
+    let mut script = format!("coproc UART {{ socat - TCP:127.0.0.1:{port}; }}; ");
+    if let Some(write) = write {
+        script.push_str(&format!(
+            "printf '%s' {} >&\"${{UART[1]}}\"; ",
+            sh_quote(write)
+        ));
+    }
+    match expect {
+        Some(expect) => script.push_str(&format!(
+            "timeout {}s grep -m1 -E {} <&\"${{UART[0]}}\"; rc=$?; ",
+            timeout.as_secs_f64(),
+            sh_quote(expect)
+        )),
+        None => script.push_str("sleep 0.1; rc=0; "),
+    }
+    script.push_str("kill \"$UART_PID\" 2> /dev/null; exit $rc");
+    vec!["bash".to_owned(), "-c".to_owned(), script]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_io_commands() {
+        assert_eq!(
+            uart_command(
+                1234,
+                Some("it's\n"),
+                Some("^ok$"),
+                Duration::from_millis(1500)
+            )[2],
+            "coproc UART { socat - TCP:127.0.0.1:1234; }; \
+            printf '%s' 'it'\\''s\n' >&\"${UART[1]}\"; \
+            timeout 1.5s grep -m1 -E '^ok$' <&\"${UART[0]}\"; rc=$?; \
+            kill \"$UART_PID\" 2> /dev/null; exit $rc"
+        );
+    }
+}
@@ -2,15 +2,37 @@ use crate::{
     component::Component,
     config::{ConnectorProperties, MachineConnector},
     containers::{Container, ContainerBuilder, Network},
+    deployment,
+    events::{Event, EventKind, EventLog},
+    images::StockImage,
+    network_impairment::NetworkImpairment,
     provider::{
-        container::ContainerMachine, gazebo::GazeboWorld, qemu::QemuMachine, renode::RenodeMachine,
+        self, container::ContainerMachine, gazebo::GazeboWorld, monitor, qemu::QemuMachine,
+        renode::RenodeMachine,
     },
-    types::{ConnectionName, ContainerRuntimeName},
+    snapshot::{
+        self, SnapshotContainer, SnapshotKind, SnapshotMetadata, SnapshotName, GUEST_SNAPSHOT_DIR,
+        SNAPSHOT_TIMEOUT,
+    },
+    time_control::TimeControl,
+    types::{Capability, ConnectionName, ContainerRuntimeName, InterfaceName},
     ComponentGraph, Config, Deployment, DeploymentContainer, WorldOrMachineComponent,
 };
-use anyhow::{bail, Result};
-use std::collections::BTreeMap;
-use std::path::Path;
+use anyhow::{anyhow, bail, Context as _, Result};
+use futures_util::future::try_join_all;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+pub use robot::RobotResult;
+pub use status::{ComponentStatus, ConnectionStatus, ContainerStatus, SystemStatus};
+
+mod fault;
+mod machine_io;
+mod robot;
+mod status;
+mod supervise;
 
 pub struct System {
     config: Config,
@@ -18,6 +40,14 @@ pub struct System {
     networks: BTreeMap<ConnectionName, Network>,
 }
 
+/// A container to snapshot, and the monitor or QMP server port its snapshot
+/// commands are sent to, container machines are committed instead
+struct SnapshotTarget {
+    container: ContainerRuntimeName,
+    kind: SnapshotKind,
+    port: Option<u16>,
+}
+
 impl System {
     pub fn from_config_no_runtime(config: Config) -> Self {
         System {
@@ -150,13 +180,536 @@ impl System {
     }
 
     pub async fn start(&mut self) -> Result<()> {
+        self.setup_container_machine_can_interfaces().await?;
+
         for rt in &mut self.containers {
             rt.start().await?;
         }
 
+        self.apply_bridge_impairments().await?;
+
+        self.event_log().append(&Event::now(
+            EventKind::Start,
+            None,
+            "system started".to_owned(),
+        ))?;
+        Ok(())
+    }
+
+    pub async fn stop(&mut self) -> Result<()> {
+        for rt in &mut self.containers {
+            rt.refresh().await?;
+        }
+
+        self.teardown_renode_external_networks().await?;
+
+        for rt in &mut self.containers {
+            rt.stop().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop and remove the system's containers
+    pub async fn remove(&mut self) -> Result<()> {
+        for rt in &mut self.containers {
+            rt.remove().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Renode's wrapped command only runs the external network teardown script when
+    /// Renode exits by itself, stopping the container kills it first. The tap devices
+    /// live on the host network, so the script is run before the containers are stopped.
+    async fn teardown_renode_external_networks(&self) -> Result<()> {
+        let deployment = self.deployment()?;
+        let script = provider::renode::guest_external_network_teardown_script_path();
+        for c in deployment
+            .renode_containers
+            .iter()
+            .filter(|c| c.generated_guest_files.contains_key(&script))
+        {
+            let container = self.find_container(&c.name)?;
+            if container.is_running() {
+                container
+                    .exec(&["/bin/bash".to_owned(), script.display().to_string()])
+                    .await?;
+            }
+        }
         Ok(())
     }
 
+    /// Container machines run arbitrary images that can't be relied on to have `ip` or
+    /// CAP_NET_ADMIN, so their CAN interfaces are created from the host's network
+    /// before they start
+    async fn setup_container_machine_can_interfaces(&self) -> Result<()> {
+        let deployment = self.deployment()?;
+        let can_interfaces: BTreeSet<&InterfaceName> = deployment
+            .container_containers
+            .iter()
+            .flat_map(|c| c.can_interfaces.values())
+            .collect();
+        if can_interfaces.is_empty() {
+            return Ok(());
+        }
+
+        let script = provider::can_bus_setup_commands(can_interfaces).join(" && ");
+        self.run_host_network_script("can-setup", script)
+            .await
+            .context("creating the CAN interfaces failed")
+    }
+
+    /// Runs a script on the host's network with CAP_NET_ADMIN, in a short-lived
+    /// container of the stock QEMU image
+    pub(crate) async fn run_host_network_script(&self, name: &str, script: String) -> Result<()> {
+        let stock = StockImage::Qemu;
+        let mut container = ContainerBuilder::default()
+            .with_name(format!("{}-{name}", self.config.global.name))
+            .with_image(stock.remote_image())
+            .with_fallback_containerfile_content(stock.containerfile())
+            .with_cmd(["/bin/bash".to_owned(), "-c".to_owned(), script])
+            .with_capabilities([Capability::net_admin()])
+            .resolve()
+            .await?;
+        container.remove().await?;
+        container.build().await?;
+        container.start().await?;
+        let exit_code = container.wait().await;
+        container.remove().await?;
+        match exit_code? {
+            0 => Ok(()),
+            code => bail!("the script exited with code {code}"),
+        }
+    }
+
+    /// Containers other than Renode's use the host's network, so their impairments are
+    /// applied with `tc netem` to the network bridges their traffic leaves through,
+    /// from within the containers once they're running. Their images require `tc`.
+    async fn apply_bridge_impairments(&self) -> Result<()> {
+        let deployment = self.deployment()?;
+        let impaired = deployment
+            .gazebo_containers
+            .iter()
+            .map(|c| (&c.name, &c.bridge_impairments))
+            .chain(
+                deployment
+                    .qemu_containers
+                    .iter()
+                    .map(|c| (&c.name, &c.bridge_impairments)),
+            )
+            .chain(
+                deployment
+                    .container_containers
+                    .iter()
+                    .map(|c| (&c.name, &c.bridge_impairments)),
+            );
+        for (name, impairments) in impaired {
+            let container = self.find_container(name)?;
+            for (bridge, impairment) in impairments.iter() {
+                container.exec(&impairment.tc_command(bridge)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the impairment of a network connection on the running system.
+    ///
+    /// The impairment is applied with `tc netem` to each Renode tap device backing
+    /// the connection and, for the other components, to the connection's host bridge.
+    /// An empty impairment removes any previously applied one.
+    pub async fn impair_connection(
+        &self,
+        connection: &ConnectionName,
+        impairment: &NetworkImpairment,
+    ) -> Result<()> {
+        let is_network_connection = self
+            .config
+            .connections
+            .iter()
+            .any(|c| c.name() == connection && c.is_network());
+        if !is_network_connection {
+            bail!("network connection '{connection}' not found");
+        }
+
+        let deployment = self.deployment()?;
+        let mut applied = false;
+        for deployment_container in deployment.renode_containers.iter() {
+            let Some(tap) = deployment_container
+                .components
+                .iter()
+                .find_map(|m| m.tap_devices.get(connection))
+            else {
+                continue;
+            };
+            let container = self
+                .containers
+                .iter()
+                .find(|c| c.name() == Some(deployment_container.name.as_str()))
+                .ok_or_else(|| anyhow!("container '{}' not found", deployment_container.name))?;
+            container.exec(&impairment.tc_command(tap)).await?;
+            applied = true;
+        }
+
+        // The other containers share the host's bridge, so it's impaired once
+        let host_connected = deployment
+            .gazebo_containers
+            .iter()
+            .map(|c| (&c.name, &c.connections))
+            .chain(
+                deployment
+                    .qemu_containers
+                    .iter()
+                    .map(|c| (&c.name, &c.connections)),
+            )
+            .chain(
+                deployment
+                    .container_containers
+                    .iter()
+                    .map(|c| (&c.name, &c.connections)),
+            )
+            .find(|(_, connections)| connections.iter().any(|c| c.name() == connection));
+        if let Some((name, _)) = host_connected {
+            // NOTE: unwrap ok, it's a network connection of the graph
+            let bridge = deployment::wired_network_bridge(&self.graph()?, connection).unwrap();
+            self.find_container(name)?
+                .exec(&impairment.tc_command(&bridge))
+                .await?;
+            applied = true;
+        }
+
+        if !applied {
+            bail!("connection '{connection}' doesn't connect any components");
+        }
+
+        Ok(())
+    }
+
+    /// Pause the simulated time of every component in the running system
+    pub async fn pause(&self) -> Result<()> {
+        let controls = self.time_controls()?;
+        try_join_all(controls.into_iter().map(|(c, tc)| tc.pause(c))).await?;
+        Ok(())
+    }
+
+    /// Resume the simulated time of every component in the running system
+    pub async fn resume(&self) -> Result<()> {
+        let controls = self.time_controls()?;
+        try_join_all(controls.into_iter().map(|(c, tc)| tc.resume(c))).await?;
+        Ok(())
+    }
+
+    /// Run every component of the paused system for the given duration, then pause
+    /// it again.
+    ///
+    /// The duration is simulated time for Renode machines and Gazebo worlds, the
+    /// other components are run for the duration in wall-clock time.
+    pub async fn step(&self, duration: Duration) -> Result<()> {
+        let controls = self.time_controls()?;
+        try_join_all(controls.into_iter().map(|(c, tc)| tc.step(c, duration))).await?;
+        Ok(())
+    }
+
+    /// How each container's time is controlled. Renode machines without a monitor
+    /// port and QEMU machines without a QMP server fall back to pausing their container.
+    fn time_controls(&self) -> Result<Vec<(&Container, TimeControl)>> {
+        let deployment = self.deployment()?;
+        let mut controls = Vec::new();
+        for c in deployment.gazebo_containers.iter() {
+            controls.push((self.find_container(&c.name)?, TimeControl::GazeboWorld));
+        }
+        for c in deployment.renode_containers.iter() {
+            let control = c
+                .machine()
+                .provider
+                .cli
+                .port
+                .map(TimeControl::RenodeMonitor)
+                .unwrap_or(TimeControl::ContainerPause);
+            controls.push((self.find_container(&c.name)?, control));
+        }
+        for c in deployment.qemu_containers.iter() {
+            let control = c
+                .machine()
+                .qmp_server_port()
+                .map(TimeControl::Qmp)
+                .unwrap_or(TimeControl::ContainerPause);
+            controls.push((self.find_container(&c.name)?, control));
+        }
+        for c in deployment.container_containers.iter() {
+            controls.push((self.find_container(&c.name)?, TimeControl::ContainerPause));
+        }
+        Ok(controls)
+    }
+
+    /// Where the system's persistent state, like snapshots, is kept.
+    ///
+    /// This is `$XDG_STATE_HOME/conductor/<system>`, falling back to
+    /// `$HOME/.local/state/conductor/<system>`.
+    pub fn state_dir(&self) -> PathBuf {
+        let base = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+            .unwrap_or_else(|| env::temp_dir().join("conductor_state"));
+        base.join("conductor")
+            .join(self.config.global.name.as_str())
+    }
+
+    /// The system's event log, in its state directory
+    pub fn event_log(&self) -> EventLog {
+        EventLog::new(&self.state_dir())
+    }
+
+    /// Save a snapshot of the running system's machines.
+    ///
+    /// The system is paused while the snapshot is taken. Renode emulations are saved
+    /// to the snapshot directory within the system's state directory, QEMU VM
+    /// snapshots are stored in each machine's qcow2 storage and container machines
+    /// are committed to local images. Only their filesystem is kept, their processes
+    /// are restarted when the snapshot is loaded.
+    /// Gazebo worlds can't be snapshotted, systems with them are refused.
+    pub async fn save_snapshot(&self, name: &SnapshotName) -> Result<SnapshotMetadata> {
+        let deployment = self.deployment()?;
+        let targets = self.snapshot_targets(&deployment)?;
+
+        // An existing snapshot of the same name is only replaced once the new one
+        // has been saved
+        let dir = snapshot::snapshot_dir(&self.state_dir(), name);
+        let partial_dir = snapshot::partial_snapshot_dir(&self.state_dir(), name);
+        if partial_dir.exists() {
+            fs::remove_dir_all(&partial_dir)?;
+        }
+        fs::create_dir_all(&partial_dir)?;
+
+        self.pause().await?;
+        let saved = self
+            .save_snapshot_targets(name, &partial_dir, &targets)
+            .await;
+        self.resume().await?;
+        if let Err(e) = saved {
+            let _ = fs::remove_dir_all(&partial_dir);
+            return Err(e);
+        }
+
+        let metadata = SnapshotMetadata {
+            name: name.to_string(),
+            system: self.config.global.name.to_string(),
+            deployment_digest: snapshot::deployment_digest(&deployment),
+            created: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            containers: targets
+                .iter()
+                .map(|t| SnapshotContainer {
+                    name: t.container.to_string(),
+                    kind: t.kind,
+                })
+                .collect(),
+        };
+        metadata.write(&partial_dir)?;
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::rename(&partial_dir, &dir)?;
+        Ok(metadata)
+    }
+
+    /// Restore the running system to a previously saved snapshot.
+    ///
+    /// The snapshot is refused if the system's deployment has changed since it was
+    /// taken. Container machines are recreated from their committed images.
+    pub async fn load_snapshot(&mut self, name: &SnapshotName) -> Result<SnapshotMetadata> {
+        let deployment = self.deployment()?;
+        let dir = snapshot::snapshot_dir(&self.state_dir(), name);
+        if !dir.exists() {
+            bail!("snapshot '{name}' not found");
+        }
+        let metadata = SnapshotMetadata::read(&dir)?;
+        if metadata.deployment_digest != snapshot::deployment_digest(&deployment) {
+            bail!("the system's deployment has changed since snapshot '{name}' was taken");
+        }
+        let targets = self.snapshot_targets(&deployment)?;
+
+        self.pause().await?;
+        let loaded = self.load_snapshot_targets(name, &dir, &targets).await;
+        self.resume().await?;
+        loaded?;
+
+        Ok(metadata)
+    }
+
+    /// The containers to snapshot, and the ports their snapshot commands are sent to
+    fn snapshot_targets(&self, deployment: &Deployment) -> Result<Vec<SnapshotTarget>> {
+        let mut targets = Vec::new();
+        // Restoring the machines around a world would leave an inconsistent system
+        if let Some(c) = deployment.gazebo_containers.first() {
+            bail!(
+                "world '{}' runs in Gazebo, systems with Gazebo worlds can't be snapshotted",
+                c.world().base.name
+            );
+        }
+        for c in deployment.renode_containers.iter() {
+            let Some(port) = c.machine().provider.cli.port else {
+                bail!(
+                    "container '{}' has no Renode monitor port, one is required for snapshots",
+                    c.name
+                );
+            };
+            targets.push(SnapshotTarget {
+                container: c.name.clone(),
+                kind: SnapshotKind::RenodeSave,
+                port: Some(port),
+            });
+        }
+        for c in deployment.qemu_containers.iter() {
+            let Some(port) = c.machine().qmp_server_port() else {
+                bail!(
+                    "container '{}' has no QMP server port, one is required for snapshots",
+                    c.name
+                );
+            };
+            if !c.machine().has_writable_qcow2_drive() {
+                bail!(
+                    "machine '{}' has no writable qcow2 storage, one is required to store its snapshots",
+                    c.machine().base.name
+                );
+            }
+            targets.push(SnapshotTarget {
+                container: c.name.clone(),
+                kind: SnapshotKind::QemuVm,
+                port: Some(port),
+            });
+        }
+        for c in deployment.container_containers.iter() {
+            targets.push(SnapshotTarget {
+                container: c.name.clone(),
+                kind: SnapshotKind::ContainerCommit,
+                port: None,
+            });
+        }
+        Ok(targets)
+    }
+
+    async fn save_snapshot_targets(
+        &self,
+        name: &SnapshotName,
+        dir: &Path,
+        targets: &[SnapshotTarget],
+    ) -> Result<()> {
+        for t in targets.iter() {
+            let container = self.find_container(&t.container)?;
+            match (t.kind, t.port) {
+                (SnapshotKind::RenodeSave, Some(port)) => {
+                    let path = snapshot::guest_renode_save_path(t.container.as_str());
+                    container
+                        .exec(&[
+                            "mkdir".to_owned(),
+                            "-p".to_owned(),
+                            GUEST_SNAPSHOT_DIR.to_owned(),
+                        ])
+                        .await?;
+                    let cmds = [format!("Save @{path}")];
+                    container
+                        .exec(&monitor::renode_monitor_sync_command(
+                            port,
+                            &cmds,
+                            SNAPSHOT_TIMEOUT,
+                        ))
+                        .await?;
+                    container.copy_from(&path, dir).await?;
+                }
+                (SnapshotKind::QemuVm, Some(port)) => {
+                    let cmd = format!("savevm {}", snapshot::qemu_vm_tag(name));
+                    container
+                        .exec(&monitor::qmp_human_monitor_sync_command(
+                            port,
+                            &cmd,
+                            SNAPSHOT_TIMEOUT,
+                        ))
+                        .await?;
+                }
+                (SnapshotKind::ContainerCommit, _) => {
+                    let tag = snapshot::container_snapshot_tag(t.container.as_str(), name);
+                    container
+                        .commit(snapshot::CONTAINER_SNAPSHOT_REPO, &tag)
+                        .await?;
+                }
+                (_, None) => unreachable!("monitor snapshots have a port"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_snapshot_targets(
+        &mut self,
+        name: &SnapshotName,
+        dir: &Path,
+        targets: &[SnapshotTarget],
+    ) -> Result<()> {
+        for t in targets.iter() {
+            let container = self.find_container_mut(&t.container)?;
+            match (t.kind, t.port) {
+                (SnapshotKind::RenodeSave, Some(port)) => {
+                    let path = snapshot::guest_renode_save_path(t.container.as_str());
+                    let file_name = Path::new(&path).file_name().unwrap();
+                    container
+                        .exec(&[
+                            "mkdir".to_owned(),
+                            "-p".to_owned(),
+                            GUEST_SNAPSHOT_DIR.to_owned(),
+                        ])
+                        .await?;
+                    container
+                        .copy_to(&dir.join(file_name), GUEST_SNAPSHOT_DIR)
+                        .await?;
+                    let cmds = [format!("Load @{path}")];
+                    container
+                        .exec(&monitor::renode_monitor_sync_command(
+                            port,
+                            &cmds,
+                            SNAPSHOT_TIMEOUT,
+                        ))
+                        .await?;
+                }
+                (SnapshotKind::QemuVm, Some(port)) => {
+                    let cmd = format!("loadvm {}", snapshot::qemu_vm_tag(name));
+                    container
+                        .exec(&monitor::qmp_human_monitor_sync_command(
+                            port,
+                            &cmd,
+                            SNAPSHOT_TIMEOUT,
+                        ))
+                        .await?;
+                }
+                (SnapshotKind::ContainerCommit, _) => {
+                    let tag = snapshot::container_snapshot_tag(t.container.as_str(), name);
+                    container
+                        .recreate_from_image(&format!(
+                            "{}:{tag}",
+                            snapshot::CONTAINER_SNAPSHOT_REPO
+                        ))
+                        .await?;
+                    // Paused like the rest of the system until it's resumed
+                    container.pause().await?;
+                }
+                (_, None) => unreachable!("monitor snapshots have a port"),
+            }
+        }
+        Ok(())
+    }
+
+    fn find_container(&self, name: &ContainerRuntimeName) -> Result<&Container> {
+        self.containers
+            .iter()
+            .find(|c| c.name() == Some(name.as_str()))
+            .ok_or_else(|| anyhow!("container '{name}' not found"))
+    }
+
+    fn find_container_mut(&mut self, name: &ContainerRuntimeName) -> Result<&mut Container> {
+        self.containers
+            .iter_mut()
+            .find(|c| c.name() == Some(name.as_str()))
+            .ok_or_else(|| anyhow!("container '{name}' not found"))
+    }
+
     async fn new_gazebo_world(
         &mut self,
         deployment: &DeploymentContainer<GazeboWorld>,
@@ -164,12 +717,21 @@ impl System {
         let name = deployment.name.clone();
         let mut cmd = deployment.args.clone();
         cmd.insert(0, deployment.command.clone());
+        let world = deployment.world();
         let mut container = ContainerBuilder::default()
-            .with_image(deployment.world().base_image())
             .with_name(name.as_str())
             .with_cmd(cmd)
             .with_env(&deployment.environment_variables.0)
-            .with_gpu_cap(deployment.uses_host_display);
+            .with_gpu_cap(deployment.uses_host_display && deployment.vnc.is_none())
+            .with_resources(deployment.resources.clone())
+            .with_devices(deployment.devices.iter().cloned())
+            .with_capabilities(deployment.capabilities.iter().cloned());
+        provider::set_provider_image(
+            &mut container,
+            &name,
+            StockImage::Gazebo,
+            &world.provider.base_image,
+        );
         if !deployment.assets.is_empty() {
             let mounts = deployment
                 .assets
@@ -190,23 +752,9 @@ impl System {
         &mut self,
         deployment: &DeploymentContainer<RenodeMachine>,
     ) -> Result<()> {
-        let name = deployment.name.clone();
         let mut cmd = deployment.args.clone();
         cmd.insert(0, deployment.command.clone());
-        let mut container = ContainerBuilder::default()
-            .with_image(deployment.base_image())
-            .with_name(name.as_str())
-            .with_cmd(cmd)
-            .with_env(&deployment.environment_variables.0)
-            .with_gpu_cap(deployment.uses_host_display);
-        if !deployment.assets.is_empty() {
-            let mounts = deployment
-                .assets
-                .as_ref()
-                .iter()
-                .map(|asset| (asset.0.to_str().unwrap(), asset.1.to_str().unwrap()));
-            container.set_mounts(mounts);
-        };
+        let container = renode_container_builder(deployment, deployment.name.as_str(), cmd);
 
         // TODO: networks
 
@@ -224,11 +772,19 @@ impl System {
         let mut cmd = deployment.args.clone();
         cmd.insert(0, deployment.command.clone());
         let mut container = ContainerBuilder::default()
-            .with_image(machine.base_image())
             .with_name(name.as_str())
             .with_cmd(cmd)
             .with_env(&deployment.environment_variables.0)
-            .with_gpu_cap(deployment.uses_host_display);
+            .with_gpu_cap(deployment.uses_host_display && deployment.vnc.is_none())
+            .with_resources(deployment.resources.clone())
+            .with_devices(deployment.devices.iter().cloned())
+            .with_capabilities(deployment.capabilities.iter().cloned());
+        provider::set_provider_image(
+            &mut container,
+            &name,
+            StockImage::Qemu,
+            &machine.provider.base_image,
+        );
         if !deployment.assets.is_empty() {
             let mounts = deployment
                 .assets
@@ -269,20 +825,33 @@ impl System {
         if let Some(ref context) = machine.provider.context {
             container.set_context(context);
         };
-        if !machine.base.assets.is_empty() {
-            let mounts = machine
-                .base
+        if !deployment.assets.is_empty() {
+            let mounts = deployment
                 .assets
                 .as_ref()
                 .iter()
                 .map(|asset| (asset.0.to_str().unwrap(), asset.1.to_str().unwrap()));
             container.set_mounts(mounts);
         };
-        // TODO: get this from bin once bin is optional and plumbed through
-        if let Some(ref cmd) = None::<Vec<String>> {
+        // An empty command means we use the image's command
+        if !deployment.command.is_empty() {
+            let mut cmd = deployment.args.clone();
+            cmd.insert(0, deployment.command.clone());
             container.set_cmd(cmd);
         };
+        if let Some(ref entrypoint) = machine.provider.entrypoint {
+            container.set_entrypoint(entrypoint.to_argv());
+        };
+        if let Some(ref workdir) = machine.provider.workdir {
+            container.set_workdir(workdir.to_string_lossy());
+        };
+        if let Some(ref user) = machine.provider.user {
+            container.set_user(user);
+        };
         container.set_env(&deployment.environment_variables.0);
+        container.set_resources(deployment.resources.clone());
+        container.set_devices(deployment.devices.iter().cloned());
+        container.set_capabilities(deployment.capabilities.iter().cloned());
 
         let networks = machine
             .base
@@ -305,6 +874,39 @@ impl System {
     }
 }
 
+/// The container running a Renode deployment container's command
+fn renode_container_builder(
+    deployment: &DeploymentContainer<RenodeMachine>,
+    name: &str,
+    cmd: Vec<String>,
+) -> ContainerBuilder {
+    let mut container = ContainerBuilder::default()
+        .with_name(name)
+        .with_cmd(cmd)
+        .with_env(&deployment.environment_variables.0)
+        .with_gpu_cap(deployment.uses_host_display && deployment.vnc.is_none())
+        .with_resources(deployment.resources.clone())
+        .with_devices(deployment.devices.iter().cloned())
+        .with_capabilities(deployment.capabilities.iter().cloned());
+    // The image is named after the deployment container, so it's shared with the
+    // containers derived from it
+    provider::set_provider_image(
+        &mut container,
+        &deployment.name,
+        StockImage::Renode,
+        &deployment.machine().provider.base_image,
+    );
+    if !deployment.assets.is_empty() {
+        let mounts = deployment
+            .assets
+            .as_ref()
+            .iter()
+            .map(|asset| (asset.0.to_str().unwrap(), asset.1.to_str().unwrap()));
+        container.set_mounts(mounts);
+    };
+    container
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -330,11 +932,15 @@ mod tests {
                     name: SystemName::new_canonicalize("fake-system").unwrap(),
                     display: None,
                     xauthority: None,
+                    wayland_socket: None,
+                    vnc: None,
                     environment_variables: Default::default(),
+                    time_scale: None,
                 },
                 machines: Vec::new(),
                 connections: BTreeSet::new(),
                 worlds: Vec::new(),
+                storages: Vec::new(),
             },
             containers: vec![
                 Container::builder()
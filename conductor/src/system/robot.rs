@@ -0,0 +1,183 @@
+use super::{renode_container_builder, System};
+use crate::{
+    provider::renode::{robot, RenodeMachine, RenodeScriptGen},
+    types::{ConnectionName, ContainerRuntimeName, TapDevice},
+    DeploymentContainer,
+};
+use anyhow::{anyhow, bail, Context as _, Result};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+/// The outcome of running the Robot suites against one Renode container
+#[derive(Clone, Debug)]
+pub struct RobotResult {
+    pub container: ContainerRuntimeName,
+    /// renode-test's exit code, non-zero when a test failed
+    pub exit_code: i64,
+    /// Where the Robot output XML, log and report were written
+    pub output_dir: PathBuf,
+}
+
+impl RobotResult {
+    pub fn passed(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+impl System {
+    /// Run Robot Framework suites with Renode's `renode-test`, once per Renode
+    /// container of the system.
+    ///
+    /// Each run is in a container of its own, set up like the system's, with the
+    /// conductor-generated script available to the suites. The script creates the
+    /// machines under their conductor names but doesn't start the emulation, suites
+    /// including `${CONDUCTOR_RESOURCE}` can use the `Load Conductor System` keyword.
+    ///
+    /// The Robot output of each run is written to `<output_dir>/<container>`.
+    /// renode-test starts its own Renode, using the configured ports, so the system
+    /// mustn't be running.
+    pub async fn run_robot_suites(
+        &self,
+        suites: &[PathBuf],
+        output_dir: &Path,
+    ) -> Result<Vec<RobotResult>> {
+        if suites.is_empty() {
+            bail!("no Robot suites to run");
+        }
+        let deployment = self.deployment()?;
+        if deployment.renode_containers.is_empty() {
+            bail!("the system has no Renode machines to run Robot suites against");
+        }
+        if let Some(c) = deployment
+            .renode_containers
+            .iter()
+            .find(|c| matches!(self.find_container(&c.name), Ok(c) if c.is_running()))
+        {
+            bail!(
+                "container '{}' is running, stop the system before running Robot suites",
+                c.name
+            );
+        }
+
+        // Suites are mounted by directory, so the resources next to them are available
+        let mut suite_dirs: Vec<PathBuf> = Vec::new();
+        let mut guest_suites = Vec::new();
+        for suite in suites.iter() {
+            let suite = suite
+                .canonicalize()
+                .with_context(|| format!("Robot suite '{}' not found", suite.display()))?;
+            let (Some(dir), Some(file_name)) = (suite.parent(), suite.file_name()) else {
+                bail!("invalid Robot suite path '{}'", suite.display());
+            };
+            let index = match suite_dirs.iter().position(|d| d == dir) {
+                Some(index) => index,
+                None => {
+                    suite_dirs.push(dir.to_owned());
+                    suite_dirs.len() - 1
+                }
+            };
+            guest_suites.push(robot::guest_suites_path(index).join(file_name));
+        }
+
+        let mut results = Vec::new();
+        for c in deployment.renode_containers.iter() {
+            let output_dir = output_dir.join(c.name.as_str());
+            let exit_code = self
+                .run_robot_container(c, &suite_dirs, &guest_suites, &output_dir)
+                .await?;
+            info!(container = %c.name, exit_code, "Robot suites finished");
+            results.push(RobotResult {
+                container: c.name.clone(),
+                exit_code,
+                output_dir,
+            });
+        }
+        Ok(results)
+    }
+
+    async fn run_robot_container(
+        &self,
+        deployment: &DeploymentContainer<RenodeMachine>,
+        suite_dirs: &[PathBuf],
+        guest_suites: &[PathBuf],
+        output_dir: &Path,
+    ) -> Result<i64> {
+        // Generated files are staged like the deployment's generated assets
+        let host_dir = env::temp_dir()
+            .join("conductor_robot")
+            .join(self.config.global.name.as_str())
+            .join(deployment.name.as_str());
+        if host_dir.exists() {
+            fs::remove_dir_all(&host_dir)?;
+        }
+        fs::create_dir_all(&host_dir)?;
+        fs::write(
+            host_dir.join(robot::RESC_FILE_NAME),
+            robot_resc_content(deployment)?,
+        )?;
+        fs::write(
+            host_dir.join(robot::RESOURCE_FILE_NAME),
+            robot::RESOURCE_CONTENT,
+        )?;
+        fs::create_dir_all(output_dir)?;
+        let output_dir = output_dir.canonicalize()?;
+
+        let mut mounts: Vec<(PathBuf, PathBuf)> = deployment
+            .assets
+            .as_ref()
+            .iter()
+            .map(|(host, guest)| (host.clone(), guest.clone()))
+            .collect();
+        for f in [robot::RESC_FILE_NAME, robot::RESOURCE_FILE_NAME] {
+            mounts.push((host_dir.join(f), Path::new(robot::GUEST_ROBOT_PATH).join(f)));
+        }
+        for (index, dir) in suite_dirs.iter().enumerate() {
+            mounts.push((dir.clone(), robot::guest_suites_path(index)));
+        }
+        mounts.push((output_dir, PathBuf::from(robot::GUEST_ROBOT_RESULTS_PATH)));
+
+        let external_networks =
+            !deployment.taps_to_bridges.is_empty() || !deployment.can_interfaces.is_empty();
+        let cmd = robot::container_command(guest_suites, external_networks);
+        let name = format!("{}-robot", deployment.name);
+        let mut builder = renode_container_builder(deployment, &name, cmd);
+        builder.set_mounts(mounts.iter().map(|(host, guest)| {
+            (
+                host.to_str().unwrap().to_owned(),
+                guest.to_str().unwrap().to_owned(),
+            )
+        }));
+
+        let mut container = builder.resolve().await?;
+        // A previous run's container would run its previous suites
+        container.remove().await?;
+        container.build().await?;
+        container.start().await?;
+        let exit_code = container.wait().await;
+        container.remove().await?;
+        exit_code
+    }
+}
+
+/// The deployment's script, without starting the emulation
+fn robot_resc_content(deployment: &DeploymentContainer<RenodeMachine>) -> Result<String> {
+    let tap_devices: BTreeMap<ConnectionName, TapDevice> = deployment
+        .components
+        .iter()
+        .flat_map(|m| m.tap_devices.clone().into_iter())
+        .collect();
+    let mut resc = Vec::new();
+    RenodeScriptGen::new(&mut resc)
+        .with_start_emulation(false)
+        .with_can_interfaces(deployment.can_interfaces.clone())
+        .generate(
+            &deployment.components,
+            &deployment.connections,
+            &tap_devices,
+        )?;
+    String::from_utf8(resc).map_err(|e| anyhow!(e))
+}
@@ -0,0 +1,247 @@
+use super::System;
+use crate::{
+    containers::{Container, ContainerState},
+    deployment,
+    events::{Event, EventKind},
+    types::ComponentName,
+    Component,
+};
+use anyhow::Result;
+use derive_more::Display;
+use serde::Serialize;
+use std::{net::Ipv4Addr, path::Path, time::Duration};
+use tokio::net::TcpStream;
+
+/// How long a component's control port gets to accept a connection before it's
+/// considered not ready
+const READY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the host's network interfaces, including the Renode tap devices, are listed
+const HOST_NET_DIR: &str = "/sys/class/net";
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SystemStatus {
+    pub name: String,
+    /// Every component and connection is healthy
+    pub healthy: bool,
+    pub components: Vec<ComponentStatus>,
+    pub connections: Vec<ConnectionStatus>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ComponentStatus {
+    pub name: String,
+    pub container: String,
+    pub state: ContainerStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i64>,
+    pub oom_killed: bool,
+    /// The container is running and its Renode monitor or QMP server, when it has
+    /// one, accepts connections
+    pub ready: bool,
+    /// Restarts by the supervisor since the system was last started
+    pub restarts: usize,
+    /// The container is running and ready
+    pub healthy: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConnectionStatus {
+    pub name: String,
+    pub kind: String,
+    pub components: Vec<String>,
+    /// Why the connection is unhealthy, e.g. a missing tap device
+    pub problems: Vec<String>,
+    pub healthy: bool,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContainerStatus {
+    #[display(fmt = "not built")]
+    NotBuilt,
+    #[display(fmt = "built")]
+    Built,
+    #[display(fmt = "running")]
+    Running,
+    #[display(fmt = "exited")]
+    Exited,
+}
+
+impl From<&ContainerState> for ContainerStatus {
+    fn from(value: &ContainerState) -> Self {
+        match value {
+            ContainerState::Defined => ContainerStatus::NotBuilt,
+            ContainerState::Built { .. } => ContainerStatus::Built,
+            ContainerState::Running { .. } => ContainerStatus::Running,
+            ContainerState::Exited { .. } => ContainerStatus::Exited,
+        }
+    }
+}
+
+impl System {
+    /// The health of the system's components and of the connections between them
+    pub async fn status(&self) -> Result<SystemStatus> {
+        let events = self.event_log().read()?;
+        let deployment = self.deployment()?;
+
+        let mut components = Vec::new();
+        for component in self.components() {
+            let name = component.name();
+            let container = self.component_container(&name)?;
+            let container_name = container.name().unwrap_or_default().to_owned();
+            let (exit_code, oom_killed) = match container.state() {
+                ContainerState::Exited {
+                    exit_code,
+                    oom_killed,
+                    ..
+                } => (Some(*exit_code), *oom_killed),
+                _ => (None, false),
+            };
+            let control_port = deployment
+                .renode_containers
+                .iter()
+                .find(|c| c.name.as_str() == container_name)
+                .and_then(|c| c.machine().provider.cli.port)
+                .or_else(|| {
+                    deployment
+                        .qemu_containers
+                        .iter()
+                        .find(|c| c.name.as_str() == container_name)
+                        .and_then(|c| c.machine().qmp_server_port())
+                });
+            let ready = is_ready(container, control_port).await;
+            components.push(ComponentStatus {
+                name: name.to_string(),
+                state: container.state().into(),
+                exit_code,
+                oom_killed,
+                ready,
+                restarts: restarts_since_start(&events, &container_name),
+                healthy: container.is_running() && ready,
+                container: container_name,
+            });
+        }
+
+        let graph = self.graph()?;
+        let mut connections = Vec::new();
+        for connection in self.config.connections.iter() {
+            let name = connection.name();
+            let connected = graph
+                .connections_to_components()
+                .get(name)
+                .cloned()
+                .unwrap_or_default();
+
+            let mut problems = Vec::new();
+            for component in connected.iter() {
+                let container = self.component_container(component)?;
+                if !container.is_running() {
+                    problems.push(format!("'{component}' isn't running"));
+                }
+            }
+            if let Some(bridge) = connection
+                .is_network()
+                .then(|| deployment::wired_network_bridge(&graph, name))
+                .flatten()
+            {
+                if is_link_down(&bridge) {
+                    problems.push(format!("bridge '{bridge}' is down"));
+                }
+            }
+            for tap in deployment
+                .renode_containers
+                .iter()
+                .flat_map(|c| c.components.iter())
+                .filter(|m| connected.contains(&ComponentName::from(m.base.name.clone())))
+                .filter_map(|m| m.tap_devices.get(name))
+            {
+                if !Path::new(HOST_NET_DIR).join(tap).exists() {
+                    problems.push(format!("tap device '{tap}' is missing"));
+                }
+            }
+
+            connections.push(ConnectionStatus {
+                name: name.to_string(),
+                kind: connection.kind().to_string(),
+                components: connected.iter().map(|c| c.to_string()).collect(),
+                healthy: problems.is_empty(),
+                problems,
+            });
+        }
+
+        Ok(SystemStatus {
+            name: self.config.global.name.to_string(),
+            healthy: components.iter().all(|c| c.healthy) && connections.iter().all(|c| c.healthy),
+            components,
+            connections,
+        })
+    }
+}
+
+/// Containers run on the host network, so a control port is reachable from the host
+async fn is_ready(container: &Container, control_port: Option<u16>) -> bool {
+    if !container.is_running() {
+        return false;
+    }
+    let Some(port) = control_port else {
+        return true;
+    };
+    matches!(
+        tokio::time::timeout(
+            READY_TIMEOUT,
+            TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+/// Whether a host network interface exists but isn't up, e.g. a bridge taken down
+/// by a disconnect fault
+fn is_link_down(iface: &str) -> bool {
+    // IFF_UP, from linux/if.h
+    const IFF_UP: u32 = 0x1;
+    std::fs::read_to_string(Path::new(HOST_NET_DIR).join(iface).join("flags"))
+        .ok()
+        .and_then(|flags| u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16).ok())
+        .is_some_and(|flags| flags & IFF_UP == 0)
+}
+
+/// The container's restarts recorded in the event log since the system was last
+/// started
+fn restarts_since_start(events: &[Event], container: &str) -> usize {
+    events
+        .iter()
+        .rev()
+        .take_while(|e| e.kind != EventKind::Start)
+        .filter(|e| e.kind == EventKind::Restart && e.target.as_deref() == Some(container))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_restarts_since_start() {
+        let event =
+            |kind, target: Option<&str>| Event::now(kind, target.map(str::to_owned), String::new());
+        let events = vec![
+            event(EventKind::Start, None),
+            event(EventKind::Restart, Some("sys_m0")),
+            event(EventKind::Start, None),
+            event(EventKind::Exit, Some("sys_m0")),
+            event(EventKind::Restart, Some("sys_m0")),
+            event(EventKind::Restart, Some("sys_m1")),
+            event(EventKind::Restart, Some("sys_m0")),
+        ];
+        assert_eq!(restarts_since_start(&events, "sys_m0"), 2);
+        assert_eq!(restarts_since_start(&events, "sys_m1"), 1);
+        assert_eq!(restarts_since_start(&events, "sys_m2"), 0);
+        assert_eq!(restarts_since_start(&[], "sys_m0"), 0);
+    }
+}
@@ -0,0 +1,243 @@
+use super::System;
+use crate::{
+    containers::ContainerState,
+    events::{Event, EventKind},
+    restart::{RestartPolicy, RestartPolicyKind},
+    types::{ComponentName, ContainerRuntimeName},
+};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// How often the supervised containers are checked for having exited
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A restarted container that keeps running this long resets its restart count
+const STABLE_AFTER: Duration = Duration::from_secs(10);
+
+/// The restart bookkeeping of a supervised container
+#[derive(Default)]
+struct Supervised {
+    policy: RestartPolicy,
+    /// Consecutive restarts, reset once the container has been stable for a while
+    restarts: u32,
+    restarted_at: Option<Instant>,
+    /// When the exited container is due to be restarted
+    restart_at: Option<Instant>,
+}
+
+/// What the supervisor does about a container that exited
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum ExitAction {
+    /// Stop supervising, the whole system exits with the container's exit code
+    Abort,
+    /// Restart the container once its backoff has passed
+    Restart,
+    /// The container ran out of restarts
+    GiveUp,
+    /// The container's restart policy doesn't restart it
+    Leave,
+}
+
+impl Supervised {
+    fn on_exit(&mut self, exit_code: i64, oom_killed: bool, abort: bool) -> ExitAction {
+        if abort {
+            return ExitAction::Abort;
+        }
+        if self
+            .restarted_at
+            .is_some_and(|at| at.elapsed() >= STABLE_AFTER)
+        {
+            self.restarts = 0;
+        }
+        if self
+            .policy
+            .should_restart(exit_code, oom_killed, self.restarts)
+        {
+            self.restart_at = Some(Instant::now() + self.policy.backoff(self.restarts));
+            ExitAction::Restart
+        } else if self.policy.kind != RestartPolicyKind::No && self.restarts > 0 {
+            ExitAction::GiveUp
+        } else {
+            ExitAction::Leave
+        }
+    }
+}
+
+impl System {
+    /// Supervise the running system, restarting the containers that exit according
+    /// to their components' restart policies. Exits and restarts are recorded in the
+    /// system's event log.
+    ///
+    /// Returns once no container is running or waiting to be restarted. When
+    /// `abort_on_exit` is set, returns that component's exit code as soon as its
+    /// container exits instead of restarting it.
+    pub async fn supervise(
+        &mut self,
+        abort_on_exit: Option<&ComponentName>,
+    ) -> Result<Option<i64>> {
+        let log = self.event_log();
+        let abort_container = abort_on_exit
+            .map(|c| {
+                self.component_container(c)?
+                    .name()
+                    .map(str::to_owned)
+                    .ok_or_else(|| anyhow!("component '{c}' has an unnamed container"))
+            })
+            .transpose()?;
+        let mut supervised: BTreeMap<String, Supervised> = self
+            .restart_policies()?
+            .into_iter()
+            .map(|(name, policy)| {
+                let s = Supervised {
+                    policy,
+                    ..Default::default()
+                };
+                (name.to_string(), s)
+            })
+            .collect();
+
+        loop {
+            for container in self.containers.iter_mut() {
+                let Some(name) = container.name().map(str::to_owned) else {
+                    continue;
+                };
+                let s = supervised.entry(name.clone()).or_default();
+
+                if let Some(restart_at) = s.restart_at {
+                    if Instant::now() < restart_at {
+                        continue;
+                    }
+                    s.restart_at = None;
+                    s.restarts += 1;
+                    s.restarted_at = Some(Instant::now());
+                    let attempt = match s.policy.max_retries {
+                        Some(max) => format!("restart {} of {max}", s.restarts),
+                        None => format!("restart {}", s.restarts),
+                    };
+                    match container.start().await {
+                        Ok(()) => log.append(&Event::now(
+                            EventKind::Restart,
+                            Some(name.clone()),
+                            format!("restarted '{name}', {attempt}"),
+                        ))?,
+                        Err(e) => {
+                            warn!(container = %name, error = %e, "Failed to restart container");
+                            log.append(&Event::now(
+                                EventKind::Restart,
+                                Some(name.clone()),
+                                format!("failed to restart '{name}', {attempt}: {e}"),
+                            ))?;
+                            // A failed restart is a failure like any other
+                            if s.policy.should_restart(1, false, s.restarts) {
+                                s.restart_at = Some(Instant::now() + s.policy.backoff(s.restarts));
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                if !container.is_running() {
+                    continue;
+                }
+                container.refresh().await?;
+                let ContainerState::Exited {
+                    exit_code,
+                    oom_killed,
+                    ..
+                } = *container.state()
+                else {
+                    continue;
+                };
+
+                let oom = if oom_killed { " (out of memory)" } else { "" };
+                let mut message = format!("'{name}' exited with code {exit_code}{oom}");
+                let is_abort_container = abort_container.as_deref() == Some(name.as_str());
+                match s.on_exit(exit_code, oom_killed, is_abort_container) {
+                    ExitAction::Abort => {
+                        log.append(&Event::now(EventKind::Exit, Some(name), message))?;
+                        return Ok(Some(exit_code));
+                    }
+                    ExitAction::GiveUp => {
+                        message.push_str(&format!(", giving up after {} restarts", s.restarts));
+                    }
+                    ExitAction::Restart | ExitAction::Leave => (),
+                }
+                log.append(&Event::now(EventKind::Exit, Some(name), message))?;
+            }
+
+            let waiting = supervised.values().any(|s| s.restart_at.is_some());
+            if !waiting && !self.containers.iter().any(|c| c.is_running()) {
+                return Ok(None);
+            }
+            tokio::time::sleep(SUPERVISE_INTERVAL).await;
+        }
+    }
+
+    /// The restart policy of each of the system's containers
+    pub fn restart_policies(&self) -> Result<BTreeMap<ContainerRuntimeName, RestartPolicy>> {
+        let deployment = self.deployment()?;
+        let policies = deployment
+            .gazebo_containers
+            .iter()
+            .map(|c| (c.name.clone(), c.restart))
+            .chain(
+                deployment
+                    .renode_containers
+                    .iter()
+                    .map(|c| (c.name.clone(), c.restart)),
+            )
+            .chain(
+                deployment
+                    .qemu_containers
+                    .iter()
+                    .map(|c| (c.name.clone(), c.restart)),
+            )
+            .chain(
+                deployment
+                    .container_containers
+                    .iter()
+                    .map(|c| (c.name.clone(), c.restart)),
+            )
+            .collect();
+        Ok(policies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supervised(kind: RestartPolicyKind, max_retries: Option<u32>) -> Supervised {
+        Supervised {
+            policy: RestartPolicy {
+                kind,
+                max_retries,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exit_actions() {
+        let mut s = supervised(RestartPolicyKind::Always, None);
+        assert_eq!(s.on_exit(3, false, true), ExitAction::Abort);
+        assert!(s.restart_at.is_none());
+        assert_eq!(s.on_exit(3, false, false), ExitAction::Restart);
+        assert!(s.restart_at.is_some());
+
+        let mut s = supervised(RestartPolicyKind::OnFailure, Some(1));
+        assert_eq!(s.on_exit(0, false, false), ExitAction::Leave);
+        s.restarts = 1;
+        s.restarted_at = Some(Instant::now());
+        assert_eq!(s.on_exit(1, false, false), ExitAction::GiveUp);
+
+        let mut s = supervised(RestartPolicyKind::No, None);
+        assert_eq!(s.on_exit(1, true, false), ExitAction::Leave);
+        assert_eq!(s.on_exit(0, false, true), ExitAction::Abort);
+    }
+}
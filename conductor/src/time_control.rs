@@ -0,0 +1,163 @@
+use crate::{
+    containers::Container,
+    provider::{
+        gazebo::REAL_TIME_UPDATE_RATE_HZ,
+        monitor::{qmp_command, renode_monitor_command},
+    },
+};
+use anyhow::Result;
+use std::time::Duration;
+
+/// How the simulated time of a container's components is controlled
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum TimeControl {
+    /// Renode monitor commands, sent to the monitor port
+    RenodeMonitor(u16),
+    /// QEMU Machine Protocol commands, sent to the QMP server port
+    Qmp(u16),
+    /// The Gazebo world control service
+    GazeboWorld,
+    /// The container's processes are frozen, for components without a way to
+    /// control their time
+    ContainerPause,
+}
+
+// NOTE:
+// * Renode and QEMU commands require socat on the guest, Gazebo's require gz-transport
+// * stepping is only exact for Renode and Gazebo, QEMU and plain containers are run
+//   for the duration in wall-clock time
+impl TimeControl {
+    pub(crate) async fn pause(self, container: &Container) -> Result<()> {
+        match self {
+            TimeControl::RenodeMonitor(port) => {
+                container
+                    .exec(&renode_monitor_command(port, &["pause"]))
+                    .await?;
+            }
+            TimeControl::Qmp(port) => {
+                container
+                    .exec(&qmp_command(port, r#"{"execute":"stop"}"#))
+                    .await?;
+            }
+            TimeControl::GazeboWorld => {
+                container
+                    .exec(&gazebo_world_control_command("pause: true"))
+                    .await?;
+            }
+            TimeControl::ContainerPause => container.pause().await?,
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn resume(self, container: &Container) -> Result<()> {
+        match self {
+            TimeControl::RenodeMonitor(port) => {
+                container
+                    .exec(&renode_monitor_command(port, &["start"]))
+                    .await?;
+            }
+            TimeControl::Qmp(port) => {
+                container
+                    .exec(&qmp_command(port, r#"{"execute":"cont"}"#))
+                    .await?;
+            }
+            TimeControl::GazeboWorld => {
+                container
+                    .exec(&gazebo_world_control_command("pause: false"))
+                    .await?;
+            }
+            TimeControl::ContainerPause => container.unpause().await?,
+        }
+        Ok(())
+    }
+
+    /// Runs a paused component for the given duration, leaving it paused
+    pub(crate) async fn step(self, container: &Container, duration: Duration) -> Result<()> {
+        match self {
+            TimeControl::RenodeMonitor(port) => {
+                let cmd = format!("emulation RunFor \"{}\"", renode_time_interval(duration));
+                container
+                    .exec(&renode_monitor_command(port, &[cmd]))
+                    .await?;
+            }
+            TimeControl::GazeboWorld => {
+                let req = format!("multi_step: {}", gazebo_iterations(duration));
+                container.exec(&gazebo_world_control_command(&req)).await?;
+            }
+            TimeControl::Qmp(_) | TimeControl::ContainerPause => {
+                self.resume(container).await?;
+                tokio::time::sleep(duration).await;
+                self.pause(container).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a duration, e.g. '500ms' or '2s', for every duration setting
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let secs = match unit {
+        "us" => value / 1_000_000.0,
+        "ms" => value / 1_000.0,
+        "s" => value,
+        "m" => value * 60.0,
+        _ => return None,
+    };
+    if secs <= 0.0 {
+        return None;
+    }
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// The world's name comes from its SDF, so it's looked up from its control service
+fn gazebo_world_control_command(req: &str) -> Vec<String> {
+    vec![
+        "sh".to_owned(),
+        "-c".to_owned(),
+        format!(
+            "gz service -s \"$(gz service -l | grep -m1 '^/world/[^/]*/control$')\" --reqtype gz.msgs.WorldControl --reptype gz.msgs.Boolean --timeout 5000 --req '{req}'"
+        ),
+    ]
+}
+
+/// Renode time intervals are formatted as 'hh:mm:ss.ffffff'
+fn renode_time_interval(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:06}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60,
+        d.subsec_micros()
+    )
+}
+
+/// The number of iterations of the default 1ms physics step, at least one
+fn gazebo_iterations(d: Duration) -> u64 {
+    ((d.as_secs_f64() * REAL_TIME_UPDATE_RATE_HZ).round() as u64).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("10"), None);
+        assert_eq!(parse_duration("99999999999999999999s"), None);
+        assert_eq!(
+            renode_time_interval(Duration::from_millis(3_723_500)),
+            "01:02:03.500000"
+        );
+        assert_eq!(gazebo_iterations(Duration::from_millis(250)), 250);
+        assert_eq!(gazebo_iterations(Duration::from_micros(10)), 1);
+    }
+}
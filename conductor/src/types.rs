@@ -18,6 +18,7 @@ name_newtype!(WorldName);
 name_newtype!(MachineName);
 name_newtype!(ConnectionName);
 name_newtype!(InterfaceName);
+name_newtype!(StorageName);
 
 // TODO - not sure what the constraints for these need to be yet
 pub type TapDevice = String;
@@ -31,6 +32,139 @@ impl InterfaceName {
     pub(crate) fn new_system_wired_network(iface_index: usize) -> Self {
         Self(format!("{}{}", Self::DEFAULT_IFACE_PREFIX, iface_index))
     }
+
+    /// The virtual CAN interface backing a CAN connection, must fit in IFNAMSIZ
+    pub(crate) fn new_system_can_bus(iface_index: usize) -> Self {
+        Self(format!("{}can{}", Self::DEFAULT_IFACE_PREFIX, iface_index))
+    }
+}
+
+/// A host device passed through to a container, in the docker `--device` notation,
+/// i.e. '<host-path>[:<guest-path>[:<cgroup-permissions>]]'
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(
+    fmt = "{}:{}:{}",
+    "host_path.display()",
+    "guest_path.display()",
+    "cgroup_permissions"
+)]
+pub struct Device {
+    pub host_path: PathBuf,
+    pub guest_path: PathBuf,
+    pub cgroup_permissions: String,
+}
+
+impl Device {
+    const DEFAULT_CGROUP_PERMISSIONS: &'static str = "rwm";
+
+    /// The device at the same path on the host and guest, with full permissions
+    pub(crate) fn new_passthrough<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        Self {
+            host_path: path.clone(),
+            guest_path: path,
+            cgroup_permissions: Self::DEFAULT_CGROUP_PERMISSIONS.to_owned(),
+        }
+    }
+}
+
+impl std::str::FromStr for Device {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Cannot construct a Device from '{s}'");
+        let mut parts = s.trim().split(':');
+        let host_path = PathBuf::from(parts.next().ok_or_else(err)?);
+        let guest_path = parts
+            .next()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| host_path.clone());
+        let cgroup_permissions = parts
+            .next()
+            .unwrap_or(Self::DEFAULT_CGROUP_PERMISSIONS)
+            .to_owned();
+        let valid_permissions = !cgroup_permissions.is_empty()
+            && cgroup_permissions
+                .chars()
+                .all(|c| matches!(c, 'r' | 'w' | 'm'));
+        if parts.next().is_some()
+            || !host_path.is_absolute()
+            || !guest_path.is_absolute()
+            || !valid_permissions
+        {
+            return Err(err());
+        }
+        Ok(Self {
+            host_path,
+            guest_path,
+            cgroup_permissions,
+        })
+    }
+}
+
+/// A Linux capability, without the 'CAP_' prefix, e.g. 'NET_ADMIN'
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, AsRef, Deref, Display, Into)]
+pub struct Capability(String);
+
+impl Capability {
+    pub(crate) fn net_admin() -> Self {
+        Self("NET_ADMIN".to_owned())
+    }
+
+    pub fn new_canonicalize<T: AsRef<str>>(s: T) -> Option<Self> {
+        let inner = s.as_ref().trim().to_ascii_uppercase();
+        let inner = inner.strip_prefix("CAP_").unwrap_or(&inner);
+        if inner.is_empty() || !inner.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            None
+        } else {
+            Some(Self(inner.to_owned()))
+        }
+    }
+}
+
+/// A port a container listens on, in the notation '<port>[/<protocol>]'.
+/// Containers use the host's network, so it's the same port on the host
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display(fmt = "{}/{}", port, protocol)]
+pub struct PublishedPort {
+    pub port: u16,
+    pub protocol: PortProtocol,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Display)]
+pub enum PortProtocol {
+    #[default]
+    #[display(fmt = "tcp")]
+    Tcp,
+    #[display(fmt = "udp")]
+    Udp,
+}
+
+impl PublishedPort {
+    pub(crate) fn new_tcp(port: u16) -> Self {
+        Self {
+            port,
+            protocol: PortProtocol::Tcp,
+        }
+    }
+}
+
+impl std::str::FromStr for PublishedPort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Cannot construct a PublishedPort from '{s}'");
+        let (port, protocol) = match s.trim().split_once('/') {
+            None => (s.trim(), PortProtocol::Tcp),
+            Some((port, "tcp")) => (port, PortProtocol::Tcp),
+            Some((port, "udp")) => (port, PortProtocol::Udp),
+            Some(_) => return Err(err()),
+        };
+        Ok(Self {
+            port: port.parse().map_err(|_| err())?,
+            protocol,
+        })
+    }
 }
 
 // TODO - this will probably need to change
@@ -93,6 +227,19 @@ impl ProviderKind {
             Container => "container",
         }
     }
+
+    pub fn is_world(self) -> bool {
+        matches!(self, ProviderKind::Gazebo)
+    }
+
+    /// Machines of this provider can't run without a bin
+    pub fn requires_machine_bin(self) -> bool {
+        use ProviderKind::*;
+        match self {
+            Gazebo | Container => false,
+            Renode | Qemu => true,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
@@ -104,6 +251,14 @@ pub enum ConnectionKind {
     Gpio,
     #[display(fmt = "{}", "self.as_str()")]
     Network,
+    #[display(fmt = "{}", "self.as_str()")]
+    WirelessNetwork,
+    #[display(fmt = "{}", "self.as_str()")]
+    Can,
+    #[display(fmt = "{}", "self.as_str()")]
+    I2c,
+    #[display(fmt = "{}", "self.as_str()")]
+    Spi,
 }
 
 impl ConnectionKind {
@@ -115,6 +270,10 @@ impl ConnectionKind {
             Uart => true,
             Gpio => false,
             Network => true,
+            WirelessNetwork => true,
+            Can => true,
+            I2c => false,
+            Spi => false,
         }
     }
 
@@ -126,15 +285,39 @@ impl ConnectionKind {
             Uart => false,
             Gpio => true,
             Network => false,
+            WirelessNetwork => true,
+            Can => false,
+            // TODO - until there's cross-container bridging for these
+            I2c => true,
+            Spi => true,
         }
     }
 
+    /// This connection kind can only be provided by Renode machines
+    pub fn is_restricted_to_renode(self) -> bool {
+        use ConnectionKind::*;
+        match self {
+            Uart | Gpio | Network | Can => false,
+            WirelessNetwork | I2c | Spi => true,
+        }
+    }
+
+    /// This connection kind is a bus with controller and target connectors
+    pub fn is_controller_target_bus(self) -> bool {
+        use ConnectionKind::*;
+        matches!(self, I2c | Spi)
+    }
+
     pub fn as_str(self) -> &'static str {
         use ConnectionKind::*;
         match self {
             Uart => "uart",
             Gpio => "gpio",
             Network => "network",
+            WirelessNetwork => "wireless-network",
+            Can => "can",
+            I2c => "i2c",
+            Spi => "spi",
         }
     }
 }
@@ -289,4 +472,53 @@ mod tests {
             Some(ComponentName("foo____bar".to_owned()))
         );
     }
+
+    #[test]
+    fn devices() {
+        assert_eq!(
+            "/dev/ttyUSB0".parse::<Device>(),
+            Ok(Device::new_passthrough("/dev/ttyUSB0"))
+        );
+        assert_eq!(
+            "/dev/ttyUSB0:/dev/ttyS1:rw".parse::<Device>(),
+            Ok(Device {
+                host_path: "/dev/ttyUSB0".into(),
+                guest_path: "/dev/ttyS1".into(),
+                cgroup_permissions: "rw".to_owned(),
+            })
+        );
+        assert!("dev/ttyUSB0".parse::<Device>().is_err());
+        assert!("/dev/ttyUSB0:/dev/ttyS1:rwx".parse::<Device>().is_err());
+    }
+
+    #[test]
+    fn published_ports() {
+        assert_eq!(
+            "1234".parse::<PublishedPort>(),
+            Ok(PublishedPort::new_tcp(1234))
+        );
+        assert_eq!(
+            "80/udp".parse::<PublishedPort>(),
+            Ok(PublishedPort {
+                port: 80,
+                protocol: PortProtocol::Udp,
+            })
+        );
+        assert_eq!(
+            PublishedPort::new_tcp(3333).to_string(),
+            "3333/tcp".to_owned()
+        );
+        assert!("8080:80".parse::<PublishedPort>().is_err());
+        assert!("80/icmp".parse::<PublishedPort>().is_err());
+        assert!("70000".parse::<PublishedPort>().is_err());
+    }
+
+    #[test]
+    fn capabilities() {
+        assert_eq!(
+            Capability::new_canonicalize("cap_sys_ptrace"),
+            Some(Capability("SYS_PTRACE".to_owned()))
+        );
+        assert_eq!(Capability::new_canonicalize("CAP_"), None);
+    }
 }